@@ -0,0 +1,97 @@
+// Stand-in for sldl that replays a recorded session, used by the integration
+// tests through the `sldl.path` setting. Takes sldl's arguments plus
+// `--fake-script <file>` naming the session to replay.
+//
+// A script has one step per line:
+//   out <text>    print a line on stdout
+//   err <text>    print a line on stderr
+//   sleep <ms>    wait, e.g. between tracks or until the job is canceled
+//   mkdir <path>  create a folder under the --path folder
+//   touch <path>  create an empty file under the --path folder
+//   config        print the --config file on stdout
+//   args          print each argument on stdout as "arg <argument>"
+//   exit <code>   stop with the exit code
+// A step prefixed with `album ` only runs when the config file turns on
+// album mode, one prefixed with `tracks ` only when it doesn't. Blank lines
+// and lines starting with # are skipped. A script without an exit step exits
+// with 0.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::{env, fs, process, thread};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|index| args.get(index + 1))
+            .cloned()
+    };
+
+    let script = match option("--fake-script") {
+        Some(script) => script,
+        None => {
+            eprintln!("fake_sldl: no --fake-script given");
+            process::exit(2);
+        }
+    };
+    let script = match fs::read_to_string(&script) {
+        Ok(script) => script,
+        Err(e) => {
+            eprintln!("fake_sldl: failed to read {}: {}", script, e);
+            process::exit(2);
+        }
+    };
+    let root = PathBuf::from(option("--path").unwrap_or_else(|| ".".to_string()));
+    let album_mode = option("--config")
+        .and_then(|config| fs::read_to_string(config).ok())
+        .map_or(false, |config| config.lines().any(|line| line.trim() == "album = true"));
+
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim_end();
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let line = match line.split_once(' ') {
+            Some(("album", step)) if album_mode => step,
+            Some(("tracks", step)) if !album_mode => step,
+            Some(("album" | "tracks", _)) => continue,
+            _ => line,
+        };
+
+        let (step, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let result = match step {
+            "out" => writeln!(std::io::stdout(), "{}", rest).and_then(|_| std::io::stdout().flush()),
+            "err" => writeln!(std::io::stderr(), "{}", rest),
+            "sleep" => {
+                thread::sleep(Duration::from_millis(rest.trim().parse().unwrap_or(0)));
+                Ok(())
+            }
+            "mkdir" => fs::create_dir_all(root.join(rest)),
+            "touch" => {
+                let path = root.join(rest);
+                path.parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(&path, b""))
+            }
+            "config" => match option("--config") {
+                Some(config) => fs::read_to_string(config).map(|config| print!("{}", config)),
+                None => Err(std::io::Error::other("no --config given")),
+            },
+            "args" => args.iter().try_for_each(|arg| writeln!(std::io::stdout(), "arg {}", arg)),
+            "exit" => process::exit(rest.trim().parse().unwrap_or(1)),
+            _ => {
+                eprintln!("fake_sldl: unknown step on line {}: {}", number + 1, line);
+                process::exit(2);
+            }
+        };
+
+        if let Err(e) = result {
+            eprintln!("fake_sldl: line {} failed: {}", number + 1, e);
+            process::exit(2);
+        }
+    }
+}
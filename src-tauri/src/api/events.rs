@@ -0,0 +1,59 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use tauri::{AppHandle, Listener};
+
+// Download events forwarded to Server-Sent Events subscribers
+const FORWARDED_EVENTS: &[&str] = &[
+    "download:started",
+    "download:progress",
+    "download:completed",
+    "download:failed",
+    "download:canceled",
+    "download:paused",
+    "downloads:cleared",
+];
+
+// An event name with its JSON payload
+pub type ApiEvent = (String, String);
+
+static SUBSCRIBERS: once_cell::sync::Lazy<Mutex<Vec<Sender<ApiEvent>>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Vec::new()));
+
+static LISTENING: AtomicBool = AtomicBool::new(false);
+
+// Listen for download events on the app once, forwarding them to subscribers
+pub fn register_listeners(app_handle: &AppHandle) {
+    if LISTENING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    for name in FORWARDED_EVENTS {
+        app_handle.listen_any(*name, move |event| {
+            broadcast(name, event.payload());
+        });
+    }
+}
+
+// Subscribe to download events
+pub fn subscribe() -> Receiver<ApiEvent> {
+    let (tx, rx) = mpsc::channel();
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.push(tx);
+    }
+    rx
+}
+
+// Send an event to every subscriber, dropping those that have gone away
+fn broadcast(name: &str, payload: &str) {
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.retain(|tx| tx.send((name.to_string(), payload.to_string())).is_ok());
+    }
+}
+
+// Disconnect all subscribers so their streams end
+pub fn close_all() {
+    if let Ok(mut subscribers) = SUBSCRIBERS.lock() {
+        subscribers.clear();
+    }
+}
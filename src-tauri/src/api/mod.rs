@@ -0,0 +1,352 @@
+use crate::commands;
+use crate::downloads::DownloadManagerState;
+use crate::error::SoulsharkError;
+use crate::settings::ApiSettings;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+// A running API server
+struct ApiServer {
+    server: Arc<Server>,
+    port: u16,
+    token: String,
+}
+
+// Global server state
+static API_SERVER: once_cell::sync::Lazy<Mutex<Option<ApiServer>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+// How often an idle event stream sends a keep-alive comment
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// Threads answering requests, and how many requests may wait for one
+const WORKERS: usize = 4;
+const QUEUE_SIZE: usize = 32;
+
+// Event streams stay open, so each gets a thread of its own, up to this many
+const MAX_EVENT_STREAMS: usize = 8;
+static EVENT_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+// Largest request body accepted
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+// sldl options a download request may set. Paths, credentials and the config
+// file are the app's to choose.
+const ALLOWED_OPTIONS: &[&str] = &["number", "offset", "reverse", "format", "pref-format", "min-bitrate", "max-bitrate"];
+
+// Body of a POST /api/downloads request, mirroring `execute_sldl`
+#[derive(Debug, Deserialize)]
+struct DownloadRequest {
+    query: String,
+    #[serde(default)]
+    options: HashMap<String, String>,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    #[serde(default)]
+    account_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct DownloadCreated {
+    id: String,
+}
+
+// Generate a random token for authenticating API requests
+pub fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+// Start, stop or restart the server to match the settings
+pub fn apply_settings(app_handle: &AppHandle, settings: &ApiSettings) -> Result<(), String> {
+    if !settings.enabled {
+        stop();
+        return Ok(());
+    }
+
+    {
+        let guard = API_SERVER.lock().unwrap();
+        if let Some(server) = guard.as_ref() {
+            if server.port == settings.port && server.token == settings.token {
+                return Ok(());
+            }
+        }
+    }
+
+    stop();
+    start(app_handle, settings)
+}
+
+// Start the API server on 127.0.0.1
+pub fn start(app_handle: &AppHandle, settings: &ApiSettings) -> Result<(), String> {
+    if settings.token.is_empty() {
+        return Err("API token is not set".to_string());
+    }
+
+    let server = Server::http(("127.0.0.1", settings.port))
+        .map_err(|e| format!("Failed to start API server: {}", e))?;
+    let server = Arc::new(server);
+
+    events::register_listeners(app_handle);
+
+    {
+        let mut guard = API_SERVER.lock().unwrap();
+        *guard = Some(ApiServer {
+            server: server.clone(),
+            port: settings.port,
+            token: settings.token.clone(),
+        });
+    }
+
+    let app_handle = app_handle.clone();
+    let token = settings.token.clone();
+    let port = settings.port;
+
+    thread::spawn(move || {
+        tracing::info!("API server started on http://127.0.0.1:{}", port);
+
+        // A fixed pool of workers takes the requests; when it falls behind,
+        // new requests are turned away instead of piling up
+        let (tx, rx) = mpsc::sync_channel::<Request>(QUEUE_SIZE);
+        let rx = Arc::new(Mutex::new(rx));
+        for _ in 0..WORKERS {
+            let rx = rx.clone();
+            let app_handle = app_handle.clone();
+            let token = token.clone();
+            thread::spawn(move || loop {
+                let request = match rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break,
+                };
+                match request {
+                    Ok(request) => handle_request(&app_handle, &token, request),
+                    Err(_) => break,
+                }
+            });
+        }
+
+        for request in server.incoming_requests() {
+            if let Err(TrySendError::Full(request)) = tx.try_send(request) {
+                let _ = request.respond(error_response(503, "Too many requests"));
+            }
+        }
+
+        tracing::info!("API server stopped");
+    });
+
+    Ok(())
+}
+
+// Stop the API server and close any open event streams
+pub fn stop() {
+    let mut guard = API_SERVER.lock().unwrap();
+    if let Some(api_server) = guard.take() {
+        api_server.server.unblock();
+        events::close_all();
+        tracing::info!("API server stopping...");
+    }
+}
+
+fn handle_request(app_handle: &AppHandle, token: &str, mut request: Request) {
+    let url = match Url::parse(&format!("http://127.0.0.1{}", request.url())) {
+        Ok(url) => url,
+        Err(e) => {
+            let _ = request.respond(error_response(400, &format!("Invalid URL: {}", e)));
+            return;
+        }
+    };
+
+    let segments: Vec<String> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).map(String::from).collect())
+        .unwrap_or_default();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let method = request.method().clone();
+
+    // EventSource can't set headers, so only the event stream takes the
+    // token from the query
+    let is_event_stream = method == Method::Get && segments.as_slice() == ["api", "events"];
+    if !is_authorized(&request, &url, token, is_event_stream) {
+        let _ = request.respond(error_response(401, "Missing or invalid API token"));
+        return;
+    }
+
+    let state = app_handle.state::<DownloadManagerState>();
+
+    let response = match (&method, segments.as_slice()) {
+        (Method::Get, ["api", "events"]) => {
+            if EVENT_STREAMS.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_STREAMS {
+                EVENT_STREAMS.fetch_sub(1, Ordering::SeqCst);
+                let _ = request.respond(error_response(503, "Too many event streams"));
+                return;
+            }
+            thread::spawn(move || {
+                stream_events(request);
+                EVENT_STREAMS.fetch_sub(1, Ordering::SeqCst);
+            });
+            return;
+        }
+        (Method::Get, ["api", "downloads"]) => match state.0.lock() {
+            Ok(download_manager) => json_response(200, &download_manager.get_all_downloads()),
+            Err(e) => error_response(500, &e.to_string()),
+        },
+        (Method::Get, ["api", "downloads", id]) => match state.0.lock() {
+            Ok(download_manager) => match download_manager.get_download(id) {
+                Some(download) => json_response(200, download),
+                None => error_response(404, &format!("Download with id {} not found", id)),
+            },
+            Err(e) => error_response(500, &e.to_string()),
+        },
+        (Method::Post, ["api", "downloads"]) => {
+            if request.body_length().map_or(false, |length| length > MAX_BODY_BYTES) {
+                let _ = request.respond(error_response(413, "Request body too large"));
+                return;
+            }
+            let mut body = String::new();
+            if let Err(e) = request.as_reader().take(MAX_BODY_BYTES as u64 + 1).read_to_string(&mut body) {
+                let _ = request.respond(error_response(400, &format!("Failed to read body: {}", e)));
+                return;
+            }
+            if body.len() > MAX_BODY_BYTES {
+                let _ = request.respond(error_response(413, "Request body too large"));
+                return;
+            }
+
+            match serde_json::from_str::<DownloadRequest>(&body) {
+                Ok(req) if !req.options.keys().all(|key| ALLOWED_OPTIONS.contains(&key.as_str())) => {
+                    error_response(
+                        400,
+                        &format!("Unsupported option, allowed options are {}", ALLOWED_OPTIONS.join(", ")),
+                    )
+                }
+                Ok(req) => {
+                    let result = tauri::async_runtime::block_on(commands::sldl::start_download(
+                        app_handle,
+                        &state,
+                        req.query,
+                        req.options,
+                        req.title,
+                        req.artist,
+                        req.album,
+                        req.account_id,
+                    ));
+                    match result {
+                        Ok(id) => json_response(201, &DownloadCreated { id }),
+                        Err(e) => command_error_response(&e),
+                    }
+                }
+                Err(e) => error_response(400, &format!("Invalid request body: {}", e)),
+            }
+        }
+        (Method::Post, ["api", "downloads", "clear"]) => {
+            match commands::downloads::clear_completed(app_handle, &state) {
+                Ok(count) => json_response(200, &json!({ "cleared": count })),
+                Err(e) => command_error_response(&e),
+            }
+        }
+        (Method::Post, ["api", "downloads", id, "cancel"]) => {
+            match commands::downloads::cancel(app_handle, &state, id) {
+                Ok(()) => json_response(200, &json!({ "id": id })),
+                Err(e) => command_error_response(&e),
+            }
+        }
+        _ => error_response(404, "Not Found"),
+    };
+
+    let _ = request.respond(response);
+}
+
+// Accept the token as a bearer token, or as a `token` query parameter where
+// `allow_query` is set
+fn is_authorized(request: &Request, url: &Url, token: &str, allow_query: bool) -> bool {
+    let from_header = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer ").map(str::to_string));
+
+    let from_query = url
+        .query_pairs()
+        .find(|(key, _)| allow_query && key == "token")
+        .map(|(_, value)| value.into_owned());
+
+    from_header
+        .or(from_query)
+        .map_or(false, |candidate| constant_time_eq(candidate.as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Hold the connection open and write download events as they happen
+fn stream_events(request: Request) {
+    let rx = events::subscribe();
+    let mut writer = request.into_writer();
+
+    let head = "HTTP/1.1 200 OK\r\n\
+                Content-Type: text/event-stream\r\n\
+                Cache-Control: no-cache\r\n\
+                Connection: keep-alive\r\n\r\n";
+    if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+        return;
+    }
+
+    loop {
+        let chunk = match rx.recv_timeout(KEEP_ALIVE_INTERVAL) {
+            Ok((event, payload)) => format!("event: {}\ndata: {}\n\n", event, payload),
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        if writer.write_all(chunk.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn json_response<T: Serialize + ?Sized>(status: u16, body: &T) -> Response<Cursor<Vec<u8>>> {
+    let data = serde_json::to_vec(body).unwrap_or_default();
+    Response::from_data(data)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error_response(status: u16, message: &str) -> Response<Cursor<Vec<u8>>> {
+    json_response(status, &json!({ "error": message }))
+}
+
+// Error from a command, with its code and a matching status
+fn command_error_response(error: &SoulsharkError) -> Response<Cursor<Vec<u8>>> {
+    let status = match error {
+        SoulsharkError::NotFound(_) => 404,
+        SoulsharkError::Validation(_) => 400,
+        _ => 500,
+    };
+    json_response(status, &json!({ "error": error.message(), "code": error.code() }))
+}
+
+// Module exports
+pub mod events;
@@ -0,0 +1,366 @@
+use crate::downloads::{history, peers::{self, PeerTracker}, Download, DownloadManager, DownloadStatus};
+use crate::logging;
+use crate::settings::{self, keystore::{KeyStorageKind, PassphraseKeyStore}, AppSettings, Credentials};
+use crate::sldl::{self, parser::{SldlLine, SldlParser}, version::Compatibility};
+use crate::spotify::token;
+use clap::{error::ErrorKind, Parser, Subcommand};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+// Set once the sldl version has been checked
+static SLDL_CHECKED: once_cell::sync::OnceCell<()> = once_cell::sync::OnceCell::new();
+
+// Environment variable holding the passphrase for protected credentials
+const PASSPHRASE_ENV: &str = "SOULSHARK_PASSPHRASE";
+
+// Exit codes reported to the calling shell
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_FAILED_TRACKS: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+// Subcommands that switch the binary into headless mode
+const CLI_COMMANDS: &[&str] = &["download", "sync", "history", "help"];
+
+#[derive(Parser)]
+#[command(name = "soulshark", version, about = "Run SoulShark downloads without the GUI")]
+struct Cli {
+    /// App data directory holding settings.json
+    #[arg(long, global = true, env = "SOULSHARK_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Echo raw sldl output
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Spotify account (user ID) to use instead of the active one
+    #[arg(long, global = true)]
+    account: Option<String>,
+
+    #[command(subcommand)]
+    command: CliCommand,
+}
+
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Download a Spotify URL or a search query
+    Download {
+        query: String,
+
+        /// Title to record for the download
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Extra sldl option as key=value (passed as --key value)
+        #[arg(long = "option", value_parser = parse_option)]
+        options: Vec<(String, String)>,
+    },
+    /// Download Liked Songs, or the given playlists, skipping what is already there
+    Sync {
+        playlists: Vec<String>,
+    },
+    /// Show recent downloads
+    History {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+// Options the app sets itself, credentials among them, can't be overridden
+fn parse_option(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected key=value, got '{}'", value))?;
+    if sldl::config::is_managed(key) {
+        return Err(format!("'{}' is set from the settings and credentials", key));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+// Check whether the process was started with a CLI subcommand, global flags
+// like --data-dir coming first or not. A subcommand with bad arguments
+// still counts, so clap reports the mistake instead of the window opening.
+pub fn is_cli_invocation() -> bool {
+    match Cli::try_parse() {
+        Ok(_) => true,
+        Err(e) if matches!(e.kind(), ErrorKind::DisplayHelp | ErrorKind::DisplayVersion) => true,
+        Err(_) => std::env::args().skip(1).any(|arg| CLI_COMMANDS.contains(&arg.as_str())),
+    }
+}
+
+// Run the CLI and return the process exit code
+pub fn run() -> i32 {
+    let cli = Cli::parse();
+
+    let data_dir = match cli.data_dir.clone().or_else(settings::default_app_data_dir) {
+        Some(dir) => dir,
+        None => {
+            eprintln!("Could not determine the app data directory, pass --data-dir");
+            return EXIT_ERROR;
+        }
+    };
+
+    // Headless runs log to the app's log files too, without echoing to the
+    // terminal
+    let log_level = settings::store::load_settings_from_dir(&data_dir)
+        .map(|settings| settings.logging.level)
+        .unwrap_or_else(|_| "info".to_string());
+    if let Err(e) = logging::init(&data_dir, &log_level, false) {
+        eprintln!("Warning: {}", e);
+    }
+
+    match cli.command {
+        CliCommand::Download { query, title, options } => {
+            let options: HashMap<String, String> = options.into_iter().collect();
+            exit_code(run_download(&data_dir, &query, title, &options, cli.account.as_deref(), cli.verbose))
+        }
+        CliCommand::Sync { playlists } => {
+            let playlists = if playlists.is_empty() {
+                vec!["spotify-likes".to_string()]
+            } else {
+                playlists
+            };
+
+            let mut code = EXIT_OK;
+            for playlist in playlists {
+                let options = HashMap::new();
+                code = code.max(exit_code(run_download(
+                    &data_dir,
+                    &playlist,
+                    None,
+                    &options,
+                    cli.account.as_deref(),
+                    cli.verbose,
+                )));
+            }
+            code
+        }
+        CliCommand::History { limit } => match history::load_history(&data_dir) {
+            Ok(downloads) => {
+                for download in downloads.iter().rev().take(limit) {
+                    println!("{}", format_history_entry(download));
+                }
+                EXIT_OK
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                EXIT_ERROR
+            }
+        },
+    }
+}
+
+fn exit_code(result: Result<Download, String>) -> i32 {
+    match result {
+        Ok(download) if download.has_failures() => EXIT_FAILED_TRACKS,
+        Ok(_) => EXIT_OK,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            EXIT_ERROR
+        }
+    }
+}
+
+// Probe the binary once per process, however many jobs a run has. Still
+// run an unsupported build, but say why progress may be missing.
+fn check_sldl_version(sidecar: &Path) {
+    SLDL_CHECKED.get_or_init(|| {
+        let info = sldl::version::probe(sidecar, None);
+        match info.compatibility {
+            Compatibility::Supported => {}
+            Compatibility::Unsupported => eprintln!(
+                "Warning: sldl {} is not supported (supported: {}), progress may not be reported",
+                info.version.as_deref().unwrap_or_default(),
+                info.supported
+            ),
+            Compatibility::Unknown => match &info.error {
+                Some(error) => eprintln!("Warning: could not detect the sldl version: {}", error),
+                None => eprintln!("Warning: could not detect the sldl version"),
+            },
+        }
+    });
+}
+
+// Run a single sldl job to completion, printing progress as it goes
+fn run_download(
+    data_dir: &Path,
+    query: &str,
+    title: Option<String>,
+    options: &HashMap<String, String>,
+    account_id: Option<&str>,
+    verbose: bool,
+) -> Result<Download, String> {
+    let settings = settings::store::load_settings_from_dir(data_dir)?;
+    if settings.security.key_storage == KeyStorageKind::Passphrase {
+        unlock_credentials(data_dir)?;
+    }
+    let mut credentials = settings::store::load_credentials_from_dir(data_dir)?;
+
+    let query = &sldl::normalize_query(query);
+    if sldl::uses_spotify(query) {
+        refresh_spotify_token(data_dir, &settings, &mut credentials, account_id)?;
+        credentials = credentials.for_spotify_account(account_id)?;
+    }
+    let is_playlist = sldl::is_playlist_query(query);
+    let title = title.unwrap_or_else(|| sldl::default_title(query, is_playlist));
+
+    let mut download_manager = DownloadManager::new();
+    let mut download = Download::new(title, None, None, query.to_string(), is_playlist);
+    download.account_id = account_id.map(str::to_string);
+    let download_id = download_manager.add_download(download);
+    let span = tracing::info_span!("download", id = %download_id);
+    let _span = span.enter();
+
+    let banned_peers = peers::banned_peers(data_dir, &settings.peers);
+    let contents = sldl::config::render(&settings, &credentials, query, None, &banned_peers);
+    let config_path = sldl::config::write(data_dir, &download_id, &contents)?;
+    let args = sldl::build_args(query, options, &settings, &config_path);
+    let sidecar = sldl::sidecar_path(&settings)?;
+
+    check_sldl_version(&sidecar);
+
+    let mut child = Command::new(&sidecar)
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            sldl::config::remove(&config_path);
+            format!("Failed to spawn sldl command: {}", e)
+        })?;
+
+    let stdout = child.stdout.take().ok_or_else(|| "Failed to capture sldl stdout".to_string())?;
+    let stderr = child.stderr.take().ok_or_else(|| "Failed to capture sldl stderr".to_string())?;
+
+    // Forward stderr as it arrives
+    let stderr_span = span.clone();
+    let stderr_thread = thread::spawn(move || {
+        let _span = stderr_span.entered();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            tracing::warn!(stream = "stderr", "{}", line);
+            eprintln!("sldl stderr: {}", line);
+        }
+    });
+
+    println!("Starting: {}", query);
+
+    let parser = SldlParser::new();
+    let mut tracker = PeerTracker::default();
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        tracing::debug!(stream = "stdout", "{}", line);
+        if verbose {
+            println!("  {}", line);
+        }
+
+        if let Some(download) = download_manager.get_download_mut(&download_id) {
+            download.add_console_log(line.clone());
+
+            let parsed = parser.parse(&line);
+            tracker.observe(&parsed);
+            if download.apply_sldl_line(&parsed).is_some() {
+                print_progress(download, &parsed);
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for sldl: {}", e))?;
+    let _ = stderr_thread.join();
+    sldl::config::remove(&config_path);
+
+    let download = download_manager
+        .get_download_mut(&download_id)
+        .ok_or_else(|| format!("Download with id {} not found", download_id))?;
+    download.finish(status.success());
+
+    println!("{}", format_history_entry(download));
+
+    if let Err(e) = history::record_download(data_dir, download) {
+        eprintln!("Warning: failed to record download history: {}", e);
+    }
+    if let Err(e) = peers::record_transfers(data_dir, tracker.transfers(), Some(download)) {
+        eprintln!("Warning: failed to record peer stats: {}", e);
+    }
+
+    Ok(download.clone())
+}
+
+// Refresh the account's access token if it is about to expire, as the app
+// does before a Spotify download, and save it for the next run
+fn refresh_spotify_token(
+    data_dir: &Path,
+    settings: &AppSettings,
+    credentials: &mut Credentials,
+    account_id: Option<&str>,
+) -> Result<(), String> {
+    let client_secret = credentials.spotify_client_secret.clone();
+    let account_id = match credentials.spotify_account(account_id) {
+        Some(account) if token::needs_refresh(account) => account.id.clone(),
+        _ => return Ok(()),
+    };
+    let account = credentials
+        .spotify_account_mut(&account_id)
+        .ok_or_else(|| format!("Unknown Spotify account: {}", account_id))?;
+
+    tauri::async_runtime::block_on(token::refresh_account(settings, client_secret.as_deref(), account))
+        .map_err(|e| format!("Failed to refresh the Spotify token: {}", e))?;
+    settings::store::save_credentials_to_dir(data_dir, credentials.clone()).map_err(String::from)
+}
+
+// Unlock passphrase-protected credentials with the passphrase from the environment
+fn unlock_credentials(data_dir: &Path) -> Result<(), String> {
+    let store = PassphraseKeyStore::new(data_dir);
+    if !store.is_locked() {
+        return Ok(());
+    }
+
+    let passphrase = std::env::var(PASSPHRASE_ENV)
+        .map_err(|_| format!("Credentials are locked, set {} to unlock them", PASSPHRASE_ENV))?;
+    store.unlock(&passphrase)
+}
+
+fn print_progress(download: &Download, line: &SldlLine) {
+    let counts = match (download.completed_tracks, download.failed_tracks, download.total_tracks) {
+        (Some(completed), Some(failed), Some(total)) => format!("[{}/{}] ", completed + failed, total),
+        _ => String::new(),
+    };
+
+    match line {
+        SldlLine::TrackCount(count) => println!("Downloading {} tracks", count),
+        SldlLine::PlaylistName { .. } => println!("Playlist: {}", download.title),
+        SldlLine::Searching(track) => println!("{}Searching: {}", counts, track),
+        SldlLine::NotFound(track) => println!("{}Not found: {}", counts, track),
+        SldlLine::Succeeded { path, .. } => println!("{}Downloaded: {}", counts, path),
+        _ => {}
+    }
+}
+
+fn status_label(status: &DownloadStatus) -> String {
+    match status {
+        DownloadStatus::Queued => "Queued".to_string(),
+        DownloadStatus::Searching => "Searching".to_string(),
+        DownloadStatus::InProgress => "In progress".to_string(),
+        DownloadStatus::Completed => "Completed".to_string(),
+        DownloadStatus::Failed(reason) => format!("Failed ({})", reason),
+        DownloadStatus::Canceled => "Canceled".to_string(),
+        DownloadStatus::Paused(reason) => format!("Paused ({})", reason),
+    }
+}
+
+fn format_history_entry(download: &Download) -> String {
+    let started = chrono::DateTime::from_timestamp(download.started_at, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+
+    let tracks = match (download.completed_tracks, download.failed_tracks, download.total_tracks) {
+        (Some(completed), Some(failed), Some(total)) => {
+            format!(" ({} ok, {} failed of {})", completed, failed, total)
+        }
+        _ => String::new(),
+    };
+
+    format!("{}  {}  {}{}", started, status_label(&download.status), download.title, tracks)
+}
@@ -0,0 +1,74 @@
+use crate::commands::sldl::{add_download, build_download, run_download};
+use crate::downloads::album::AlbumMode;
+use crate::downloads::{Download, DownloadManagerState};
+use crate::error::SoulsharkError;
+use crate::settings::{self, SettingsState};
+use crate::spotify::api::SpotifyApi;
+use crate::spotify::link::{SpotifyLink, SpotifyLinkKind};
+use crate::spotify::token::SpotifyTokenManager;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+/// Download a Spotify album (ID or link) as a whole: sldl looks for a single
+/// peer folder with at least the Spotify tracklist, and the result is checked
+/// for missing tracks and mixed formats or bitrates. When no folder has the
+/// album, the tracks are downloaded one by one if `allow_fallback` (or the
+/// `sldl.config.album.fallback_to_tracks` setting) allows it. Returns the
+/// download ID.
+#[tauri::command]
+pub async fn download_album<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+    album_id: String,
+    allow_fallback: Option<bool>,
+    account_id: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let album_id = match SpotifyLink::parse(&album_id) {
+        Some(SpotifyLink { kind: SpotifyLinkKind::Album, id }) => id,
+        Some(_) => return Err(SoulsharkError::Validation(format!("Not a Spotify album: {}", album_id))),
+        None => album_id,
+    };
+
+    let access_token = app_handle
+        .state::<SpotifyTokenManager>()
+        .get_valid_token(&app_handle, account_id.as_deref())
+        .await
+        .map_err(SoulsharkError::Auth)?;
+    let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+    let api = SpotifyApi::with_base_url(access_token, &settings.endpoints.spotify_api_url);
+    let album = api.get_album(&album_id).await.map_err(SoulsharkError::Network)?;
+
+    let link = SpotifyLink {
+        kind: SpotifyLinkKind::Album,
+        id: album.id.clone(),
+    };
+    let mut download = build_album_download(
+        link.url(),
+        album.name.clone(),
+        album.artists.first().map(|artist| artist.name.clone()),
+        Some(album.total_tracks),
+        allow_fallback.unwrap_or(settings.sldl.config.album.fallback_to_tracks),
+    );
+    download.account_id = account_id;
+
+    let download_id = add_download(&app_handle, &state, download)?;
+    run_download(&app_handle, &state, &download_id, HashMap::new()).await?;
+    Ok(download_id)
+}
+
+// Build an album mode download. `expected_tracks` is the length of the
+// tracklist the downloaded folder is checked against.
+pub fn build_album_download(
+    query: String,
+    title: String,
+    artist: Option<String>,
+    expected_tracks: Option<usize>,
+    allow_fallback: bool,
+) -> Download {
+    let mut download = build_download(query, Some(title.clone()), artist, Some(title));
+    if let Some(expected_tracks) = expected_tracks {
+        download.set_playlist_info(expected_tracks);
+    }
+    download.album_mode = Some(AlbumMode::new(expected_tracks, allow_fallback));
+    download
+}
@@ -0,0 +1,176 @@
+use crate::commands::sldl::{add_download, build_download, run_queued_blocking};
+use crate::downloads::{emit_download_event, Download, DownloadManagerState, DownloadStatus};
+use crate::error::SoulsharkError;
+use crate::settings::{self, SettingsState};
+use crate::spotify::api::SpotifyApi;
+use crate::spotify::discography::{self, DiscographyFilters};
+use crate::spotify::link::{SpotifyLink, SpotifyLinkKind};
+use crate::spotify::token::SpotifyTokenManager;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+// Jobs created for an artist download
+#[derive(Debug, Serialize)]
+pub struct ArtistDownload {
+    pub parent_id: String,
+    pub album_ids: Vec<String>,
+}
+
+/// Download an artist's catalogue as one album job per release, grouped under
+/// a parent job that tracks how many albums have finished. Uses the given
+/// Spotify account, or the active one.
+#[tauri::command]
+pub async fn download_artist<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+    artist_id: String,
+    filters: Option<DiscographyFilters>,
+    account_id: Option<String>,
+) -> Result<ArtistDownload, SoulsharkError> {
+    let filters = filters.unwrap_or_default();
+    let access_token = app_handle
+        .state::<SpotifyTokenManager>()
+        .get_valid_token(&app_handle, account_id.as_deref())
+        .await
+        .map_err(SoulsharkError::Auth)?;
+    let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+    let api = SpotifyApi::with_base_url(access_token, &settings.endpoints.spotify_api_url);
+
+    let artist = api.get_artist(&artist_id).await.map_err(SoulsharkError::Network)?;
+    let groups: Vec<&str> = filters.album_types.iter().map(String::as_str).collect();
+    let albums = api
+        .get_artist_albums(&artist_id, &groups)
+        .await
+        .map_err(SoulsharkError::Network)?;
+    let albums = discography::select_albums(albums, &filters);
+
+    if albums.is_empty() {
+        return Err(SoulsharkError::NotFound(format!("No releases by {} match the filters", artist.name)));
+    }
+
+    // Parent job aggregating the album jobs; it counts albums rather than tracks
+    let artist_link = SpotifyLink {
+        kind: SpotifyLinkKind::Artist,
+        id: artist_id.clone(),
+    };
+    let mut parent = Download::new(
+        format!("{} (Discography)", artist.name),
+        Some(artist.name.clone()),
+        None,
+        artist_link.url(),
+        true,
+    );
+    parent.set_playlist_info(albums.len());
+    parent.account_id = account_id.clone();
+    let parent_id = add_download(&app_handle, &state, parent)?;
+
+    let mut album_ids = Vec::with_capacity(albums.len());
+    for album in &albums {
+        let album_link = SpotifyLink {
+            kind: SpotifyLinkKind::Album,
+            id: album.id.clone(),
+        };
+        let mut download = build_download(
+            album_link.url(),
+            Some(album.name.clone()),
+            Some(artist.name.clone()),
+            Some(album.name.clone()),
+        );
+        download.parent_id = Some(parent_id.clone());
+        download.account_id = account_id.clone();
+        album_ids.push(add_download(&app_handle, &state, download)?);
+    }
+
+    // Work through the albums in the background
+    let app_handle_clone = app_handle.clone();
+    let parent_id_clone = parent_id.clone();
+    let queued_ids = album_ids.clone();
+    std::thread::spawn(move || run_album_jobs(&app_handle_clone, &parent_id_clone, &queued_ids));
+
+    Ok(ArtistDownload {
+        parent_id,
+        album_ids,
+    })
+}
+
+// Run the album jobs one at a time, rolling their results up into the parent
+fn run_album_jobs<R: Runtime>(app_handle: &AppHandle<R>, parent_id: &str, album_ids: &[String]) {
+    let state = app_handle.state::<DownloadManagerState>();
+
+    update_download(app_handle, &state, parent_id, |parent| {
+        parent.update_status(DownloadStatus::InProgress);
+        Some("download:progress")
+    });
+
+    run_queued_blocking(
+        app_handle,
+        &state,
+        album_ids,
+        || !is_canceled(&state, parent_id),
+        |_, status| {
+            update_download(app_handle, &state, parent_id, |parent| {
+                if matches!(status, Some(DownloadStatus::Completed)) {
+                    parent.increment_completed_tracks();
+                } else {
+                    parent.increment_failed_tracks();
+                }
+                Some("download:progress")
+            });
+        },
+    );
+
+    // Canceling the parent cancels the albums that haven't started
+    if is_canceled(&state, parent_id) {
+        for album_id in album_ids {
+            let queued = state
+                .0
+                .lock()
+                .ok()
+                .and_then(|manager| manager.get_download(album_id).map(|d| d.status == DownloadStatus::Queued))
+                .unwrap_or(false);
+            if queued {
+                let _ = crate::commands::downloads::cancel(app_handle, &state, album_id);
+            }
+        }
+        return;
+    }
+
+    update_download(app_handle, &state, parent_id, |parent| {
+        if parent.completed_tracks == Some(0) {
+            parent.update_status(DownloadStatus::Failed(SoulsharkError::Sidecar(
+                "All album downloads failed".to_string(),
+            )));
+            Some("download:failed")
+        } else {
+            parent.update_status(DownloadStatus::Completed);
+            parent.update_progress(1.0);
+            Some("download:completed")
+        }
+    });
+}
+
+fn is_canceled(state: &DownloadManagerState, id: &str) -> bool {
+    state
+        .0
+        .lock()
+        .ok()
+        .and_then(|manager| manager.get_download(id).map(|d| d.status == DownloadStatus::Canceled))
+        .unwrap_or(false)
+}
+
+// Apply a change to a download and emit the event it returns
+fn update_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    id: &str,
+    update: impl FnOnce(&mut Download) -> Option<&'static str>,
+) {
+    if let Ok(mut download_manager) = state.0.lock() {
+        if let Some(download) = download_manager.get_download_mut(id) {
+            if let Some(event_name) = update(download) {
+                let download_clone = download.clone();
+                emit_download_event(app_handle, event_name, &download_clone);
+            }
+        }
+    }
+}
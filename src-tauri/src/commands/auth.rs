@@ -0,0 +1,116 @@
+use crate::commands::downloads::cancel;
+use crate::commands::spotify::stop_spotify_callback_server;
+use crate::downloads::DownloadManagerState;
+use crate::error::SoulsharkError;
+use crate::settings;
+use crate::sldl;
+use crate::spotify::token::SpotifyTokenManager;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+// Store holding the PKCE verifier of a pending Spotify login
+const SPOTIFY_AUTH_STORE: &str = "spotify-auth.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthService {
+    Spotify,
+    Soulseek,
+}
+
+// Payload of the auth:changed event
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthChanged {
+    pub service: AuthService,
+    pub logged_in: bool,
+}
+
+/// Log out of Spotify or Soulseek: wipe the stored credentials and pending
+/// login state, and cancel unfinished downloads that use them. For Spotify,
+/// `account_id` unlinks just that account instead of all of them. Returns the
+/// number of canceled downloads.
+#[tauri::command]
+pub async fn logout(
+    app_handle: AppHandle,
+    state: State<'_, DownloadManagerState>,
+    service: AuthService,
+    account_id: Option<String>,
+) -> Result<usize, SoulsharkError> {
+    let mut credentials = settings::store::get_credentials(&app_handle).await?;
+
+    // Downloads of the removed account, including those that would use it as
+    // the active account
+    let removed_active = account_id.is_some() && credentials.active_spotify_account == account_id;
+    let uses_account = |download_account: &Option<String>| match &account_id {
+        Some(_) => download_account == &account_id || (removed_active && download_account.is_none()),
+        None => true,
+    };
+
+    match (service, &account_id) {
+        (AuthService::Spotify, Some(account_id)) => {
+            if !credentials.remove_spotify_account(account_id) {
+                return Err(SoulsharkError::NotFound(format!("Unknown Spotify account: {}", account_id)));
+            }
+            app_handle.state::<SpotifyTokenManager>().clear().await;
+        }
+        (AuthService::Spotify, None) => {
+            credentials.spotify_accounts.clear();
+            credentials.active_spotify_account = None;
+            credentials.spotify_access_token = None;
+            credentials.spotify_refresh_token = None;
+            credentials.spotify_token_expires_at = None;
+            app_handle.state::<SpotifyTokenManager>().clear().await;
+
+            // Drop any half-finished login
+            stop_spotify_callback_server()?;
+            let store = app_handle
+                .store(SPOTIFY_AUTH_STORE)
+                .map_err(|e| SoulsharkError::Settings(format!("Failed to load store: {}", e)))?;
+            store.clear();
+            store
+                .save()
+                .map_err(|e| SoulsharkError::Settings(format!("Failed to save store: {}", e)))?;
+        }
+        (AuthService::Soulseek, _) => {
+            credentials.soulseek_password = None;
+        }
+    }
+
+    let logged_in = service == AuthService::Spotify && !credentials.spotify_accounts.is_empty();
+    settings::store::save_credentials(&app_handle, credentials).await?;
+
+    // Every download logs in to Soulseek, only some use Spotify
+    let affected: Vec<String> = {
+        let download_manager = state.0.lock()?;
+        download_manager
+            .get_all_downloads()
+            .into_iter()
+            .filter(|download| !download.status.is_finished())
+            .filter(|download| {
+                service == AuthService::Soulseek
+                    || (sldl::uses_spotify(&download.query) && uses_account(&download.account_id))
+            })
+            .map(|download| download.id)
+            .collect()
+    };
+
+    for id in &affected {
+        if let Ok(mut download_manager) = state.0.lock() {
+            if let Some(download) = download_manager.get_download_mut(id) {
+                download.add_console_log(format!("Canceled: logged out of {:?}", service));
+            }
+        }
+        cancel(&app_handle, &state, id)?;
+    }
+
+    let _ = app_handle.emit(
+        "auth:changed",
+        AuthChanged {
+            service,
+            logged_in,
+        },
+    );
+
+    Ok(affected.len())
+}
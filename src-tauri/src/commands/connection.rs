@@ -0,0 +1,97 @@
+use crate::error::SoulsharkError;
+use crate::settings::{self, SettingsState};
+use crate::soulseek;
+use crate::spotify::api::{self, SpotifyApi};
+use crate::spotify::token::SpotifyTokenManager;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// How long to wait for the Soulseek server before giving up
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+/// Log in to the Soulseek server with the given or saved credentials
+#[tauri::command]
+pub async fn test_soulseek_login(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    username: Option<String>,
+    password: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let settings = settings::store::get_settings(state)?;
+
+    let username = non_empty(username)
+        .or_else(|| non_empty(Some(settings.soulseek.username.clone())))
+        .ok_or_else(|| SoulsharkError::Auth("Soulseek username is not set".to_string()))?;
+
+    let password = match non_empty(password) {
+        Some(password) => password,
+        None => non_empty(settings::store::get_credentials(&app_handle).await?.soulseek_password)
+            .ok_or_else(|| SoulsharkError::Auth("Soulseek password is not set".to_string()))?,
+    };
+
+    let server = settings.endpoints.soulseek_server.clone();
+    let greeting = tauri::async_runtime::spawn_blocking(move || {
+        soulseek::test_login(&server, &username, &password, LOGIN_TIMEOUT)
+    })
+    .await
+    .map_err(|e| SoulsharkError::Internal(format!("Login test failed: {}", e)))??;
+
+    Ok(if greeting.is_empty() {
+        "Logged in to Soulseek".to_string()
+    } else {
+        format!("Logged in to Soulseek: {}", greeting)
+    })
+}
+
+/// Check the Spotify client credentials and, if connected, the saved access token
+#[tauri::command]
+pub async fn test_spotify_credentials(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let settings = settings::store::get_settings(state)?;
+    let credentials = settings::store::get_credentials(&app_handle).await?;
+
+    let client_id = non_empty(client_id)
+        .or_else(|| non_empty(Some(settings.spotify.client_id.clone())))
+        .ok_or_else(|| SoulsharkError::Auth("Spotify client ID is not set".to_string()))?;
+
+    let mut results = Vec::new();
+
+    if let Some(client_secret) = non_empty(client_secret).or(non_empty(credentials.spotify_client_secret)) {
+        api::check_client_credentials(&settings.endpoints.spotify_accounts_url, &client_id, &client_secret)
+            .await
+            .map_err(SoulsharkError::Auth)?;
+        results.push("Client ID and secret are valid".to_string());
+    }
+
+    if non_empty(credentials.spotify_access_token).is_some() {
+        // Refreshes the token first if it has expired
+        let access_token = app_handle
+            .state::<SpotifyTokenManager>()
+            .get_valid_token(&app_handle, None)
+            .await
+            .map_err(SoulsharkError::Auth)?;
+        let user = SpotifyApi::with_base_url(access_token, &settings.endpoints.spotify_api_url)
+            .get_current_user()
+            .await
+            .map_err(|e| SoulsharkError::Auth(format!("Saved Spotify login is not valid, reconnect your account: {}", e)))?;
+        results.push(format!(
+            "Connected as {}",
+            user.display_name.unwrap_or(user.id)
+        ));
+    }
+
+    if results.is_empty() {
+        return Err(SoulsharkError::Validation(
+            "Nothing to test: set a client secret or connect your Spotify account".to_string(),
+        ));
+    }
+    Ok(results.join(". "))
+}
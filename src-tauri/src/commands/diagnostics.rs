@@ -0,0 +1,69 @@
+use crate::commands::sldl::{detect_sldl_version, SldlInfoState};
+use crate::downloads::{history, DownloadManagerState};
+use crate::error::SoulsharkError;
+use crate::logging::{self, diagnostics::{self, SystemInfo}};
+use crate::settings::{self, SettingsState};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+/// Bundle the logs, the settings without secrets, the sldl version and the
+/// recent downloads into a zip for bug reports. Written to `path`, or to the
+/// diagnostics folder in the app data dir; returns where it was written.
+#[tauri::command]
+pub async fn export_diagnostics(
+    app_handle: AppHandle,
+    state: State<'_, DownloadManagerState>,
+    path: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| SoulsharkError::Filesystem(format!("Failed to get app data directory: {}", e)))?;
+    let settings = settings::store::get_settings(app_handle.state::<SettingsState>())?;
+
+    // Jobs of this session, then the most recent finished ones
+    let mut downloads = state.0.lock()?.get_all_downloads();
+    let finished = history::load_history(&app_data_dir).map_err(SoulsharkError::Filesystem)?;
+    for download in finished.into_iter().rev().take(diagnostics::RECENT_DOWNLOADS) {
+        if !downloads.iter().any(|d| d.id == download.id) {
+            downloads.push(download);
+        }
+    }
+
+    let system = system_info(&app_handle).await?;
+
+    let path = path.map(PathBuf::from).unwrap_or_else(|| {
+        app_data_dir.join("diagnostics").join(format!(
+            "soulshark-diagnostics-{}.zip",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ))
+    });
+    diagnostics::write_bundle(&path, &logging::log_files(&app_data_dir), &settings, &system, &downloads)
+        .map_err(SoulsharkError::Filesystem)?;
+
+    tracing::info!(path = %path.display(), "Diagnostics exported");
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// App version, platform and the sldl binary in use with its version and
+/// whether it is supported
+#[tauri::command]
+pub async fn get_system_info(app_handle: AppHandle) -> Result<SystemInfo, SoulsharkError> {
+    system_info(&app_handle).await
+}
+
+// sldl is probed now if that hasn't happened since startup
+async fn system_info(app_handle: &AppHandle) -> Result<SystemInfo, SoulsharkError> {
+    let recorded = app_handle.state::<SldlInfoState>().0.lock()?.clone();
+    let sldl = match recorded {
+        Some(sldl) => sldl,
+        None => detect_sldl_version(app_handle).await,
+    };
+
+    Ok(SystemInfo {
+        app_version: app_handle.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        sldl,
+    })
+}
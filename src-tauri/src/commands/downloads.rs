@@ -1,10 +1,16 @@
-use crate::downloads::{Download, DownloadManagerState, DownloadStatus, emit_download_event, emit_download_message};
-use tauri::{AppHandle, State};
+use crate::commands::sldl::{run_download, stop_sldl};
+use crate::downloads::cleanup::{self, CleanupAction};
+use crate::downloads::{history, Download, DownloadManagerState, DownloadStatus, emit_download_event, emit_download_message};
+use crate::error::SoulsharkError;
+use crate::settings::SettingsState;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Runtime, State};
 
 /// Get all downloads
 #[tauri::command]
-pub async fn get_all_downloads(state: State<'_, DownloadManagerState>) -> Result<Vec<Download>, String> {
-    let downloads = state.0.lock().map_err(|e| e.to_string())?.get_all_downloads();
+pub async fn get_all_downloads(state: State<'_, DownloadManagerState>) -> Result<Vec<Download>, SoulsharkError> {
+    let downloads = state.0.lock()?.get_all_downloads();
     Ok(downloads)
 }
 
@@ -13,54 +19,139 @@ pub async fn get_all_downloads(state: State<'_, DownloadManagerState>) -> Result
 pub async fn get_download(
     id: String,
     state: State<'_, DownloadManagerState>,
-) -> Result<Option<Download>, String> {
-    let download_manager = state.0.lock().map_err(|e| e.to_string())?;
+) -> Result<Option<Download>, SoulsharkError> {
+    let download_manager = state.0.lock()?;
     let download = download_manager.get_download(&id).cloned();
     Ok(download)
 }
 
 /// Cancel a download (if possible)
 #[tauri::command]
-pub async fn cancel_download(
+pub async fn cancel_download<R: Runtime>(
     id: String,
-    app_handle: AppHandle,
+    app_handle: AppHandle<R>,
     state: State<'_, DownloadManagerState>,
-) -> Result<(), String> {
+) -> Result<(), SoulsharkError> {
+    cancel(&app_handle, &state, &id)
+}
+
+/// Resume a download paused for low disk space. sldl skips the tracks it
+/// already downloaded.
+#[tauri::command]
+pub async fn resume_download<R: Runtime>(
+    id: String,
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+) -> Result<(), SoulsharkError> {
+    {
+        let mut download_manager = state.0.lock()?;
+        let download = download_manager
+            .get_download_mut(&id)
+            .ok_or_else(|| SoulsharkError::NotFound(format!("Download with id {} not found", id)))?;
+        if !download.status.is_paused() {
+            return Err(SoulsharkError::Validation("The download is not paused".to_string()));
+        }
+        download.update_status(DownloadStatus::Queued);
+        let download_clone = download.clone();
+        emit_download_event(&app_handle, "download:progress", &download_clone);
+    }
+
+    run_download(&app_handle, &state, &id, HashMap::new()).await
+}
+
+/// Clear completed downloads from the list
+#[tauri::command]
+pub async fn clear_completed_downloads<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+) -> Result<(), SoulsharkError> {
+    clear_completed(&app_handle, &state)?;
+    Ok(())
+}
+
+// Mark a download as canceled, stop its sldl process and notify the frontend
+pub fn cancel<R: Runtime>(app_handle: &AppHandle<R>, state: &DownloadManagerState, id: &str) -> Result<(), SoulsharkError> {
     // Update download status to canceled
     {
-        let mut download_manager = state.0.lock().map_err(|e| e.to_string())?;
-        download_manager.update_download_status(&id, DownloadStatus::Canceled)?;
+        let mut download_manager = state.0.lock()?;
+        download_manager
+            .update_download_status(id, DownloadStatus::Canceled)
+            .map_err(SoulsharkError::NotFound)?;
     }
 
+    // Set before stopping sldl so its exit isn't taken as a failure
+    stop_sldl(id);
+
     // Get the updated download to emit event
     let download = {
-        let download_manager = state.0.lock().map_err(|e| e.to_string())?;
-        download_manager.get_download(&id).cloned()
+        let download_manager = state.0.lock()?;
+        download_manager.get_download(id).cloned()
     };
 
     if let Some(download) = download {
         // Emit download canceled event
-        emit_download_event(&app_handle, "download:canceled", &download);
+        emit_download_event(app_handle, "download:canceled", &download);
     }
 
     Ok(())
 }
 
-/// Clear completed downloads from the list
-#[tauri::command]
-pub async fn clear_completed_downloads(
-    app_handle: AppHandle,
-    state: State<'_, DownloadManagerState>,
-) -> Result<(), String> {
+// Clear finished downloads, returning how many were removed
+pub fn clear_completed<R: Runtime>(app_handle: &AppHandle<R>, state: &DownloadManagerState) -> Result<usize, SoulsharkError> {
     // Clear completed downloads and get the count
     let count = {
-        let mut download_manager = state.0.lock().map_err(|e| e.to_string())?;
+        let mut download_manager = state.0.lock()?;
         download_manager.clear_completed_downloads()
     };
     
     // Emit an event to notify the frontend
     let message = format!("Cleared {} completed downloads", count);
-    emit_download_message(&app_handle, "downloads:cleared", &message);
+    emit_download_message(app_handle, "downloads:cleared", &message);
     
-    Ok(())
+    Ok(count)
+}
+
+/// Clean up the folders a finished download created: list what would be
+/// moved to the trash or removed, or do it unless `dry_run` is set
+#[tauri::command]
+pub async fn cleanup_download<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+    settings_state: State<'_, SettingsState<R>>,
+    id: String,
+    dry_run: bool,
+) -> Result<Vec<CleanupAction>, SoulsharkError> {
+    let app_data_dir = crate::settings::store::app_data_dir(&app_handle)?;
+
+    // Downloads cleared from the list are still in the history
+    let download = {
+        let download_manager = state.0.lock()?;
+        download_manager.get_download(&id).cloned()
+    };
+    let download = match download {
+        Some(download) => download,
+        None => history::load_history(&app_data_dir)
+            .map_err(SoulsharkError::Filesystem)?
+            .into_iter()
+            .find(|download| download.id == id)
+            .ok_or_else(|| SoulsharkError::NotFound(format!("Download with id {} not found", id)))?,
+    };
+
+    if !download.status.is_finished() {
+        return Err(SoulsharkError::Validation("The download is still running".to_string()));
+    }
+
+    let settings = crate::settings::store::get_settings(settings_state)?;
+    if settings.soulseek.downloads_path.is_empty() {
+        return Err(SoulsharkError::Settings("No downloads path is set".to_string()));
+    }
+
+    let created_dirs: Vec<PathBuf> = download.created_dirs.iter().map(PathBuf::from).collect();
+    let actions = cleanup::plan(&created_dirs);
+    if dry_run {
+        return Ok(actions);
+    }
+
+    cleanup::execute(&app_data_dir, Path::new(&settings.soulseek.downloads_path), &id, &actions)
+        .map_err(SoulsharkError::Filesystem)
 }
@@ -1,4 +1,11 @@
+pub mod album;
+pub mod artist;
+pub mod auth;
+pub mod connection;
+pub mod diagnostics;
 pub mod downloads;
+pub mod peers;
+pub mod profiles;
 pub mod settings;
 pub mod sldl;
 pub mod spotify;
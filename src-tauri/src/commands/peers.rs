@@ -0,0 +1,80 @@
+use crate::downloads::peers::{self, PeerList, PeerStats};
+use crate::error::SoulsharkError;
+use crate::settings::{self, PeerSettings, SettingsState};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime};
+
+// A peer's stats with whether downloads currently skip it
+#[derive(Debug, Serialize)]
+pub struct PeerEntry {
+    #[serde(flatten)]
+    pub stats: PeerStats,
+    pub failure_rate: f64,
+    pub banned: bool,
+}
+
+impl PeerEntry {
+    fn new(stats: PeerStats, settings: &PeerSettings) -> Self {
+        Self {
+            failure_rate: stats.failure_rate(),
+            banned: stats.is_banned(settings),
+            stats,
+        }
+    }
+}
+
+/// List every peer with recorded transfers or a list entry, by username
+#[tauri::command]
+pub async fn list_peers<R: Runtime>(app_handle: AppHandle<R>) -> Result<Vec<PeerEntry>, SoulsharkError> {
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+    let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+
+    let peers = peers::load_peers(&app_data_dir).map_err(SoulsharkError::Filesystem)?;
+    Ok(peers
+        .into_values()
+        .map(|stats| PeerEntry::new(stats, &settings.peers))
+        .collect())
+}
+
+/// Blacklist or whitelist a peer, or take it off its list with `list: null`.
+/// Blacklisted peers are passed to sldl as banned users from the next run.
+#[tauri::command]
+pub async fn set_peer_list<R: Runtime>(
+    app_handle: AppHandle<R>,
+    username: String,
+    list: Option<PeerList>,
+) -> Result<PeerEntry, SoulsharkError> {
+    let username = check_username(&username)?;
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+    let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+
+    let stats = peers::set_list(&app_data_dir, username, list).map_err(SoulsharkError::Filesystem)?;
+    tracing::info!(peer = %username, list = ?list, "Peer list changed");
+    Ok(PeerEntry::new(stats, &settings.peers))
+}
+
+/// Forget a peer's stats and list entry
+#[tauri::command]
+pub async fn remove_peer<R: Runtime>(app_handle: AppHandle<R>, username: String) -> Result<(), SoulsharkError> {
+    let username = check_username(&username)?;
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+
+    let removed = peers::update_peers(&app_data_dir, |peers| peers.remove(username).is_some())
+        .map_err(SoulsharkError::Filesystem)?;
+    if !removed {
+        return Err(SoulsharkError::NotFound(format!("No stats for peer {}", username)));
+    }
+    Ok(())
+}
+
+// Usernames end up in the comma-separated banned-users line of sldl's config
+fn check_username(username: &str) -> Result<&str, SoulsharkError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(SoulsharkError::Validation("Enter a username".to_string()));
+    }
+    if username.contains([',', '\\', '\n', '\r']) {
+        return Err(SoulsharkError::Validation(format!("Invalid username: {}", username)));
+    }
+    Ok(username)
+}
@@ -0,0 +1,38 @@
+use crate::commands::settings::apply_services;
+use crate::error::SoulsharkError;
+use crate::settings::profiles::{self, ProfileList};
+use crate::settings::AppSettings;
+use crate::spotify::token::SpotifyTokenManager;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// List the settings profiles and the active one
+#[tauri::command]
+pub async fn list_profiles(app_handle: AppHandle) -> Result<ProfileList, SoulsharkError> {
+    profiles::list_profiles(&app_handle)
+}
+
+/// Add a profile, copying the current settings and credentials unless
+/// `copy_current` is false
+#[tauri::command]
+pub async fn create_profile(app_handle: AppHandle, name: String, copy_current: Option<bool>) -> Result<ProfileList, SoulsharkError> {
+    profiles::create_profile(&app_handle, &name, copy_current.unwrap_or(true))?;
+    profiles::list_profiles(&app_handle)
+}
+
+/// Make another profile active and return its settings
+#[tauri::command]
+pub async fn switch_profile(app_handle: AppHandle, name: String) -> Result<AppSettings, SoulsharkError> {
+    let settings = profiles::switch_profile(&app_handle, &name)?;
+    app_handle.state::<SpotifyTokenManager>().clear().await;
+    apply_services(&app_handle, &settings);
+
+    let _ = app_handle.emit("profile:switched", name.trim());
+    Ok(settings)
+}
+
+/// Delete an inactive profile
+#[tauri::command]
+pub async fn delete_profile(app_handle: AppHandle, name: String) -> Result<ProfileList, SoulsharkError> {
+    profiles::delete_profile(&app_handle, &name)?;
+    profiles::list_profiles(&app_handle)
+}
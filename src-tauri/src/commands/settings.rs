@@ -1,28 +1,294 @@
+use crate::commands::sldl;
+use crate::error::SoulsharkError;
+use crate::logging;
+use crate::naming::{self, NamingOptions, TrackTags};
+use crate::{api, watch};
+use crate::settings::export;
+use crate::settings::keystore::{self, KeyStorageKind, PassphraseKeyStore};
+use crate::settings::validation::{self, FieldError};
 use crate::settings::{self, AppSettings, Credentials, SettingsState};
-use tauri::{AppHandle, State};
+use crate::spotify::token::SpotifyTokenManager;
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[derive(Debug, Serialize)]
+pub struct KeyStorageStatus {
+    pub kind: KeyStorageKind,
+    pub locked: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub settings: AppSettings,
+    pub imported_secrets: bool,
+}
 
 #[tauri::command]
-pub async fn get_settings(state: State<'_, SettingsState>) -> Result<AppSettings, String> {
+pub async fn get_settings(state: State<'_, SettingsState>) -> Result<AppSettings, SoulsharkError> {
     settings::store::get_settings(state)
 }
 
 #[tauri::command]
 pub async fn save_settings(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    mut settings: AppSettings,
+) -> Result<(), SoulsharkError> {
+    let errors = validation::validate(&settings);
+    if !errors.is_empty() {
+        return Err(invalid_settings(&errors));
+    }
+
+    // Enabling the API without a token generates one
+    if settings.api.enabled && settings.api.token.is_empty() {
+        settings.api.token = api::generate_token();
+    }
+
+    // A different sldl binary has to be checked again
+    let sldl_changed = settings::store::get_settings(state.clone())
+        .map_or(true, |previous| previous.sldl.path != settings.sldl.path);
+
+    let api_settings = settings.api.clone();
+    let watch_settings = settings.watch.clone();
+    let log_level = settings.logging.level.clone();
+    settings::store::save_settings(state, settings)?;
+
+    logging::set_level(&log_level).map_err(SoulsharkError::Internal)?;
+    if sldl_changed {
+        sldl::detect_sldl_version(&app_handle).await;
+    }
+
+    api::apply_settings(&app_handle, &api_settings).map_err(SoulsharkError::Network)?;
+    watch::apply_settings(&app_handle, &watch_settings).map_err(SoulsharkError::Filesystem)
+}
+
+// Validation error listing every invalid field
+fn invalid_settings(errors: &[FieldError]) -> SoulsharkError {
+    let messages: Vec<String> = errors
+        .iter()
+        .map(|error| format!("{}: {}", error.field, error.message))
+        .collect();
+    SoulsharkError::Validation(format!("Invalid settings: {}", messages.join("; ")))
+}
+
+// Restart the API server and the watch folder after settings were replaced
+// wholesale (import, profile switch); failures are reported but not fatal
+pub fn apply_services(app_handle: &AppHandle, settings: &AppSettings) {
+    if let Err(e) = logging::set_level(&settings.logging.level) {
+        tracing::error!("{}", e);
+    }
+    if let Err(e) = api::apply_settings(app_handle, &settings.api) {
+        tracing::error!("Failed to apply API settings: {}", e);
+    }
+    if let Err(e) = watch::apply_settings(app_handle, &settings.watch) {
+        tracing::error!("Failed to apply watch folder settings: {}", e);
+    }
+
+    // The profile may use another sldl binary
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        sldl::detect_sldl_version(&app_handle).await;
+    });
+}
+
+/// Check settings without saving them, returning every invalid field
+#[tauri::command]
+pub async fn validate_settings(settings: AppSettings) -> Result<Vec<FieldError>, SoulsharkError> {
+    Ok(validation::validate(&settings))
+}
+
+/// Render a name format for a track (a built-in sample if none is given),
+/// showing the path a download would be saved under
+#[tauri::command]
+pub async fn preview_name_format(
     state: State<'_, SettingsState>,
-    settings: AppSettings,
-) -> Result<(), String> {
-    settings::store::save_settings(state, settings)
+    template: String,
+    sample_track: Option<TrackTags>,
+) -> Result<String, SoulsharkError> {
+    let settings = settings::store::get_settings(state)?;
+    let tags = sample_track.unwrap_or_else(TrackTags::sample);
+
+    naming::render_name(&template, &tags, &NamingOptions::from_settings(&settings)).map_err(SoulsharkError::Validation)
 }
 
 #[tauri::command]
 pub async fn save_credentials(
     app_handle: AppHandle,
     credentials: Credentials,
-) -> Result<(), String> {
-    settings::store::save_credentials(&app_handle, credentials).await
+) -> Result<(), SoulsharkError> {
+    settings::store::save_credentials(&app_handle, credentials).await?;
+    app_handle.state::<SpotifyTokenManager>().clear().await;
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn get_credentials(app_handle: AppHandle) -> Result<Credentials, String> {
+pub async fn get_credentials(app_handle: AppHandle) -> Result<Credentials, SoulsharkError> {
     settings::store::get_credentials(&app_handle).await
 }
+
+/// Report which backend holds the credentials key and whether it is locked
+#[tauri::command]
+pub async fn get_key_storage_status(app_handle: AppHandle) -> Result<KeyStorageStatus, SoulsharkError> {
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+    let kind = settings::store::key_storage_kind(&app_handle);
+    let locked = kind == KeyStorageKind::Passphrase && PassphraseKeyStore::new(&app_data_dir).is_locked();
+
+    Ok(KeyStorageStatus { kind, locked })
+}
+
+/// Unlock passphrase-protected credentials for this session
+#[tauri::command]
+pub async fn unlock_credentials(app_handle: AppHandle, passphrase: String) -> Result<(), SoulsharkError> {
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+    PassphraseKeyStore::new(&app_data_dir)
+        .unlock(&passphrase)
+        .map_err(SoulsharkError::Auth)?;
+
+    let _ = app_handle.emit("credentials:unlocked", ());
+    Ok(())
+}
+
+/// Move the credentials key to another backend. A passphrase is required when
+/// switching to (or changing) the passphrase backend.
+#[tauri::command]
+pub async fn set_key_storage(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    kind: KeyStorageKind,
+    passphrase: Option<String>,
+) -> Result<KeyStorageStatus, SoulsharkError> {
+    let app_data_dir = settings::store::app_data_dir(&app_handle)?;
+    let passphrase_store = PassphraseKeyStore::new(&app_data_dir);
+
+    let mut settings = settings::store::get_settings(state.clone())?;
+    let current = settings.security.key_storage;
+
+    if current == KeyStorageKind::Passphrase && passphrase_store.is_locked() {
+        return Err(SoulsharkError::Locked(
+            "Credentials are locked. Unlock them with your passphrase".to_string(),
+        ));
+    }
+
+    if kind == KeyStorageKind::Passphrase {
+        let passphrase =
+            passphrase.ok_or_else(|| SoulsharkError::Validation("A passphrase is required".to_string()))?;
+        passphrase_store
+            .set_passphrase(&passphrase)
+            .map_err(SoulsharkError::Settings)?;
+    }
+
+    keystore::migrate_key(&app_data_dir, current, kind).map_err(SoulsharkError::Settings)?;
+
+    settings.security.key_storage = kind;
+    settings::store::save_settings(state, settings)?;
+
+    Ok(KeyStorageStatus { kind, locked: false })
+}
+
+/// Re-encrypt the stored credentials under a freshly generated key
+#[tauri::command]
+pub async fn rotate_encryption_key(app_handle: AppHandle) -> Result<(), SoulsharkError> {
+    settings::store::rotate_encryption_key(&app_handle)
+}
+
+/// Delete all stored credentials and their key, e.g. after the key was lost
+/// or corrupted
+#[tauri::command]
+pub async fn reset_credentials(app_handle: AppHandle) -> Result<(), SoulsharkError> {
+    settings::store::reset_credentials(&app_handle)?;
+    app_handle.state::<SpotifyTokenManager>().clear().await;
+
+    let _ = app_handle.emit("credentials:reset", ());
+    Ok(())
+}
+
+/// Write the settings to a file to share. Credentials are only included when
+/// requested, encrypted with the given passphrase.
+#[tauri::command]
+pub async fn export_settings(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    path: String,
+    include_secrets: bool,
+    passphrase: Option<String>,
+) -> Result<(), SoulsharkError> {
+    let settings = settings::store::get_settings(state)?;
+
+    let export = if include_secrets {
+        let passphrase = passphrase
+            .ok_or_else(|| SoulsharkError::Validation("A passphrase is required to export secrets".to_string()))?;
+        let credentials = settings::store::get_credentials(&app_handle).await?;
+        export::build_export(&settings, Some((&credentials, &passphrase)))
+    } else {
+        export::build_export(&settings, None)
+    }
+    .map_err(SoulsharkError::Settings)?;
+
+    export::write_export(Path::new(&path), &export).map_err(SoulsharkError::Filesystem)
+}
+
+/// Replace the settings with an exported file. Key storage, endpoints, the
+/// sldl binary, extra sldl options and input tools, the watch folder and the
+/// local API stay as configured on this machine, and credentials are only
+/// replaced if the file has them. A file whose settings don't validate here
+/// is refused.
+#[tauri::command]
+pub async fn import_settings(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<ImportResult, SoulsharkError> {
+    let export = export::read_export(Path::new(&path)).map_err(SoulsharkError::Filesystem)?;
+    let mut imported = export::exported_settings(&export).map_err(SoulsharkError::Settings)?;
+    let current = settings::store::get_settings(state.clone())?;
+
+    // A shared file must not redirect tokens to other servers, run another
+    // binary or commands (sldl config lines, yt-dlp arguments) or open
+    // folders and ports on this machine
+    imported.security = current.security.clone();
+    imported.endpoints = current.endpoints.clone();
+    imported.sldl.path = current.sldl.path.clone();
+    imported.sldl.config.extra_lines = current.sldl.config.extra_lines.clone();
+    imported.sldl.config.input = current.sldl.config.input.clone();
+    imported.watch = current.watch.clone();
+    imported.api = current.api.clone();
+
+    let errors = validation::validate(&imported);
+    if !errors.is_empty() {
+        return Err(invalid_settings(&errors));
+    }
+
+    // Decrypt before saving anything, so a wrong passphrase changes nothing
+    let credentials = match &export.secrets {
+        Some(secrets) => {
+            let passphrase = passphrase.ok_or_else(|| {
+                SoulsharkError::Validation("This file contains secrets, enter its passphrase".to_string())
+            })?;
+            Some(export::decrypt_secrets(secrets, &passphrase).map_err(SoulsharkError::Auth)?)
+        }
+        None => None,
+    };
+    let imported_secrets = credentials.is_some();
+
+    settings::store::save_settings(state.clone(), imported.clone())?;
+
+    // Put the old settings back if the secrets can't be saved, so the stored
+    // credentials always go with the settings they came with
+    if let Some(credentials) = credentials {
+        if let Err(e) = settings::store::save_credentials(&app_handle, credentials).await {
+            if let Err(rollback) = settings::store::save_settings(state, current) {
+                tracing::error!("Failed to restore the settings after a failed import: {}", rollback);
+            }
+            return Err(e);
+        }
+        app_handle.state::<SpotifyTokenManager>().clear().await;
+    }
+    apply_services(&app_handle, &imported);
+
+    Ok(ImportResult {
+        settings: imported,
+        imported_secrets,
+    })
+}
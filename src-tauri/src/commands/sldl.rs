@@ -1,389 +1,424 @@
-use crate::downloads::{Download, DownloadManagerState, DownloadStatus, emit_download_event};
-use crate::settings::{self, SettingsState};
-use std::collections::HashMap;
-use tauri::{AppHandle, Manager, Emitter, State};
-use tauri_plugin_shell::{ShellExt, process::CommandEvent};
-use regex::Regex;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use crate::downloads::{cleanup, disk, history, import, peers::{self, PeerTracker}, Download, DownloadManager, DownloadManagerState, DownloadStatus, emit_download_event};
+use crate::error::SoulsharkError;
+use crate::settings::{self, AppSettings, CleanupMode, SettingsState};
+use crate::sldl::{self, parser::{SldlLine, SldlParser}, version::{self, Compatibility, SldlInfo}};
+use crate::spotify::api::SpotifyApi;
+use crate::spotify::link::{self, SpotifyLink, SpotifyLinkKind};
+use crate::spotify::token::SpotifyTokenManager;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tauri_plugin_shell::{ShellExt, process::{Command, CommandChild, CommandEvent}};
+use tracing::Instrument;
+
+// Version of the sldl binary the current settings point at, once probed
+#[derive(Default)]
+pub struct SldlInfoState(pub Mutex<Option<SldlInfo>>);
+
+// sldl processes of the running jobs, by download ID
+static RUNNING: once_cell::sync::Lazy<Mutex<HashMap<String, CommandChild>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
 
 #[tauri::command]
-pub async fn execute_sldl(
-    app_handle: AppHandle,
+#[allow(clippy::too_many_arguments)]
+pub async fn execute_sldl<R: Runtime>(
+    app_handle: AppHandle<R>,
     state: State<'_, DownloadManagerState>,
     query: String,
     options: HashMap<String, String>,
     title: Option<String>,
     artist: Option<String>,
     album: Option<String>,
-) -> Result<String, String> {
-    // Check if this is a Spotify playlist
-    let is_playlist = query.contains("spotify:") || query.contains("spotify.com/playlist") || query == "spotify-likes";
-    
-    // Create a new download entry
-    let download_title = title.clone().unwrap_or_else(|| {
-        if is_playlist {
-            // For playlists, use a better default title than the URL
-            if query == "spotify-likes" {
-                "Spotify Liked Songs".to_string()
-            } else {
-                "Spotify Playlist (Loading...)".to_string()
+    account_id: Option<String>,
+) -> Result<String, SoulsharkError> {
+    start_download(&app_handle, &state, query, options, title, artist, album, account_id).await
+}
+
+// Result of a bulk enqueue
+#[derive(Debug, Serialize)]
+pub struct BulkEnqueueResult {
+    pub download_ids: Vec<String>,
+    pub duplicates: usize,
+}
+
+/// Queue every link or "Artist - Title" line in pasted text as its own
+/// download. Duplicates are skipped and the jobs run one at a time.
+#[tauri::command]
+pub async fn bulk_enqueue<R: Runtime>(
+    app_handle: AppHandle<R>,
+    state: State<'_, DownloadManagerState>,
+    text: String,
+    account_id: Option<String>,
+) -> Result<BulkEnqueueResult, SoulsharkError> {
+    let parsed = import::parse_text(&text, true);
+    let parsed_count = parsed.len();
+
+    // Resolve share links so they de-duplicate against full links
+    let mut entries = Vec::with_capacity(parsed_count);
+    for mut entry in parsed {
+        if link::is_short_link(&entry.query) {
+            match link::resolve_short_link(&entry.query).await {
+                Ok(resolved) => entry.query = resolved.url(),
+                Err(e) => tracing::error!("{}", e),
             }
-        } else {
-            query.clone()
         }
+        entries.push(entry);
+    }
+
+    let entries = import::dedupe(entries);
+    let duplicates = parsed_count - entries.len();
+
+    let mut download_ids = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let download_id = create_download(
+            &app_handle,
+            &state,
+            entry.query,
+            entry.title,
+            entry.artist,
+            entry.album,
+            account_id.clone(),
+        )?;
+        download_ids.push(download_id);
+    }
+
+    // Work through the queue in the background
+    let app_handle_clone = app_handle.clone();
+    let queued_ids = download_ids.clone();
+    std::thread::spawn(move || {
+        let state = app_handle_clone.state::<DownloadManagerState>();
+        run_queued_blocking(&app_handle_clone, &state, &queued_ids, || true, |_, _| {});
     });
+
+    Ok(BulkEnqueueResult {
+        download_ids,
+        duplicates,
+    })
+}
+
+// Create a download entry and spawn sldl for it, returning the download ID.
+// Shared by the `execute_sldl` command and the local HTTP API.
+#[allow(clippy::too_many_arguments)]
+pub async fn start_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    query: String,
+    options: HashMap<String, String>,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    account_id: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let download_id = create_download(app_handle, state, query, title, artist, album, account_id)?;
+    run_download(app_handle, state, &download_id, options).await?;
+    Ok(download_id)
+}
+
+// Add a queued download entry without starting it. Spotify queries use the
+// given account, or the active one if None.
+pub fn create_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    query: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    account_id: Option<String>,
+) -> Result<String, SoulsharkError> {
+    let mut download = build_download(query, title, artist, album);
+    download.account_id = account_id;
+    add_download(app_handle, state, download)
+}
+
+// Build a download entry for a query
+pub fn build_download(
+    query: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+) -> Download {
+    // Check if this is a Spotify playlist, album or artist
+    let query = sldl::normalize_query(&query);
+    let is_playlist = sldl::is_playlist_query(&query);
     
-    let download = Download::new(
-        download_title,
-        artist.clone(),
-        album.clone(),
-        query.clone(),
-        is_playlist
-    );
+    // Create a new download entry
+    let download_title = title.unwrap_or_else(|| sldl::default_title(&query, is_playlist));
     
+    Download::new(download_title, artist, album, query, is_playlist)
+}
+
+// Register a download with the manager and notify the frontend
+pub fn add_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    download: Download,
+) -> Result<String, SoulsharkError> {
     // Get the download ID
     let download_id = download.id.clone();
     
     // Add the download to the manager
     {
-        let mut download_manager = state.0.lock().map_err(|e| e.to_string())?;
+        let mut download_manager = state.0.lock()?;
         download_manager.add_download(download.clone());
     }
     
     // Emit download started event
-    emit_download_event(&app_handle, "download:started", &download);
-    
-    // Get credentials
-    let credentials = settings::store::get_credentials(&app_handle).await?;
+    emit_download_event(app_handle, "download:started", &download);
 
-    // Get settings
-    let settings_state = app_handle.state::<SettingsState>();
-    let settings = settings::store::get_settings(settings_state)?;
+    Ok(download_id)
+}
 
-    // Build sldl command
-    let mut command = app_handle
-        .shell()
-        .sidecar("sldl")
-        .map_err(|e| format!("Failed to create sidecar command: {}", e))?;
+// Spawn sldl for a queued download. If it can't be started the download is
+// marked as failed so nothing waits on it forever. Everything logged for the
+// job, including sldl's output, is recorded in its span.
+#[tracing::instrument(name = "download", skip_all, fields(id = %download_id))]
+pub async fn run_download<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    download_id: &str,
+    options: HashMap<String, String>,
+) -> Result<(), SoulsharkError> {
+    let result = spawn_sldl(app_handle, state, download_id, options).await;
 
-    // Build the command with all arguments
-    let mut args = Vec::new();
+    if let Err(e) = &result {
+        tracing::error!(code = e.code(), "Download failed: {}", e);
+        if let Ok(mut download_manager) = state.0.lock() {
+            if let Some(download) = download_manager.get_download_mut(download_id) {
+                download.update_status(DownloadStatus::Failed(e.clone()));
+                let download_clone = download.clone();
+                emit_download_event(app_handle, "download:failed", &download_clone);
+            }
+        }
+    }
 
-    // Add the query
-    args.push(query.clone());
+    result
+}
 
-    // Add Soulseek credentials
-    if !settings.soulseek.username.is_empty() {
-        args.push("--user".to_string());
-        args.push(settings.soulseek.username.clone());
-    }
+// Run queued downloads one after another, blocking the current thread until
+// each one finishes. Stops early once `should_continue` returns false, and
+// calls `on_finished` with the final state of each download.
+// Returns whether every download completed.
+pub fn run_queued_blocking<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    download_ids: &[String],
+    should_continue: impl Fn() -> bool,
+    mut on_finished: impl FnMut(&str, Option<&DownloadStatus>),
+) -> bool {
+    let mut all_completed = true;
 
-    if let Some(password) = &credentials.soulseek_password {
-        if !password.is_empty() {
-            args.push("--pass".to_string());
-            args.push(password.clone());
+    for download_id in download_ids {
+        if !should_continue() {
+            return false;
         }
-    }
 
-    // Add Spotify credentials if the query is a Spotify URL or "spotify-likes"
-    if query.contains("spotify") {
-        if !settings.spotify.client_id.is_empty() {
-            args.push("--spotify-id".to_string());
-            args.push(settings.spotify.client_id.clone());
+        let result = tauri::async_runtime::block_on(run_download(
+            app_handle,
+            state,
+            download_id,
+            HashMap::new(),
+        ));
+
+        let status = match result {
+            Ok(()) => wait_for_download(state, download_id),
+            Err(_) => None,
+        };
+        if !matches!(status, Some(DownloadStatus::Completed)) {
+            all_completed = false;
         }
+        on_finished(download_id, status.as_ref());
+    }
 
-        if let Some(client_secret) = &credentials.spotify_client_secret {
-            if !client_secret.is_empty() {
-                args.push("--spotify-secret".to_string());
-                args.push(client_secret.clone());
-            }
+    all_completed
+}
+
+// Block until a download reaches a final state or is paused, returning that
+// state
+pub fn wait_for_download(state: &DownloadManagerState, download_id: &str) -> Option<DownloadStatus> {
+    loop {
+        let status = {
+            let download_manager = state.0.lock().ok()?;
+            download_manager.get_download(download_id)?.status.clone()
+        };
+
+        // Paused jobs wait for the user, so the queue moves on
+        if status.is_finished() || status.is_paused() {
+            return Some(status);
         }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
 
-        if let Some(access_token) = &credentials.spotify_access_token {
-            if !access_token.is_empty() {
-                args.push("--spotify-token".to_string());
-                args.push(access_token.clone());
-            }
+async fn spawn_sldl<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &DownloadManagerState,
+    download_id: &str,
+    options: HashMap<String, String>,
+) -> Result<(), SoulsharkError> {
+    let (query, account_id, album_mode) = {
+        let download_manager = state.0.lock()?;
+        let download = download_manager
+            .get_download(download_id)
+            .ok_or_else(|| SoulsharkError::NotFound(format!("Download with id {} not found", download_id)))?;
+
+        // Queued downloads can be canceled before they start
+        if download.status == DownloadStatus::Canceled {
+            return Ok(());
         }
+        (download.query.clone(), download.account_id.clone(), download.album_mode.clone())
+    };
 
-        if let Some(refresh_token) = &credentials.spotify_refresh_token {
-            if !refresh_token.is_empty() {
-                args.push("--spotify-refresh".to_string());
-                args.push(refresh_token.clone());
+    // Make sure sldl gets a current Spotify token
+    let access_token = if sldl::uses_spotify(&query) {
+        match app_handle
+            .state::<SpotifyTokenManager>()
+            .get_valid_token(app_handle, account_id.as_deref())
+            .await
+        {
+            Ok(access_token) => Some(access_token),
+            Err(e) => {
+                tracing::warn!("Could not refresh the Spotify token: {}", e);
+                None
             }
         }
+    } else {
+        None
+    };
+
+    // Get credentials, with the tokens of the job's Spotify account
+    let mut credentials = settings::store::get_credentials(app_handle).await?;
+    if sldl::uses_spotify(&query) {
+        credentials = credentials
+            .for_spotify_account(account_id.as_deref())
+            .map_err(SoulsharkError::Auth)?;
     }
 
-    // Add download path
+    // Get settings
+    let settings_state = app_handle.state::<SettingsState<R>>();
+    let settings = settings::store::get_settings(settings_state)?;
+
+    // Hold the job back if the downloads volume can't take it. It stays
+    // paused until the user frees some space and resumes it.
+    let downloads_root = PathBuf::from(&settings.soulseek.downloads_path);
+    let min_free = settings.soulseek.min_free_space_mb.saturating_mul(disk::MB);
     if !settings.soulseek.downloads_path.is_empty() {
-        args.push("--path".to_string());
-        args.push(settings.soulseek.downloads_path.clone());
-    }
+        let track_count = estimate_track_count(&query, access_token, &settings).await;
+        let required = disk::estimate_required(track_count, &settings.soulseek.preferred_format);
 
-    // Add preferred format
-    if !settings.soulseek.preferred_format.is_empty() {
-        args.push("--pref-format".to_string());
-        args.push(settings.soulseek.preferred_format.clone());
-    }
-    
-    // Add name format
-    if !settings.output.name_format.is_empty() {
-        args.push("--name-format".to_string());
-        args.push(settings.output.name_format.clone());
+        match disk::check_space(&downloads_root, required, min_free) {
+            Ok(Some(reason)) => {
+                pause_download(app_handle, &state.0, download_id, reason);
+                return Ok(());
+            }
+            Ok(None) => {}
+            // Not knowing the free space is no reason to hold the job back
+            Err(e) => tracing::warn!("Skipping the disk space check: {}", e),
+        }
     }
 
-    // Add any additional options
-    for (key, value) in options {
-        args.push(format!("--{}", key));
-        args.push(value);
-    }
+    // Build sldl command
+    let mut command = sldl_command(app_handle, &settings)?;
+
+    // Write the job's config file, removed again once sldl exits
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| SoulsharkError::Filesystem(format!("Failed to get app data directory: {}", e)))?;
+    let banned_peers = peers::banned_peers(&app_data_dir, &settings.peers);
+    let contents = sldl::config::render(&settings, &credentials, &query, album_mode.as_ref(), &banned_peers);
+    let config_path = sldl::config::write(&app_data_dir, download_id, &contents).map_err(SoulsharkError::Filesystem)?;
+
+    // Build the command with all arguments
+    let args = sldl::build_args(&query, &options, &settings, &config_path);
 
     // Add all arguments to the command
     command = command.args(args);
+
+    // Remember what the downloads path holds, so the cleanup afterwards only
+    // touches folders this job created
+    let cleanup_mode = settings.output.cleanup;
+    let before = (!settings.soulseek.downloads_path.is_empty() && cleanup_mode != CleanupMode::Off)
+        .then(|| cleanup::snapshot(&downloads_root));
     
     // Execute the command
-    let (mut rx, _child) = command
-        .spawn()
-        .map_err(|e| format!("Failed to spawn sldl command: {}", e))?;
+    let (mut rx, child) = command.spawn().map_err(|e| {
+        sldl::config::remove(&config_path);
+        SoulsharkError::Sidecar(format!("Failed to spawn sldl command: {}", e))
+    })?;
+
+    // Keep the process so canceling or pausing the job can stop it
+    if let Ok(mut running) = RUNNING.lock() {
+        running.insert(download_id.to_string(), child);
+    }
+
+    // Pause the job if the free space runs low while it downloads
+    if min_free > 0 && !settings.soulseek.downloads_path.is_empty() {
+        let app_handle_clone = app_handle.clone();
+        let download_manager_state = state.0.clone();
+        let download_id_clone = download_id.to_string();
+        let downloads_root = downloads_root.clone();
+        let span = tracing::Span::current();
+        std::thread::spawn(move || {
+            let _span = span.entered();
+            monitor_disk_space(
+                &app_handle_clone,
+                &download_manager_state,
+                &download_id_clone,
+                &downloads_root,
+                min_free,
+            );
+        });
+    }
     
     // Clone what we need for the async task
     let app_handle_clone = app_handle.clone();
-    let download_id_clone = download_id.clone();
+    let options_clone = options.clone();
+    let download_id_clone = download_id.to_string();
     let download_manager_state = state.0.clone();
     
-    // Flag to track if we're processing a playlist
-    let is_playlist_download = Arc::new(AtomicBool::new(false));
-    let is_playlist_clone = is_playlist_download.clone();
-    
     // Handle command output in a separate task
     tauri::async_runtime::spawn(async move {
-        // Compile regex patterns for parsing progress
-        let playlist_re = Regex::new(r"Downloading (\d+) tracks:").unwrap();
-        let loading_playlist_re = Regex::new(r"Loading Spotify playlist").unwrap();
-        let playlist_name_re = Regex::new(r"Playlist: (.+) by (.+)").unwrap();
-        let searching_re = Regex::new(r"Searching: (.+)").unwrap();
-        let initialize_re = Regex::new(r"Initialize:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap();
-        let progress_re = Regex::new(r"InProgress:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap();
-        let success_re = Regex::new(r"Succeeded:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap();
-        let completed_re = Regex::new(r"Completed: (\d+) succeeded, (\d+) failed").unwrap();
-        let not_found_re = Regex::new(r"Not found: (.+)").unwrap();
+        let parser = SldlParser::new();
+        let mut tracker = PeerTracker::default();
+        // Files this run reported, for finding the folders it created
+        let mut reported_paths = HashSet::new();
         
         while let Some(event) = rx.recv().await {
             match event {
                 CommandEvent::Stdout(line) => {
                     let line_str = String::from_utf8_lossy(&line).to_string();
-                    println!("sldl stdout: {}", line_str);
-                    
-                    // Add to download's console logs
-                    if let Ok(mut download_manager) = download_manager_state.lock() {
-                        if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                            download.add_console_log(line_str.clone());
-                        }
-                    }
+                    tracing::debug!(stream = "stdout", "{}", line_str);
                     
                     // Emit stdout event to the frontend
                     let _ = app_handle_clone.emit("sldl:stdout", line_str.clone());
                     
-                    // Check if this is a playlist download
-                    if let Some(caps) = playlist_re.captures(&line_str) {
-                        if let Some(count_match) = caps.get(1) {
-                            if let Ok(track_count) = count_match.as_str().parse::<usize>() {
-                                is_playlist_clone.store(true, Ordering::SeqCst);
-                                
-                                // Update download with playlist info
-                                if let Ok(mut download_manager) = download_manager_state.lock() {
-                                    if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                        download.set_playlist_info(track_count);
-                                        
-                                        // Emit progress event
-                                        let download_clone = download.clone();
-                                        emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Check for loading playlist message
-                    else if loading_playlist_re.is_match(&line_str) {
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                download.update_status(DownloadStatus::Searching);
-                                
-                                // Emit progress event
-                                let download_clone = download.clone();
-                                emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                            }
-                        }
+                    // Add to download's console logs and apply any progress it reports
+                    let parsed = parser.parse(&line_str);
+                    tracker.observe(&parsed);
+                    if let SldlLine::InProgress(path) | SldlLine::Succeeded { path, .. } = &parsed {
+                        reported_paths.insert(path.clone());
                     }
-                    
-                    // Check for playlist name
-                    else if let Some(caps) = playlist_name_re.captures(&line_str) {
-                        if let (Some(playlist_name), Some(creator)) = (caps.get(1), caps.get(2)) {
-                            if let Ok(mut download_manager) = download_manager_state.lock() {
-                                if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                    // Update the download title with the actual playlist name
-                                    download.title = format!("{} by {}", playlist_name.as_str(), creator.as_str());
-                                    
-                                    // Emit progress event
-                                    let download_clone = download.clone();
-                                    emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Check for searching status
-                    else if let Some(caps) = searching_re.captures(&line_str) {
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                download.update_status(DownloadStatus::Searching);
-                                
-                                // If this is a single track download, update the title with the actual track name
-                                if !download.is_playlist {
-                                    if let Some(track_name) = caps.get(1) {
-                                        download.title = track_name.as_str().to_string();
-                                    }
-                                }
-                                
-                                // Emit progress event
-                                let download_clone = download.clone();
-                                emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                            }
-                        }
-                    }
-                    
-                    // Check for initialize status
-                    else if initialize_re.is_match(&line_str) {
-                        // Update status to InProgress
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                download.update_status(DownloadStatus::InProgress);
-                                
-                                // Only set progress to 0 for single downloads
-                                // For playlists, we track progress by completed/total
-                                if !download.is_playlist {
-                                    download.update_progress(0.0);
-                                }
-                                
-                                // Emit progress event
-                                let download_clone = download.clone();
-                                emit_download_event(&app_handle_clone, "download:progress", &download_clone);
+                    if let Ok(mut download_manager) = download_manager_state.lock() {
+                        if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
+                            download.add_console_log(line_str.clone());
+                            
+                            // Output still buffered when a job is paused or
+                            // canceled must not change its status
+                            if download.status.is_paused() || download.status == DownloadStatus::Canceled {
+                                continue;
                             }
-                        }
-                    }
-                    
-                    // Check for in progress status
-                    else if let Some(caps) = progress_re.captures(&line_str) {
-                        // Extract file path and update progress
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                // For single downloads, set progress to 0.5 (50%)
-                                if !download.is_playlist {
-                                    download.update_progress(0.5);
-                                }
-                                
-                                // Extract file path if available
-                                if let Some(file_path) = caps.get(1) {
-                                    download.set_file_path(file_path.as_str().to_string());
-                                }
-                                
-                                // Emit progress event
+                            if let Some(event_name) = download.apply_sldl_line(&parsed) {
                                 let download_clone = download.clone();
-                                emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                            }
-                        }
-                    }
-                    
-                    // Check for not found status
-                    else if not_found_re.is_match(&line_str) {
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                // For playlists, increment failed tracks
-                                if download.is_playlist {
-                                    download.increment_failed_tracks();
-                                    
-                                    // Emit progress event
-                                    let download_clone = download.clone();
-                                    emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Check for success status
-                    else if let Some(caps) = success_re.captures(&line_str) {
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                // For playlists, increment completed tracks
-                                if download.is_playlist {
-                                    download.increment_completed_tracks();
-
-                                    // Fallback: if playlist has only 1 track, mark as completed immediately
-                                    if let (Some(total), Some(completed), Some(failed)) = (
-                                        download.total_tracks,
-                                        download.completed_tracks,
-                                        download.failed_tracks
-                                    ) {
-                                        if total == 1 && completed + failed >= 1 {
-                                            download.update_status(DownloadStatus::Completed);
-                                            download.update_progress(1.0);
-                                            let download_clone = download.clone();
-                                            emit_download_event(&app_handle_clone, "download:completed", &download_clone);
-                                            continue;
-                                        }
-                                    }
-
-                                    // Otherwise, emit progress event
-                                    let download_clone = download.clone();
-                                    emit_download_event(&app_handle_clone, "download:progress", &download_clone);
-                                } else {
-                                    // For single downloads, mark as completed
-                                    download.update_status(DownloadStatus::Completed);
-                                    download.update_progress(1.0);
-
-                                    // Extract file path if available
-                                    if let Some(file_path) = caps.get(1) {
-                                        download.set_file_path(file_path.as_str().to_string());
-                                    }
-
-                                    let download_clone = download.clone();
-                                    emit_download_event(&app_handle_clone, "download:completed", &download_clone);
-                                }
-                            }
-                        }
-                    }
-                    
-                    // Check for playlist completion
-                    else if let Some(caps) = completed_re.captures(&line_str) {
-                        if let (Some(succeeded), Some(failed)) = (caps.get(1), caps.get(2)) {
-                            if let (Ok(succeeded_count), Ok(failed_count)) = (
-                                succeeded.as_str().parse::<usize>(),
-                                failed.as_str().parse::<usize>()
-                            ) {
-                                if let Ok(mut download_manager) = download_manager_state.lock() {
-                                    if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                        // Update final counts
-                                        download.completed_tracks = Some(succeeded_count);
-                                        download.failed_tracks = Some(failed_count);
-                                        
-                                        // Mark as completed
-                                        download.update_status(DownloadStatus::Completed);
-                                        download.update_progress(1.0);
-                                        
-                                        // Emit completed event
-                                        let download_clone = download.clone();
-                                        emit_download_event(&app_handle_clone, "download:completed", &download_clone);
-                                    }
-                                }
+                                emit_download_event(&app_handle_clone, event_name, &download_clone);
                             }
                         }
                     }
                 },
                 CommandEvent::Stderr(line) => {
                     let line_str = String::from_utf8_lossy(&line).to_string();
-                    eprintln!("sldl stderr: {}", line_str);
+                    tracing::warn!(stream = "stderr", "{}", line_str);
                     
                     // Add to download's console logs
                     if let Ok(mut download_manager) = download_manager_state.lock() {
@@ -396,80 +431,107 @@ pub async fn execute_sldl(
                     let _ = app_handle_clone.emit("sldl:stderr", line_str);
                 },
                 CommandEvent::Terminated(status) => {
-                    println!("sldl terminated with status: {:?}", status);
+                    tracing::info!(code = ?status.code, "sldl terminated");
+                    if let Ok(mut running) = RUNNING.lock() {
+                        running.remove(&download_id_clone);
+                    }
+                    sldl::config::remove(&config_path);
                     
                     // Emit terminated event to the frontend
                     let is_success = status.code.map_or(false, |code| code == 0);
                     let _ = app_handle_clone.emit("sldl:terminated", is_success);
                     
-                    // Cleanup unwanted playlist metadata files
-                    let download_path = {
-                        let settings_state = app_handle_clone.state::<SettingsState>();
-                        if let Ok(settings) = settings::store::get_settings(settings_state) {
-                            settings.soulseek.downloads_path.clone()
-                        } else {
-                            String::new()
-                        }
-                    };
-                    if !download_path.is_empty() {
-                        tauri::async_runtime::spawn(async move {
-                            use std::path::Path;
-                            use tokio::fs;
-                            use tokio_stream::wrappers::ReadDirStream;
-                            use tokio_stream::StreamExt;
-
-                            fn clean_dir<'a>(path: &'a Path) -> std::pin::Pin<Box<dyn std::future::Future<Output=std::io::Result<()>> + Send + 'a>> {
-                                fn inner<'a>(path: &'a Path) -> std::pin::Pin<Box<dyn std::future::Future<Output=std::io::Result<()>> + Send + 'a>> {
-                                    Box::pin(async move {
-                                        let mut entries = fs::read_dir(path).await.map(ReadDirStream::new)?;
-                                        while let Some(Ok(entry)) = entries.next().await {
-                                            let entry_path = entry.path();
-                                            if entry_path.is_dir() {
-                                                inner(&entry_path).await?;
-                                                if let Ok(mut dir_stream) = fs::read_dir(&entry_path).await {
-                                                    if matches!(dir_stream.next_entry().await, Ok(None)) {
-                                                        let _ = fs::remove_dir(&entry_path).await;
-                                                    }
-                                                }
-                                            } else if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
-                                                if name == "_index.sldl" {
-                                                    let _ = fs::remove_file(&entry_path).await;
-                                                }
-                                            }
-                                        }
-                                        Ok(())
-                                    })
+                    let created_dirs = before
+                        .as_ref()
+                        .map(|before| cleanup::created_dirs(&downloads_root, before, &reported_paths))
+                        .unwrap_or_default();
+
+                    // Settle the final status and record the download in the
+                    // history. A paused job isn't finished; its folders and
+                    // index files are kept so it can be resumed. A canceled
+                    // job keeps its status. An album no folder had in full
+                    // is downloaded track by track if that is allowed.
+                    let mut fall_back = false;
+                    let finished = if let Ok(mut download_manager) = download_manager_state.lock() {
+                        download_manager.get_download_mut(&download_id_clone).and_then(|download| {
+                            // Earlier runs of a resumed job created some of its folders
+                            for dir in &created_dirs {
+                                let dir = dir.to_string_lossy().to_string();
+                                if !download.created_dirs.contains(&dir) {
+                                    download.created_dirs.push(dir);
                                 }
-                                inner(path)
                             }
+                            if download.status.is_paused() {
+                                return None;
+                            }
+                            if download.status != DownloadStatus::Canceled
+                                && download.album_mode.as_ref().map_or(false, |album| album.should_fall_back(is_success))
+                            {
+                                download.fall_back_to_tracks();
+                                emit_download_event(&app_handle_clone, "download:progress", download);
+                                fall_back = true;
+                                return None;
+                            }
+                            if download.status != DownloadStatus::Canceled {
+                                if let Some(event_name) = download.finish(is_success) {
+                                    let download_clone = download.clone();
+                                    emit_download_event(&app_handle_clone, event_name, &download_clone);
+                                }
+                            }
+                            Some(download.clone())
+                        })
+                    } else {
+                        None
+                    };
 
-                            let _ = clean_dir(Path::new(&download_path)).await;
+                    // Run sldl again without album mode, the job keeps its ID
+                    if fall_back {
+                        tracing::info!("No complete album folder found, falling back to single tracks");
+                        let app_handle = app_handle_clone.clone();
+                        let download_id = download_id_clone.clone();
+                        let options = options_clone.clone();
+                        std::thread::spawn(move || {
+                            let state = app_handle.state::<DownloadManagerState>();
+                            let _ = tauri::async_runtime::block_on(run_download(&app_handle, &state, &download_id, options));
                         });
                     }
 
-                    // If the command failed, update the download status
-                    if !is_success {
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                download.update_status(DownloadStatus::Failed("Command failed".to_string()));
-                                
-                                // Emit failed event
-                                let download_clone = download.clone();
-                                emit_download_event(&app_handle_clone, "download:failed", &download_clone);
-                            }
+                    // Every run's transfers count towards the peers, including
+                    // paused runs and album runs that fall back
+                    if let Err(e) = peers::record_transfers(&app_data_dir, tracker.transfers(), finished.as_ref()) {
+                        tracing::error!("Failed to record peer stats: {}", e);
+                    }
+
+                    if let Some(download) = finished {
+                        if let Err(e) = history::record_download(&app_data_dir, &download) {
+                            tracing::error!("Failed to record download history: {}", e);
                         }
-                    } else {
-                        // If command succeeded but we didn't get a completion message
-                        if let Ok(mut download_manager) = download_manager_state.lock() {
-                            if let Some(download) = download_manager.get_download_mut(&download_id_clone) {
-                                if download.status != DownloadStatus::Completed {
-                                    download.update_status(DownloadStatus::Completed);
-                                    download.update_progress(1.0);
-                                    
-                                    // Emit completed event
-                                    let download_clone = download.clone();
-                                    emit_download_event(&app_handle_clone, "download:completed", &download_clone);
+
+                        // Move leftover index files to the trash and drop
+                        // folders left empty. An index file in the downloads
+                        // path itself may belong to another job that is still
+                        // running, so it is left until that one is done.
+                        if let Some(before) = before.as_ref() {
+                            let created_dirs: Vec<PathBuf> = download.created_dirs.iter().map(PathBuf::from).collect();
+                            let mut actions = cleanup::plan(&created_dirs);
+                            let others_running = download_manager_state.lock().map_or(true, |download_manager| {
+                                download_manager.get_all_downloads().iter().any(|other| {
+                                    other.id != download_id_clone
+                                        && matches!(other.status, DownloadStatus::Searching | DownloadStatus::InProgress)
+                                })
+                            });
+                            if !others_running {
+                                actions.extend(cleanup::new_index_files(&downloads_root, before));
+                            }
+
+                            if cleanup_mode == CleanupMode::DryRun {
+                                for action in &actions {
+                                    tracing::info!(kind = ?action.kind, path = %action.path, "Cleanup (dry run)");
                                 }
+                            } else if let Err(e) =
+                                cleanup::execute(&app_data_dir, &downloads_root, &download_id_clone, &actions)
+                            {
+                                tracing::error!("Failed to clean up after download: {}", e);
                             }
                         }
                     }
@@ -477,7 +539,150 @@ pub async fn execute_sldl(
                 _ => {}
             }
         }
-    });
+    }.instrument(tracing::Span::current()));
 
-    Ok(download_id)
+    Ok(())
+}
+
+// Number of tracks a query is likely to download. Spotify collections are
+// looked up; anything else, or a failed lookup, counts as one track.
+async fn estimate_track_count(query: &str, access_token: Option<String>, settings: &AppSettings) -> usize {
+    let access_token = match access_token {
+        Some(access_token) => access_token,
+        None => return 1,
+    };
+    let api = SpotifyApi::with_base_url(access_token, &settings.endpoints.spotify_api_url);
+
+    let count = if query == "spotify-likes" {
+        api.get_saved_track_count().await
+    } else {
+        match SpotifyLink::parse(query) {
+            Some(SpotifyLink { kind: SpotifyLinkKind::Playlist, id }) => api.get_playlist_track_count(&id).await,
+            Some(SpotifyLink { kind: SpotifyLinkKind::Album, id }) => api.get_album_track_count(&id).await,
+            Some(SpotifyLink { kind: SpotifyLinkKind::Artist, id }) => api
+                .get_artist_albums(&id, &["album", "single"])
+                .await
+                .map(|albums| albums.iter().map(|album| album.total_tracks).sum()),
+            _ => Ok(1),
+        }
+    };
+
+    count.unwrap_or_else(|e| {
+        tracing::warn!("Could not count the tracks of {}: {}", query, e);
+        1
+    })
+}
+
+// Mark a download as paused with the reason and notify the frontend
+fn pause_download<R: Runtime>(app_handle: &AppHandle<R>, download_manager_state: &Mutex<DownloadManager>, download_id: &str, reason: String) {
+    if let Ok(mut download_manager) = download_manager_state.lock() {
+        if let Some(download) = download_manager.get_download_mut(download_id) {
+            download.add_console_log(format!("Paused: {}", reason));
+            download.update_status(DownloadStatus::Paused(reason));
+            let download_clone = download.clone();
+            emit_download_event(app_handle, "download:paused", &download_clone);
+        }
+    }
+}
+
+// sldl command for the configured binary, or the bundled sidecar
+pub fn sldl_command<R: Runtime>(app_handle: &AppHandle<R>, settings: &AppSettings) -> Result<Command, SoulsharkError> {
+    if !settings.sldl.path.is_empty() {
+        return Ok(app_handle.shell().command(&settings.sldl.path));
+    }
+
+    app_handle
+        .shell()
+        .sidecar(sldl::SIDECAR_NAME)
+        .map_err(|e| SoulsharkError::Sidecar(format!("Failed to create sidecar command: {}", e)))
+}
+
+// Probe the configured sldl and record its version. A version outside the
+// supported range is logged as a warning since the parser may not
+// understand its output.
+pub async fn detect_sldl_version<R: Runtime>(app_handle: &AppHandle<R>) -> SldlInfo {
+    let info = match settings::store::get_settings(app_handle.state::<SettingsState<R>>()) {
+        Ok(settings) => {
+            let configured = (!settings.sldl.path.is_empty()).then(|| settings.sldl.path.clone());
+            match sldl::sidecar_path(&settings) {
+                Ok(binary) => tauri::async_runtime::spawn_blocking(move || version::probe(&binary, configured))
+                    .await
+                    .unwrap_or_else(|e| SldlInfo::from_error(None, e.to_string())),
+                Err(e) => SldlInfo::from_error(configured, e),
+            }
+        }
+        Err(e) => SldlInfo::from_error(None, e.to_string()),
+    };
+
+    match info.compatibility {
+        Compatibility::Supported => tracing::info!(version = ?info.version, "Detected sldl"),
+        Compatibility::Unsupported => tracing::warn!(
+            version = ?info.version,
+            supported = %info.supported,
+            "sldl version is not supported, downloads may not report progress"
+        ),
+        Compatibility::Unknown => tracing::warn!(error = ?info.error, "Could not detect the sldl version"),
+    }
+
+    if let Some(state) = app_handle.try_state::<SldlInfoState>() {
+        if let Ok(mut recorded) = state.0.lock() {
+            *recorded = Some(info.clone());
+        }
+    }
+    info
+}
+
+// Stop the sldl process of a job, if it is running
+pub fn stop_sldl(download_id: &str) {
+    let child = RUNNING.lock().ok().and_then(|mut running| running.remove(download_id));
+    if let Some(child) = child {
+        if let Err(e) = child.kill() {
+            tracing::error!("Failed to stop sldl: {}", e);
+        }
+    }
+}
+
+// Check the free space of the downloads volume while a job runs. Once it drops
+// below the minimum the job is paused and sldl stopped; its index file lets a
+// resumed run skip the tracks already downloaded.
+fn monitor_disk_space<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    download_manager_state: &Arc<Mutex<DownloadManager>>,
+    download_id: &str,
+    downloads_root: &std::path::Path,
+    min_free: u64,
+) {
+    loop {
+        std::thread::sleep(disk::MONITOR_INTERVAL);
+
+        let running = download_manager_state.lock().ok().map_or(false, |download_manager| {
+            download_manager
+                .get_download(download_id)
+                .map_or(false, |download| !download.status.is_finished() && !download.status.is_paused())
+        });
+        if !running {
+            return;
+        }
+
+        let free = match disk::free_space(downloads_root) {
+            Ok(free) => free,
+            Err(e) => {
+                tracing::warn!("{}", e);
+                continue;
+            }
+        };
+        if free >= min_free {
+            continue;
+        }
+
+        // Pause before stopping sldl so its exit isn't taken as a failure
+        let reason = format!(
+            "Low disk space: {} free, at least {} required",
+            disk::format_size(free),
+            disk::format_size(min_free)
+        );
+        pause_download(app_handle, download_manager_state, download_id, reason);
+        stop_sldl(download_id);
+        return;
+    }
 }
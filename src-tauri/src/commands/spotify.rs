@@ -1,22 +1,25 @@
+use crate::commands::auth::{AuthChanged, AuthService};
+use crate::error::SoulsharkError;
+use crate::spotify::auth::{self, Pkce};
+use crate::spotify::token::SpotifyTokenManager;
 use crate::{Credentials, SettingsState};
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_store::StoreExt;
 use tiny_http::{Response, Server};
 use url::Url;
 
-// Shared state for the HTTP server
-struct ServerState {
-    app_handle: AppHandle,
-    is_running: bool,
+// Running callback server, and the redirect URI it serves
+struct CallbackServer {
+    server: Arc<Server>,
+    redirect_uri: String,
 }
 
 // Global server state
-static SERVER_STATE: once_cell::sync::Lazy<Arc<Mutex<Option<Arc<Mutex<ServerState>>>>>> =
-    once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(None)));
+static CALLBACK_SERVER: once_cell::sync::Lazy<Mutex<Option<CallbackServer>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
 
 // HTML response for successful authentication
 const SUCCESS_HTML: &str = r#"<!DOCTYPE html>
@@ -114,13 +117,21 @@ const ERROR_HTML: &str = r#"<!DOCTYPE html>
 </body>
 </html>"#;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TokenResponse {
-    access_token: String,
-    token_type: String,
-    expires_in: u64,
-    refresh_token: String,
-    scope: String,
+// Store holding the PKCE verifier of a pending login
+const AUTH_STORE: &str = "spotify-auth.json";
+
+// Redirect URI of the login in progress: the running server's, or the one
+// stored when the login started
+fn pending_redirect_uri(app_handle: &AppHandle) -> Option<String> {
+    if let Some(callback_server) = CALLBACK_SERVER.lock().unwrap().as_ref() {
+        return Some(callback_server.redirect_uri.clone());
+    }
+
+    app_handle
+        .store(AUTH_STORE)
+        .ok()
+        .and_then(|store| store.get("redirect_uri"))
+        .and_then(|value| value.as_str().map(str::to_string))
 }
 
 /// Exchange the authorization code for an access token
@@ -130,81 +141,18 @@ pub async fn exchange_spotify_code(
     code: String,
     code_verifier: String,
     state: State<'_, SettingsState>,
-) -> Result<(), String> {
-    // Get the app settings (not used but needed for validation)
-    let _settings = crate::commands::settings::get_settings(state.clone())
-        .await
-        .map_err(|e| format!("Failed to get settings: {}", e))?;
-
-    // Get the current credentials
-    let credentials = crate::commands::settings::get_credentials(app_handle.clone())
-        .await
-        .map_err(|e| format!("Failed to get credentials: {}", e))?;
-
-    // Build the token request
-    let client = Client::new();
-
-    // Get the client secret
-    let client_secret = match &credentials.spotify_client_secret {
-        Some(secret) if !secret.is_empty() => secret.as_str(),
-        _ => return Err("Spotify client secret is not set".to_string()),
+) -> Result<(), SoulsharkError> {
+    // Must match the redirect URI of the authorize request
+    let redirect_uri = match pending_redirect_uri(&app_handle) {
+        Some(redirect_uri) => redirect_uri,
+        None => crate::settings::store::get_settings(state)?.spotify.redirect_uri,
     };
 
-    // Get the app settings again to use in the params
-    let settings = crate::commands::settings::get_settings(state.clone())
-        .await
-        .map_err(|e| format!("Failed to get settings: {}", e))?;
-
-    let params = [
-        ("client_id", settings.spotify.client_id.as_str()),
-        ("client_secret", client_secret),
-        ("grant_type", "authorization_code"),
-        ("code", code.as_str()),
-        ("redirect_uri", "http://localhost:5174/callback"),
-        ("code_verifier", code_verifier.as_str()),
-    ];
-
-    // Send the token request
-    let response = client
-        .post("https://accounts.spotify.com/api/token")
-        .form(&params)
-        .send()
+    app_handle
+        .state::<SpotifyTokenManager>()
+        .exchange_code(&app_handle, &code, &code_verifier, &redirect_uri)
         .await
-        .map_err(|e| format!("Failed to send token request: {}", e))?;
-
-    // Check if the request was successful
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Token request failed: {}", error_text));
-    }
-
-    // Parse the response
-    let token_response: TokenResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse token response: {}", e))?;
-
-    // Calculate the expiration time
-    let expires_at = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        + token_response.expires_in;
-
-    // Update the credentials
-    let updated_credentials = Credentials {
-        soulseek_password: credentials.soulseek_password,
-        spotify_client_secret: credentials.spotify_client_secret,
-        spotify_access_token: Some(token_response.access_token),
-        spotify_refresh_token: Some(token_response.refresh_token),
-        spotify_token_expires_at: Some(expires_at),
-    };
-
-    // Save the updated credentials
-    crate::commands::settings::save_credentials(app_handle, updated_credentials)
-        .await
-        .map_err(|e| format!("Failed to save credentials: {}", e))?;
-
+        .map_err(SoulsharkError::Auth)?;
     Ok(())
 }
 
@@ -213,11 +161,11 @@ pub async fn exchange_spotify_code(
 pub async fn check_pending_auth(
     app_handle: AppHandle,
     state: State<'_, SettingsState>,
-) -> Result<bool, String> {
+) -> Result<bool, SoulsharkError> {
     // Try to load the store
     let store = app_handle
-        .store("spotify-auth.json")
-        .map_err(|e| format!("Failed to load store: {}", e))?;
+        .store(AUTH_STORE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to load store: {}", e)))?;
 
     // Check if we have a code verifier
     let code_verifier = match store.get("code_verifier") {
@@ -230,14 +178,10 @@ pub async fn check_pending_auth(
     }
 
     // Get the app settings (not used but needed for validation)
-    let _settings = crate::commands::settings::get_settings(state.clone())
-        .await
-        .map_err(|e| format!("Failed to get settings: {}", e))?;
+    let _settings = crate::commands::settings::get_settings(state.clone()).await?;
 
     // Get the current credentials
-    let credentials = crate::commands::settings::get_credentials(app_handle.clone())
-        .await
-        .map_err(|e| format!("Failed to get credentials: {}", e))?;
+    let credentials = crate::commands::settings::get_credentials(app_handle.clone()).await?;
 
     // Check if we already have valid tokens
     if credentials.spotify_access_token.is_some() && credentials.spotify_refresh_token.is_some() {
@@ -253,411 +197,296 @@ pub async fn check_pending_auth(
     Ok(false)
 }
 
-/// Start the HTTP server for Spotify callback
+/// Start a Spotify login: generate the PKCE verifier and the CSRF state,
+/// start the callback server and return the URL to open in the browser. No
+/// client secret is needed; if one is set it is sent along as before. With
+/// `add_account`, Spotify asks which account to use so another one can be
+/// linked.
 #[tauri::command]
-pub fn start_spotify_callback_server(app_handle: AppHandle) -> Result<(), String> {
-    // Create a server on localhost:5174
-    let server = match Server::http("127.0.0.1:5174") {
-        Ok(server) => server,
-        Err(e) => return Err(format!("Failed to start server: {}", e)),
-    };
+pub async fn begin_spotify_auth(
+    app_handle: AppHandle,
+    state: State<'_, SettingsState>,
+    add_account: Option<bool>,
+) -> Result<String, SoulsharkError> {
+    let settings = crate::commands::settings::get_settings(state).await?;
 
-    // Create shared state
-    let server_state = Arc::new(Mutex::new(ServerState {
-        app_handle: app_handle.clone(),
-        is_running: true,
-    }));
+    if settings.spotify.client_id.is_empty() {
+        return Err(SoulsharkError::Auth("Spotify client ID is not set".to_string()));
+    }
 
-    // Store the server state in the global variable
-    {
-        let mut global_state = SERVER_STATE.lock().unwrap();
-        *global_state = Some(server_state.clone());
+    let redirect_uri = start_spotify_callback_server(app_handle.clone())?;
+
+    // Keep the verifier, state and redirect URI until the callback arrives
+    let pkce = Pkce::generate();
+    let auth_state = auth::generate_state();
+    let store = app_handle
+        .store(AUTH_STORE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to load store: {}", e)))?;
+    store.set("code_verifier", pkce.verifier.clone());
+    store.set("state", auth_state.clone());
+    store.set("redirect_uri", redirect_uri.clone());
+    store
+        .save()
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to save store: {}", e)))?;
+
+    auth::authorize_url(
+        &settings.endpoints.spotify_accounts_url,
+        &settings.spotify.client_id,
+        &redirect_uri,
+        &pkce.challenge,
+        Some(&auth_state),
+        add_account.unwrap_or(false),
+    )
+    .map_err(SoulsharkError::Settings)
+}
+
+// The error message may carry text from the callback URL
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
     }
+    escaped
+}
+
+fn html_response(html: String, status: u16) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(html)
+        .with_status_code(status)
+        .with_header(
+            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
+                .unwrap(),
+        )
+}
 
-    // Clone for the thread
-    let server_arc = Arc::new(server);
-    let state_clone = server_state.clone();
+// Bind on the loopback port of the redirect URI. No other port is tried:
+// Spotify only redirects to the URI registered for the app.
+fn bind_callback_server(redirect_uri: &str) -> Result<(Server, Url), SoulsharkError> {
+    let url = Url::parse(redirect_uri)
+        .map_err(|e| SoulsharkError::Validation(format!("Invalid redirect URI: {}", e)))?;
+    if url.scheme() != "http" {
+        return Err(SoulsharkError::Validation("The redirect URI must use http".to_string()));
+    }
+    if !matches!(url.host_str(), Some("localhost") | Some("127.0.0.1")) {
+        return Err(SoulsharkError::Validation(
+            "The redirect URI must point to localhost or 127.0.0.1".to_string(),
+        ));
+    }
+
+    let port = url.port_or_known_default().unwrap_or(80);
+    let server = Server::http(("127.0.0.1", port)).map_err(|e| {
+        let in_use = e
+            .downcast_ref::<std::io::Error>()
+            .map_or(false, |e| e.kind() == std::io::ErrorKind::AddrInUse);
+        if in_use {
+            SoulsharkError::PortInUse(format!(
+                "Port {} is in use, so the redirect URI {} can't be served. Close the program using the port, or change the redirect URI in the settings and in your Spotify app.",
+                port, url
+            ))
+        } else {
+            SoulsharkError::Network(format!("Failed to listen on port {} for the redirect URI {}: {}", port, url, e))
+        }
+    })?;
+    Ok((server, url))
+}
+
+/// Start the HTTP server for Spotify callback on the configured redirect URI.
+/// Returns the redirect URI it serves.
+#[tauri::command]
+pub fn start_spotify_callback_server(app_handle: AppHandle) -> Result<String, SoulsharkError> {
+    // Only one login at a time
+    stop_spotify_callback_server()?;
+
+    let settings = crate::settings::store::get_settings(app_handle.state::<SettingsState>())?;
+    let (server, redirect_url) = bind_callback_server(&settings.spotify.redirect_uri)?;
+    let redirect_uri = redirect_url.to_string();
+
+    let server = Arc::new(server);
+    *CALLBACK_SERVER.lock().unwrap() = Some(CallbackServer {
+        server: server.clone(),
+        redirect_uri: redirect_uri.clone(),
+    });
 
     // Start the server in a separate thread
     thread::spawn(move || {
-        println!("Spotify callback server started on http://localhost:5174");
+        tracing::info!("Spotify callback server started on {}", redirect_url);
 
-        for request in server_arc.incoming_requests() {
-            // Check if we should stop the server
-            {
-                let state = state_clone.lock().unwrap();
-                if !state.is_running {
-                    break;
-                }
-            }
+        // Ends as soon as the server is unblocked by stop_spotify_callback_server
+        for request in server.incoming_requests() {
+            let path = request.url().split('?').next().unwrap_or_default().to_string();
 
-            // Only handle GET requests to /callback
-            if request.method().as_str() != "GET" || !request.url().starts_with("/callback") {
+            // Only handle GET requests to the redirect path
+            if request.method().as_str() != "GET" || path != redirect_url.path() {
                 let response = Response::from_string("Not Found").with_status_code(404);
                 let _ = request.respond(response);
                 continue;
             }
 
-            // Parse the URL to get the query parameters
-            let url_string = format!("http://localhost:5174{}", request.url());
-            let url = match Url::parse(&url_string) {
-                Ok(url) => url,
-                Err(e) => {
-                    let error_html =
-                        ERROR_HTML.replace("ERROR_MESSAGE", &format!("Invalid URL: {}", e));
-                    let response = Response::from_string(error_html)
-                        .with_status_code(400)
-                        .with_header(
-                            tiny_http::Header::from_bytes(
-                                &b"Content-Type"[..],
-                                &b"text/html; charset=utf-8"[..],
-                            )
-                            .unwrap(),
-                        );
-                    let _ = request.respond(response);
-                    continue;
-                }
-            };
-
-            // Extract the code parameter
-            let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
-
-            // Check if there's an error
-            if let Some(error) = params.get("error") {
-                let error_html =
-                    ERROR_HTML.replace("ERROR_MESSAGE", &format!("Spotify error: {}", error));
-                let response = Response::from_string(error_html)
-                    .with_status_code(400)
-                    .with_header(
-                        tiny_http::Header::from_bytes(
-                            &b"Content-Type"[..],
-                            &b"text/html; charset=utf-8"[..],
-                        )
-                        .unwrap(),
+            match handle_callback(&app_handle, &redirect_url, request.url()) {
+                Ok(()) => {
+                    let _ = request.respond(html_response(SUCCESS_HTML.to_string(), 200));
+                    let _ = app_handle.emit("auth:completed", "spotify");
+                    let _ = app_handle.emit(
+                        "auth:changed",
+                        AuthChanged {
+                            service: AuthService::Spotify,
+                            logged_in: true,
+                        },
                     );
-                let _ = request.respond(response);
-                continue;
-            }
-
-            // Check if we have a code
-            let code = match params.get("code") {
-                Some(code) => code.clone(),
-                None => {
-                    let error_html = ERROR_HTML.replace(
-                        "ERROR_MESSAGE",
-                        "No authorization code found in the callback URL",
-                    );
-                    let response = Response::from_string(error_html)
-                        .with_status_code(400)
-                        .with_header(
-                            tiny_http::Header::from_bytes(
-                                &b"Content-Type"[..],
-                                &b"text/html; charset=utf-8"[..],
-                            )
-                            .unwrap(),
-                        );
-                    let _ = request.respond(response);
-                    continue;
-                }
-            };
-
-            // Get the app handle from the state
-            let app_handle = {
-                let state = state_clone.lock().unwrap();
-                state.app_handle.clone()
-            };
-
-            // Get the code verifier from the store
-            let store = match app_handle.store("spotify-auth.json") {
-                Ok(store) => store,
-                Err(e) => {
-                    let error_html = ERROR_HTML
-                        .replace("ERROR_MESSAGE", &format!("Failed to load store: {}", e));
-                    let response = Response::from_string(error_html)
-                        .with_status_code(500)
-                        .with_header(
-                            tiny_http::Header::from_bytes(
-                                &b"Content-Type"[..],
-                                &b"text/html; charset=utf-8"[..],
-                            )
-                            .unwrap(),
-                        );
-                    let _ = request.respond(response);
-                    continue;
-                }
-            };
-
-            // Get the code verifier
-            let code_verifier = match store.get("code_verifier") {
-                Some(verifier) => match verifier.as_str() {
-                    Some(v) => v.to_string(),
-                    None => {
-                        let error_html =
-                            ERROR_HTML.replace("ERROR_MESSAGE", "Invalid code verifier format");
-                        let response = Response::from_string(error_html)
-                            .with_status_code(500)
-                            .with_header(
-                                tiny_http::Header::from_bytes(
-                                    &b"Content-Type"[..],
-                                    &b"text/html; charset=utf-8"[..],
-                                )
-                                .unwrap(),
-                            );
-                        let _ = request.respond(response);
-                        continue;
-                    }
-                },
-                None => {
-                    let error_html = ERROR_HTML.replace(
-                        "ERROR_MESSAGE",
-                        "No code verifier found. Please try authenticating again.",
-                    );
-                    let response = Response::from_string(error_html)
-                        .with_status_code(400)
-                        .with_header(
-                            tiny_http::Header::from_bytes(
-                                &b"Content-Type"[..],
-                                &b"text/html; charset=utf-8"[..],
-                            )
-                            .unwrap(),
-                        );
-                    let _ = request.respond(response);
-                    continue;
+                    break;
                 }
-            };
-
-            // Clear the code verifier from the store
-            let _ = store.delete("code_verifier");
-            let _ = store.save();
-
-            // Exchange the code for tokens using our helper function
-            let result =
-                exchange_code_blocking(app_handle.clone(), code.clone(), code_verifier.clone());
-
-            // Send the response
-            let response = match result {
-                Ok(_) => Response::from_string(SUCCESS_HTML).with_header(
-                    tiny_http::Header::from_bytes(
-                        &b"Content-Type"[..],
-                        &b"text/html; charset=utf-8"[..],
-                    )
-                    .unwrap(),
-                ),
                 Err(e) => {
-                    let error_html = ERROR_HTML
-                        .replace("ERROR_MESSAGE", &format!("Failed to exchange code: {}", e));
-                    Response::from_string(error_html)
-                        .with_status_code(500)
-                        .with_header(
-                            tiny_http::Header::from_bytes(
-                                &b"Content-Type"[..],
-                                &b"text/html; charset=utf-8"[..],
-                            )
-                            .unwrap(),
-                        )
+                    tracing::warn!("Spotify login failed: {}", e);
+                    let html = ERROR_HTML.replace("ERROR_MESSAGE", &escape_html(e.message()));
+                    let _ = request.respond(html_response(html, 400));
+                    let _ = app_handle.emit("auth:failed", e);
                 }
-            };
+            }
+        }
 
-            let _ = request.respond(response);
+        // Clear the global state unless another server has replaced it
+        let mut guard = CALLBACK_SERVER.lock().unwrap();
+        if guard.as_ref().map_or(false, |current| Arc::ptr_eq(&current.server, &server)) {
+            *guard = None;
         }
 
-        println!("Spotify callback server stopped");
+        tracing::info!("Spotify callback server stopped");
     });
 
-    Ok(())
+    Ok(redirect_uri)
 }
 
-// Helper function to exchange the code for tokens in a blocking way
-fn exchange_code_blocking(
-    app_handle: AppHandle,
-    code: String,
-    code_verifier: String,
-) -> Result<(), String> {
-    // Create a new runtime for this thread
-    let rt = tokio::runtime::Builder::new_current_thread()
-        .enable_all()
-        .build()
-        .unwrap();
-
-    // Run the async code in the runtime
-    rt.block_on(async {
-        // Get the settings state
-        let settings_state = app_handle.state::<SettingsState>();
-
-        // Get the app settings
-        let settings = match crate::commands::settings::get_settings(settings_state.clone()).await {
-            Ok(s) => s,
-            Err(e) => return Err(format!("Failed to get settings: {}", e)),
-        };
-
-        // Get the current credentials
-        let credentials = match crate::commands::settings::get_credentials(app_handle.clone()).await
-        {
-            Ok(c) => c,
-            Err(e) => return Err(format!("Failed to get credentials: {}", e)),
-        };
-
-        // Build the token request
-        let client = Client::new();
-
-        // Get the client secret
-        let client_secret = match &credentials.spotify_client_secret {
-            Some(secret) if !secret.is_empty() => secret.as_str(),
-            _ => return Err("Spotify client secret is not set".to_string()),
-        };
-
-        let params = [
-            ("client_id", settings.spotify.client_id.as_str()),
-            ("client_secret", client_secret),
-            ("grant_type", "authorization_code"),
-            ("code", code.as_str()),
-            ("redirect_uri", "http://localhost:5174/callback"),
-            ("code_verifier", code_verifier.as_str()),
-        ];
-
-        // Send the token request
-        let response = match client
-            .post("https://accounts.spotify.com/api/token")
-            .form(&params)
-            .send()
-            .await
-        {
-            Ok(r) => r,
-            Err(e) => return Err(format!("Failed to send token request: {}", e)),
-        };
-
-        // Check if the request was successful
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(format!("Token request failed: {}", error_text));
-        }
+// Validate a callback request and exchange its code for tokens
+fn handle_callback(app_handle: &AppHandle, redirect_url: &Url, request_url: &str) -> Result<(), SoulsharkError> {
+    let url = redirect_url
+        .join(request_url)
+        .map_err(|e| SoulsharkError::Validation(format!("Invalid URL: {}", e)))?;
+    let params: std::collections::HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+    // Check if there's an error
+    if let Some(error) = params.get("error") {
+        return Err(SoulsharkError::Auth(format!("Spotify error: {}", error)));
+    }
 
-        // Parse the response
-        let token_response: TokenResponse = match response.json().await {
-            Ok(t) => t,
-            Err(e) => return Err(format!("Failed to parse token response: {}", e)),
-        };
-
-        // Calculate the expiration time
-        let expires_at = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            + token_response.expires_in;
-
-        // Update the credentials
-        let updated_credentials = Credentials {
-            soulseek_password: credentials.soulseek_password,
-            spotify_client_secret: credentials.spotify_client_secret,
-            spotify_access_token: Some(token_response.access_token),
-            spotify_refresh_token: Some(token_response.refresh_token),
-            spotify_token_expires_at: Some(expires_at),
-        };
-
-        // Save the updated credentials
-        match crate::commands::settings::save_credentials(app_handle, updated_credentials).await {
-            Ok(_) => Ok(()),
-            Err(e) => Err(format!("Failed to save credentials: {}", e)),
+    let store = app_handle
+        .store(AUTH_STORE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to load store: {}", e)))?;
+    let stored = |key: &str| store.get(key).and_then(|value| value.as_str().map(str::to_string));
+
+    // The state must match the one sent with the authorize request, otherwise
+    // the callback didn't come from a login started here
+    match (stored("state"), params.get("state")) {
+        (Some(expected), Some(state)) if !expected.is_empty() && &expected == state => {}
+        _ => {
+            return Err(SoulsharkError::Auth(
+                "Invalid state parameter. Please try authenticating again.".to_string(),
+            ))
         }
-    })
+    }
+
+    let code = params
+        .get("code")
+        .cloned()
+        .ok_or_else(|| SoulsharkError::Auth("No authorization code found in the callback URL".to_string()))?;
+    let code_verifier = stored("code_verifier").ok_or_else(|| {
+        SoulsharkError::Auth("No code verifier found. Please try authenticating again.".to_string())
+    })?;
+    let redirect_uri = stored("redirect_uri").unwrap_or_else(|| redirect_url.to_string());
+
+    // The login is consumed whether or not the exchange succeeds
+    store.delete("code_verifier");
+    store.delete("state");
+    store.delete("redirect_uri");
+    let _ = store.save();
+
+    // The server thread isn't async, so wait for the exchange here
+    let token_manager = app_handle.state::<SpotifyTokenManager>();
+    tauri::async_runtime::block_on(token_manager.exchange_code(app_handle, &code, &code_verifier, &redirect_uri))
+        .map_err(SoulsharkError::Auth)?;
+    Ok(())
 }
 
 /// Stop the Spotify callback server
 #[tauri::command]
-pub fn stop_spotify_callback_server() -> Result<(), String> {
-    // Get the server state
-    let mut guard = SERVER_STATE.lock().unwrap();
-
-    // If the server is running, stop it
-    if let Some(state_arc) = guard.take() {
-        // Set the is_running flag to false
-        let mut state = state_arc.lock().unwrap();
-        state.is_running = false;
-
-        println!("Spotify callback server stopping...");
+pub fn stop_spotify_callback_server() -> Result<(), SoulsharkError> {
+    // Unblocking ends the accept loop right away
+    if let Some(callback_server) = CALLBACK_SERVER.lock().unwrap().take() {
+        callback_server.server.unblock();
+        tracing::info!("Spotify callback server stopping...");
     }
 
     Ok(())
 }
 
-/// Refresh the Spotify access token
+/// Refresh the access token of a Spotify account, the active one if no
+/// account is given
 #[tauri::command]
-pub async fn refresh_spotify_token(
-    app_handle: AppHandle,
-    state: State<'_, SettingsState>,
-) -> Result<(), String> {
-    // Get the app settings
-    let settings = crate::commands::settings::get_settings(state.clone())
-        .await
-        .map_err(|e| format!("Failed to get settings: {}", e))?;
-
-    // Get the current credentials
-    let credentials = crate::commands::settings::get_credentials(app_handle.clone())
+pub async fn refresh_spotify_token(app_handle: AppHandle, account_id: Option<String>) -> Result<(), SoulsharkError> {
+    app_handle
+        .state::<SpotifyTokenManager>()
+        .refresh(&app_handle, account_id.as_deref())
         .await
-        .map_err(|e| format!("Failed to get credentials: {}", e))?;
-
-    // Check if we have a refresh token
-    let refresh_token = match credentials.spotify_refresh_token {
-        Some(token) => token,
-        None => return Err("No refresh token available".to_string()),
-    };
+        .map_err(SoulsharkError::Auth)?;
+    Ok(())
+}
 
-    // Build the token request
-    let client = Client::new();
+// Linked account as shown in the UI, without its tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct SpotifyAccountSummary {
+    pub id: String,
+    pub display_name: String,
+    pub active: bool,
+}
 
-    // Get the client secret
-    let client_secret = match &credentials.spotify_client_secret {
-        Some(secret) if !secret.is_empty() => secret.as_str(),
-        _ => return Err("Spotify client secret is not set".to_string()),
-    };
+fn account_summaries(credentials: &Credentials) -> Vec<SpotifyAccountSummary> {
+    credentials
+        .spotify_accounts
+        .iter()
+        .map(|account| SpotifyAccountSummary {
+            id: account.id.clone(),
+            display_name: account.display_name.clone(),
+            active: credentials.active_spotify_account.as_ref() == Some(&account.id),
+        })
+        .collect()
+}
 
-    let params = [
-        ("client_id", settings.spotify.client_id.as_str()),
-        ("client_secret", client_secret),
-        ("grant_type", "refresh_token"),
-        ("refresh_token", refresh_token.as_str()),
-    ];
-
-    // Send the token request
-    let response = client
-        .post("https://accounts.spotify.com/api/token")
-        .form(&params)
-        .send()
+/// List the linked Spotify accounts. Accounts linked before accounts had
+/// names are looked up first.
+#[tauri::command]
+pub async fn list_spotify_accounts(app_handle: AppHandle) -> Result<Vec<SpotifyAccountSummary>, SoulsharkError> {
+    if let Err(e) = app_handle
+        .state::<SpotifyTokenManager>()
+        .identify_accounts(&app_handle)
         .await
-        .map_err(|e| format!("Failed to send token request: {}", e))?;
-
-    // Check if the request was successful
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(format!("Token request failed: {}", error_text));
+    {
+        tracing::warn!("Failed to look up the Spotify profile: {}", e);
     }
 
-    // Parse the response
-    let token_response: TokenResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse token response: {}", e))?;
-
-    // Calculate the expiration time
-    let expires_at = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        + token_response.expires_in;
-
-    // Update the credentials
-    let updated_credentials = Credentials {
-        soulseek_password: credentials.soulseek_password,
-        spotify_client_secret: credentials.spotify_client_secret,
-        spotify_access_token: Some(token_response.access_token),
-        spotify_refresh_token: Some(token_response.refresh_token),
-        spotify_token_expires_at: Some(expires_at),
-    };
+    let credentials = crate::settings::store::get_credentials(&app_handle).await?;
+    Ok(account_summaries(&credentials))
+}
 
-    // Save the updated credentials
-    crate::commands::settings::save_credentials(app_handle, updated_credentials)
-        .await
-        .map_err(|e| format!("Failed to save credentials: {}", e))?;
+/// Make another linked Spotify account the active one, used by default for
+/// browsing and downloads
+#[tauri::command]
+pub async fn switch_spotify_account(
+    app_handle: AppHandle,
+    account_id: String,
+) -> Result<Vec<SpotifyAccountSummary>, SoulsharkError> {
+    let mut credentials = crate::settings::store::get_credentials(&app_handle).await?;
+    if credentials.spotify_account(Some(&account_id)).is_none() {
+        return Err(SoulsharkError::NotFound(format!("Unknown Spotify account: {}", account_id)));
+    }
 
-    Ok(())
+    credentials.active_spotify_account = Some(account_id.clone());
+    crate::settings::store::save_credentials(&app_handle, credentials.clone()).await?;
+
+    let _ = app_handle.emit("spotify:account_switched", &account_id);
+    Ok(account_summaries(&credentials))
 }
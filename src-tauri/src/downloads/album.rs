@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Formats whose bitrate follows the audio, so differing bitrates are expected
+const LOSSLESS_FORMATS: &[&str] = &["flac", "wav", "aiff", "alac"];
+
+// Spread of bitrates tolerated within a lossy album, for VBR encodes
+const VBR_TOLERANCE_KBPS: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlbumStatus {
+    // Looking for a folder with the whole tracklist
+    Searching,
+    Downloading,
+    // Every track came in one format at a consistent bitrate
+    Complete,
+    // Every track came, but formats or bitrates differ
+    Mismatched,
+    // Tracks are missing and falling back wasn't allowed
+    Incomplete,
+    // No folder had the whole album
+    NotFound,
+    // No folder had the whole album, so the tracks are downloaded one by one
+    FellBack,
+}
+
+// A track downloaded as part of the album
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumTrack {
+    pub path: String,
+    pub format: String,
+    pub bitrate_kbps: u32,
+}
+
+// Album-level state of a download run in album mode, where sldl picks a
+// single peer folder holding the whole tracklist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlbumMode {
+    pub status: AlbumStatus,
+    // Length of the Spotify tracklist, if known
+    pub expected_tracks: Option<usize>,
+    // Whether to download the tracks one by one when no folder has the album
+    pub allow_fallback: bool,
+    pub tracks: Vec<AlbumTrack>,
+    // What is wrong with the downloaded album, if anything
+    #[serde(default)]
+    pub problems: Vec<String>,
+}
+
+impl AlbumMode {
+    pub fn new(expected_tracks: Option<usize>, allow_fallback: bool) -> Self {
+        Self {
+            status: AlbumStatus::Searching,
+            expected_tracks,
+            allow_fallback,
+            tracks: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    // Whether sldl still runs in album mode, i.e. hasn't fallen back
+    pub fn is_active(&self) -> bool {
+        self.status != AlbumStatus::FellBack
+    }
+
+    pub fn record_track(&mut self, path: &str, bitrate_kbps: u32) {
+        let format = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        self.tracks.push(AlbumTrack {
+            path: path.to_string(),
+            format,
+            bitrate_kbps,
+        });
+    }
+
+    // Whether the album run got the whole tracklist
+    pub fn has_all_tracks(&self) -> bool {
+        match self.expected_tracks {
+            Some(expected) => self.tracks.len() >= expected,
+            None => !self.tracks.is_empty(),
+        }
+    }
+
+    // Whether a finished album run should be retried track by track
+    pub fn should_fall_back(&self, success: bool) -> bool {
+        self.allow_fallback && self.is_active() && (!success || !self.has_all_tracks())
+    }
+
+    pub fn fall_back(&mut self) {
+        self.status = AlbumStatus::FellBack;
+        self.problems = vec!["No folder had the whole album, downloading the tracks one by one".to_string()];
+        self.tracks.clear();
+    }
+
+    // Check what an album run downloaded against the tracklist and for
+    // consistent formats and bitrates, settling the album status
+    pub fn verify(&mut self) {
+        if !self.is_active() {
+            return;
+        }
+
+        self.problems.clear();
+        if self.tracks.is_empty() {
+            self.status = AlbumStatus::NotFound;
+            self.problems.push("No folder with the whole album was found".to_string());
+            return;
+        }
+        if !self.has_all_tracks() {
+            self.status = AlbumStatus::Incomplete;
+            self.problems.push(format!(
+                "Got {} of {} tracks",
+                self.tracks.len(),
+                self.expected_tracks.unwrap_or_default()
+            ));
+            return;
+        }
+
+        let mut formats: Vec<&str> = self.tracks.iter().map(|track| track.format.as_str()).collect();
+        formats.sort();
+        formats.dedup();
+        if formats.len() > 1 {
+            self.problems.push(format!("Mixed formats: {}", formats.join(", ")));
+        } else if !formats.iter().any(|format| LOSSLESS_FORMATS.contains(format)) {
+            let bitrates = self.tracks.iter().map(|track| track.bitrate_kbps);
+            let (min, max) = (bitrates.clone().min().unwrap_or(0), bitrates.max().unwrap_or(0));
+            if max - min > VBR_TOLERANCE_KBPS {
+                self.problems.push(format!("Bitrates range from {} to {} kbps", min, max));
+            }
+        }
+
+        self.status = if self.problems.is_empty() {
+            AlbumStatus::Complete
+        } else {
+            AlbumStatus::Mismatched
+        };
+    }
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+// Index file sldl leaves in every folder it downloads to
+const INDEX_FILE: &str = "_index.sldl";
+
+// Folder in the app data dir that removed files are moved to
+const TRASH_DIR: &str = "trash";
+
+// Log of every file moved to the trash and folder removed
+const AUDIT_LOG_FILE: &str = "cleanup_audit.log";
+
+// Names directly under the downloads path before a job started. Only the top
+// level is read; the job's own output says which folders it wrote to.
+#[derive(Debug, Default)]
+pub struct DirSnapshot {
+    entries: HashSet<OsString>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupActionKind {
+    // Move a file to the trash folder
+    Trash,
+    // Remove an empty folder
+    RemoveDir,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupAction {
+    pub kind: CleanupActionKind,
+    pub path: String,
+}
+
+// A line of the audit log
+#[derive(Debug, Serialize)]
+struct AuditEntry<'a> {
+    timestamp: String,
+    download_id: &'a str,
+    action: CleanupActionKind,
+    path: &'a str,
+    // Where a trashed file went
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trash_path: Option<String>,
+}
+
+// Record what the downloads path holds at its top level
+pub fn snapshot(root: &Path) -> DirSnapshot {
+    let entries = fs::read_dir(root)
+        .map(|entries| entries.flatten().map(|entry| entry.file_name()).collect())
+        .unwrap_or_default();
+    DirSnapshot { entries }
+}
+
+// Folders under the downloads path the job created, from the file paths its
+// InProgress and Succeeded lines reported: the top folder of each file, unless
+// it was there before the job. Folders of other jobs are never included, and
+// remote paths (`user\folder\file`) don't match a local folder.
+pub fn created_dirs<'a>(
+    root: &Path,
+    before: &DirSnapshot,
+    reported_paths: impl IntoIterator<Item = &'a String>,
+) -> Vec<PathBuf> {
+    let mut created = BTreeSet::new();
+    for reported in reported_paths {
+        let path = root.join(reported);
+        let mut components = match path.strip_prefix(root) {
+            Ok(relative) => relative.components(),
+            Err(_) => continue,
+        };
+        let top = match components.next() {
+            Some(Component::Normal(top)) => top,
+            _ => continue,
+        };
+        // A file right in the downloads path has no folder of its own
+        if components.next().is_none() || before.entries.contains(top) {
+            continue;
+        }
+
+        let dir = root.join(top);
+        if dir.is_dir() {
+            created.insert(dir);
+        }
+    }
+    created.into_iter().collect()
+}
+
+// Work out what cleaning up the given folders would do: index files go to the
+// trash, then folders left empty are removed, innermost first. Nothing
+// outside the folders is touched.
+pub fn plan(created_dirs: &[PathBuf]) -> Vec<CleanupAction> {
+    let mut actions = Vec::new();
+
+    for dir in created_dirs {
+        if dir.is_dir() {
+            plan_dir(dir, &mut actions);
+        }
+    }
+
+    actions
+}
+
+// Plan a folder's contents, returning whether the folder ends up empty
+fn plan_dir(dir: &Path, actions: &mut Vec<CleanupAction>) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    let mut empty = true;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {
+                if !plan_dir(&path, actions) {
+                    empty = false;
+                }
+            }
+            Ok(file_type) if file_type.is_file() && is_index_file(&path) => {
+                actions.push(CleanupAction {
+                    kind: CleanupActionKind::Trash,
+                    path: path.to_string_lossy().to_string(),
+                });
+            }
+            _ => empty = false,
+        }
+    }
+
+    if empty {
+        actions.push(CleanupAction {
+            kind: CleanupActionKind::RemoveDir,
+            path: dir.to_string_lossy().to_string(),
+        });
+    }
+    empty
+}
+
+// The index file sldl writes into the downloads path itself for single
+// tracks, if it wasn't there before the job. Index files in other folders
+// that already existed are kept.
+pub fn new_index_files(root: &Path, before: &DirSnapshot) -> Vec<CleanupAction> {
+    let path = root.join(INDEX_FILE);
+    if !path.is_file() || before.entries.contains(OsStr::new(INDEX_FILE)) {
+        return Vec::new();
+    }
+
+    vec![CleanupAction {
+        kind: CleanupActionKind::Trash,
+        path: path.to_string_lossy().to_string(),
+    }]
+}
+
+// Carry out a plan, recording each step in the audit log. Files are moved to
+// trash/<time>-<download id>/ keeping their path relative to the downloads
+// path; folders are only removed if they are still empty. Returns the actions
+// that were done.
+pub fn execute(
+    app_data_dir: &Path,
+    root: &Path,
+    download_id: &str,
+    actions: &[CleanupAction],
+) -> Result<Vec<CleanupAction>, String> {
+    let trash_dir = app_data_dir.join(TRASH_DIR).join(format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S"),
+        download_id
+    ));
+    let mut done = Vec::new();
+
+    for action in actions {
+        let path = Path::new(&action.path);
+
+        // Never act outside the downloads path
+        if !path.starts_with(root) || path == root {
+            tracing::warn!("Skipping cleanup outside the downloads path: {}", action.path);
+            continue;
+        }
+
+        let result = match action.kind {
+            CleanupActionKind::Trash => {
+                let relative = path.strip_prefix(root).unwrap_or(path);
+                let target = trash_dir.join(relative);
+                move_file(path, &target).map(|_| Some(target.to_string_lossy().to_string()))
+            }
+            CleanupActionKind::RemoveDir => fs::remove_dir(path)
+                .map(|_| None)
+                .map_err(|e| format!("Failed to remove {}: {}", action.path, e)),
+        };
+
+        match result {
+            Ok(trash_path) => {
+                record(
+                    app_data_dir,
+                    &AuditEntry {
+                        timestamp: chrono::Utc::now().to_rfc3339(),
+                        download_id,
+                        action: action.kind,
+                        path: &action.path,
+                        trash_path,
+                    },
+                )?;
+                done.push(action.clone());
+            }
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+
+    Ok(done)
+}
+
+// Rename into the trash, copying when it is on another file system
+fn move_file(path: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create trash folder: {}", e))?;
+    }
+
+    if fs::rename(path, target).is_err() {
+        fs::copy(path, target).map_err(|e| format!("Failed to move {} to the trash: {}", path.display(), e))?;
+        fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+fn record(app_data_dir: &Path, entry: &AuditEntry) -> Result<(), String> {
+    fs::create_dir_all(app_data_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(app_data_dir.join(AUDIT_LOG_FILE))
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write audit log: {}", e))
+}
+
+fn is_index_file(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| name == INDEX_FILE)
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::time::Duration;
+
+pub const MB: u64 = 1024 * 1024;
+const GB: u64 = 1024 * MB;
+
+// Rough size of one track in each format, in MB
+const AVERAGE_TRACK_MB: &[(&str, u64)] = &[
+    ("flac", 30),
+    ("alac", 28),
+    ("wav", 45),
+    ("aiff", 45),
+    ("mp3", 10),
+    ("m4a", 7),
+    ("aac", 7),
+    ("ogg", 5),
+    ("opus", 5),
+];
+
+// Used when no format is preferred or it isn't listed above
+const DEFAULT_TRACK_MB: u64 = 30;
+
+// How often a running download checks the free space
+pub const MONITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+// Average size of a track in the first preferred format
+pub fn average_track_size(preferred_format: &str) -> u64 {
+    let format = preferred_format
+        .split(',')
+        .map(str::trim)
+        .find(|format| !format.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    AVERAGE_TRACK_MB
+        .iter()
+        .find(|(name, _)| *name == format)
+        .map_or(DEFAULT_TRACK_MB, |(_, size)| *size)
+        * MB
+}
+
+// Space a job of the given number of tracks is likely to need
+pub fn estimate_required(track_count: usize, preferred_format: &str) -> u64 {
+    average_track_size(preferred_format).saturating_mul(track_count as u64)
+}
+
+// Free space on the volume holding a path. The downloads path may not exist
+// yet, so the nearest existing parent is used.
+pub fn free_space(path: &Path) -> Result<u64, String> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| format!("No existing folder found for {}", path.display()))?;
+
+    fs2::available_space(existing)
+        .map_err(|e| format!("Failed to read the free space of {}: {}", existing.display(), e))
+}
+
+// Check that a job fits on the volume with the minimum free space left over.
+// Returns why it doesn't, or an error if the free space can't be read.
+pub fn check_space(path: &Path, required: u64, min_free: u64) -> Result<Option<String>, String> {
+    let free = free_space(path)?;
+    if free < required.saturating_add(min_free) {
+        return Ok(Some(format!(
+            "Not enough disk space: about {} needed and {} kept free, but only {} available",
+            format_size(required),
+            format_size(min_free),
+            format_size(free)
+        )));
+    }
+    Ok(None)
+}
+
+// Human readable size, e.g. "1.5 GB" or "300 MB"
+pub fn format_size(bytes: u64) -> String {
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else {
+        format!("{} MB", bytes / MB)
+    }
+}
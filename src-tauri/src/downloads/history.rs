@@ -0,0 +1,42 @@
+use crate::downloads::Download;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HISTORY_FILE: &str = "download_history.json";
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+fn history_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(HISTORY_FILE)
+}
+
+// Load finished downloads, oldest first
+pub fn load_history(app_data_dir: &Path) -> Result<Vec<Download>, String> {
+    let path = history_path(app_data_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read history: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse history: {}", e))
+}
+
+// Record a finished download. Console logs are dropped to keep the file small.
+pub fn record_download(app_data_dir: &Path, download: &Download) -> Result<(), String> {
+    let mut history = load_history(app_data_dir).unwrap_or_default();
+
+    let mut entry = download.clone();
+    entry.console_logs.clear();
+    history.retain(|d| d.id != entry.id);
+    history.push(entry);
+
+    // Keep only the most recent entries
+    if history.len() > MAX_HISTORY_ENTRIES {
+        let excess = history.len() - MAX_HISTORY_ENTRIES;
+        history.drain(0..excess);
+    }
+
+    fs::create_dir_all(app_data_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let data = serde_json::to_string_pretty(&history)
+        .map_err(|e| format!("Failed to serialize history: {}", e))?;
+    fs::write(history_path(app_data_dir), data).map_err(|e| format!("Failed to write history: {}", e))
+}
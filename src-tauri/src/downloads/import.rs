@@ -0,0 +1,162 @@
+use crate::spotify::link::SpotifyLink;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+
+// A single wanted item parsed from an import list
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportEntry {
+    pub query: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+impl ImportEntry {
+    fn from_link(link: &str) -> Self {
+        // Canonical Spotify URLs let the same item pasted as a URI and a URL de-duplicate
+        let query = SpotifyLink::parse(link)
+            .map(|link| link.url())
+            .unwrap_or_else(|| link.to_string());
+
+        Self {
+            query,
+            title: None,
+            artist: None,
+            album: None,
+        }
+    }
+
+    fn from_track(artist: Option<&str>, title: &str, album: Option<&str>) -> Self {
+        let artist = artist.map(str::trim).filter(|a| !a.is_empty());
+        let title = title.trim();
+        let query = match artist {
+            Some(artist) => format!("{} - {}", artist, title),
+            None => title.to_string(),
+        };
+
+        Self {
+            query,
+            title: Some(title.to_string()),
+            artist: artist.map(str::to_string),
+            album: album.map(str::trim).filter(|a| !a.is_empty()).map(str::to_string),
+        }
+    }
+}
+
+// Header names recognised in CSV exports from other services
+const ARTIST_COLUMNS: &[&str] = &["artist", "artists", "artist name", "artist name(s)", "album artist"];
+const TITLE_COLUMNS: &[&str] = &["title", "track", "track name", "name", "song"];
+const ALBUM_COLUMNS: &[&str] = &["album", "album name", "release"];
+
+fn link_regex() -> Regex {
+    Regex::new(
+        r"(https?://(?:open\.spotify\.com|spotify\.link|(?:www\.|m\.|music\.)?youtube\.com|youtu\.be)/\S+|spotify:(?:user:[^:\s]+:)?[a-z]+:[A-Za-z0-9]+)",
+    )
+    .unwrap()
+}
+
+// Parse an import file based on its extension
+pub fn parse_import_file(path: &Path) -> Result<Vec<ImportEntry>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    let entries = match extension.as_str() {
+        "csv" => parse_csv(&content)?,
+        "txt" => parse_text(&content, true),
+        // Any other file only contributes the links it contains
+        _ => parse_text(&content, false),
+    };
+
+    Ok(dedupe(entries))
+}
+
+// Parse text with one link or "Artist - Title" per line. When `allow_queries`
+// is false, lines without a link are ignored.
+pub fn parse_text(content: &str, allow_queries: bool) -> Vec<ImportEntry> {
+    let link_re = link_regex();
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+
+        let links: Vec<&str> = link_re.find_iter(line).map(|m| m.as_str()).collect();
+        if !links.is_empty() {
+            entries.extend(links.into_iter().map(ImportEntry::from_link));
+            continue;
+        }
+
+        if !allow_queries {
+            continue;
+        }
+
+        match line.split_once(" - ") {
+            Some((artist, title)) => entries.push(ImportEntry::from_track(Some(artist), title, None)),
+            None => entries.push(ImportEntry::from_track(None, line, None)),
+        }
+    }
+
+    entries
+}
+
+// Parse a CSV export with artist/title/album columns
+pub fn parse_csv(content: &str) -> Result<Vec<ImportEntry>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read CSV header: {}", e))?
+        .clone();
+
+    let find_column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|h| names.contains(&h.to_lowercase().as_str()))
+    };
+
+    let title_col = find_column(TITLE_COLUMNS)
+        .ok_or_else(|| "CSV file has no title column".to_string())?;
+    let artist_col = find_column(ARTIST_COLUMNS);
+    let album_col = find_column(ALBUM_COLUMNS);
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Failed to read CSV row: {}", e))?;
+
+        let title = match record.get(title_col) {
+            Some(title) if !title.is_empty() => title,
+            _ => continue,
+        };
+
+        // Exports list several artists separated by ';' or ','; the first is enough to search
+        let artist = artist_col
+            .and_then(|col| record.get(col))
+            .and_then(|artists| artists.split(|c: char| c == ';' || c == ',').next());
+        let album = album_col.and_then(|col| record.get(col));
+
+        entries.push(ImportEntry::from_track(artist, title, album));
+    }
+
+    Ok(entries)
+}
+
+// Drop repeated queries, keeping the first occurrence
+pub fn dedupe(entries: Vec<ImportEntry>) -> Vec<ImportEntry> {
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .filter(|entry| seen.insert(entry.query.to_lowercase()))
+        .collect()
+}
@@ -1,7 +1,10 @@
+use crate::error::{self, SoulsharkError};
+use album::{AlbumMode, AlbumStatus};
+use crate::sldl::parser::SldlLine;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime};
 use uuid::Uuid;
 
 // Download status enum
@@ -11,8 +14,21 @@ pub enum DownloadStatus {
     Searching,
     InProgress,
     Completed,
-    Failed(String),
+    Failed(#[serde(deserialize_with = "error::deserialize_failure")] SoulsharkError),
     Canceled,
+    // Stopped with the reason, e.g. low disk space, until resumed
+    Paused(String),
+}
+
+impl DownloadStatus {
+    // Whether the download has reached a final state
+    pub fn is_finished(&self) -> bool {
+        matches!(self, DownloadStatus::Completed | DownloadStatus::Canceled | DownloadStatus::Failed(_))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(self, DownloadStatus::Paused(_))
+    }
 }
 
 // Download struct to track individual downloads
@@ -32,6 +48,19 @@ pub struct Download {
     pub completed_tracks: Option<usize>,
     pub failed_tracks: Option<usize>,
     pub console_logs: Vec<String>,
+    // Set on jobs that belong to an aggregate job, e.g. albums of an artist
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    // Spotify account whose token the job uses, the active one if None
+    #[serde(default)]
+    pub account_id: Option<String>,
+    // Folders under the downloads path this job created, the only ones its
+    // cleanup may touch
+    #[serde(default)]
+    pub created_dirs: Vec<String>,
+    // Set on album downloads, which look for one folder with the whole album
+    #[serde(default)]
+    pub album_mode: Option<AlbumMode>,
 }
 
 impl Download {
@@ -51,6 +80,10 @@ impl Download {
             completed_tracks: None,
             failed_tracks: None,
             console_logs: Vec::new(),
+            parent_id: None,
+            account_id: None,
+            created_dirs: Vec::new(),
+            album_mode: None,
         }
     }
 
@@ -94,6 +127,154 @@ impl Download {
         }
     }
     
+    // Apply a parsed line of sldl output to this download, returning the name
+    // of the event to emit if anything changed
+    pub fn apply_sldl_line(&mut self, line: &SldlLine) -> Option<&'static str> {
+        match line {
+            SldlLine::TrackCount(count) => {
+                self.set_playlist_info(*count);
+                Some("download:progress")
+            }
+            SldlLine::LoadingPlaylist => {
+                self.update_status(DownloadStatus::Searching);
+                Some("download:progress")
+            }
+            SldlLine::PlaylistName { name, creator } => {
+                // Update the download title with the actual playlist name
+                self.title = format!("{} by {}", name, creator);
+                Some("download:progress")
+            }
+            SldlLine::Searching(track_name) => {
+                self.update_status(DownloadStatus::Searching);
+
+                // If this is a single track download, update the title with the actual track name
+                if !self.is_playlist {
+                    self.title = track_name.clone();
+                }
+                Some("download:progress")
+            }
+            SldlLine::Initialize(_) => {
+                self.update_status(DownloadStatus::InProgress);
+                if let Some(album) = self.album_mode.as_mut().filter(|album| album.is_active()) {
+                    album.status = AlbumStatus::Downloading;
+                }
+
+                // Only set progress to 0 for single downloads
+                // For playlists, we track progress by completed/total
+                if !self.is_playlist {
+                    self.update_progress(0.0);
+                }
+                Some("download:progress")
+            }
+            SldlLine::InProgress(file_path) => {
+                // For single downloads, set progress to 0.5 (50%)
+                if !self.is_playlist {
+                    self.update_progress(0.5);
+                }
+                self.set_file_path(file_path.clone());
+                Some("download:progress")
+            }
+            SldlLine::NotFound(_) => {
+                // For playlists, increment failed tracks
+                if self.is_playlist {
+                    self.increment_failed_tracks();
+                    Some("download:progress")
+                } else {
+                    None
+                }
+            }
+            SldlLine::Succeeded { path: file_path, bitrate_kbps, .. } => {
+                if let Some(album) = self.album_mode.as_mut().filter(|album| album.is_active()) {
+                    album.record_track(file_path, *bitrate_kbps);
+                }
+
+                if self.is_playlist {
+                    self.increment_completed_tracks();
+
+                    // Fallback: if playlist has only 1 track, mark as completed immediately
+                    if let (Some(total), Some(completed), Some(failed)) =
+                        (self.total_tracks, self.completed_tracks, self.failed_tracks)
+                    {
+                        if total == 1 && completed + failed >= 1 {
+                            self.update_status(DownloadStatus::Completed);
+                            self.update_progress(1.0);
+                            return Some("download:completed");
+                        }
+                    }
+                    Some("download:progress")
+                } else {
+                    // For single downloads, mark as completed
+                    self.update_status(DownloadStatus::Completed);
+                    self.update_progress(1.0);
+                    self.set_file_path(file_path.clone());
+                    Some("download:completed")
+                }
+            }
+            SldlLine::Completed { succeeded, failed } => {
+                // Update final counts
+                self.completed_tracks = Some(*succeeded);
+                self.failed_tracks = Some(*failed);
+
+                self.update_status(DownloadStatus::Completed);
+                self.update_progress(1.0);
+                Some("download:completed")
+            }
+            // Counted by the final summary; only peer stats look at these
+            SldlLine::Failed(_) | SldlLine::Other => None,
+        }
+    }
+
+    // Settle the final status once sldl has exited, returning the event to emit.
+    // An album download whose album wasn't found fails even if sldl didn't.
+    pub fn finish(&mut self, success: bool) -> Option<&'static str> {
+        if let Some(album) = self.album_mode.as_mut() {
+            album.verify();
+            match album.status {
+                AlbumStatus::NotFound if success => {
+                    let message = album.problems.join("; ");
+                    self.update_status(DownloadStatus::Failed(SoulsharkError::NotFound(message)));
+                    return Some("download:failed");
+                }
+                AlbumStatus::Incomplete => {
+                    let expected = album.expected_tracks.unwrap_or_default();
+                    self.failed_tracks = Some(expected.saturating_sub(album.tracks.len()));
+                }
+                _ => {}
+            }
+        }
+
+        if !success {
+            self.update_status(DownloadStatus::Failed(SoulsharkError::Sidecar("sldl exited with an error".to_string())));
+            Some("download:failed")
+        } else if self.status != DownloadStatus::Completed {
+            // Command succeeded but we didn't get a completion message
+            self.update_status(DownloadStatus::Completed);
+            self.update_progress(1.0);
+            Some("download:completed")
+        } else {
+            None
+        }
+    }
+
+    // Switch an album download to downloading the tracks one by one, before
+    // sldl is run again
+    pub fn fall_back_to_tracks(&mut self) {
+        if let Some(album) = self.album_mode.as_mut() {
+            album.fall_back();
+            self.add_console_log("No folder had the whole album, downloading the tracks one by one".to_string());
+        }
+        if let Some(total) = self.total_tracks {
+            self.set_playlist_info(total);
+        }
+        self.progress = None;
+        self.update_status(DownloadStatus::Searching);
+    }
+
+    // Whether the download or any of its tracks failed
+    pub fn has_failures(&self) -> bool {
+        matches!(self.status, DownloadStatus::Failed(_)) || self.failed_tracks.unwrap_or(0) > 0
+    }
+
     fn update_playlist_progress(&mut self) {
         if let (Some(completed), Some(failed), Some(total)) = (self.completed_tracks, self.failed_tracks, self.total_tracks) {
             if total > 0 {
@@ -197,19 +378,27 @@ pub fn init_download_manager() -> DownloadManagerState {
 }
 
 // Helper function to emit download events
-pub fn emit_download_event(app_handle: &AppHandle, event: &str, payload: &Download) {
+pub fn emit_download_event<R: Runtime>(app_handle: &AppHandle<R>, event: &str, payload: &Download) {
     use tauri::Emitter;
     
     if let Err(e) = app_handle.emit(event, payload) {
-        eprintln!("Failed to emit event {}: {}", event, e);
+        tracing::warn!("Failed to emit event {}: {}", event, e);
     }
 }
 
 // Helper function to emit download events for string messages
-pub fn emit_download_message(app_handle: &AppHandle, event: &str, message: &str) {
+pub fn emit_download_message<R: Runtime>(app_handle: &AppHandle<R>, event: &str, message: &str) {
     use tauri::Emitter;
     
     if let Err(e) = app_handle.emit(event, message) {
-        eprintln!("Failed to emit event {}: {}", event, e);
+        tracing::warn!("Failed to emit event {}: {}", event, e);
     }
 }
+
+// Module exports
+pub mod album;
+pub mod cleanup;
+pub mod disk;
+pub mod history;
+pub mod import;
+pub mod peers;
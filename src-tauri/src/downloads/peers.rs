@@ -0,0 +1,239 @@
+use crate::downloads::album::AlbumStatus;
+use crate::downloads::Download;
+use crate::settings::PeerSettings;
+use crate::sldl::parser::{peer_of, SldlLine};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const PEERS_FILE: &str = "peers.json";
+
+// Jobs finishing together must not overwrite each other's stats
+static PEERS_LOCK: once_cell::sync::Lazy<Mutex<()>> = once_cell::sync::Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PeerList {
+    // Never download from this peer
+    Blacklist,
+    // Never blacklist this peer automatically
+    Whitelist,
+}
+
+// What the finished downloads say about a Soulseek peer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerStats {
+    pub username: String,
+    pub successes: u32,
+    pub failures: u32,
+    // Transfers that arrived but belonged to an album with mixed formats,
+    // bitrates or missing tracks
+    pub verification_failures: u32,
+    // Mean speed of the transfers that could be timed, in KB/s
+    pub average_speed_kbps: Option<f64>,
+    pub timed_transfers: u32,
+    // Unix timestamp of the last recorded transfer
+    pub last_transfer_at: Option<i64>,
+    pub list: Option<PeerList>,
+}
+
+impl PeerStats {
+    fn new(username: &str) -> Self {
+        Self {
+            username: username.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn transfers(&self) -> u32 {
+        self.successes + self.failures
+    }
+
+    // Share of transfers that failed or didn't pass the album checks
+    pub fn failure_rate(&self) -> f64 {
+        if self.transfers() == 0 {
+            return 0.0;
+        }
+        let bad = (self.failures + self.verification_failures).min(self.transfers());
+        f64::from(bad) / f64::from(self.transfers())
+    }
+
+    // Whether downloads skip this peer, by hand or for its track record
+    pub fn is_banned(&self, settings: &PeerSettings) -> bool {
+        match self.list {
+            Some(PeerList::Blacklist) => true,
+            Some(PeerList::Whitelist) => false,
+            None => {
+                settings.auto_blacklist
+                    && self.transfers() >= settings.min_transfers
+                    && self.failure_rate() >= settings.max_failure_rate
+            }
+        }
+    }
+
+    fn record_speed(&mut self, speed_kbps: f64) {
+        let total = self.average_speed_kbps.unwrap_or(0.0) * f64::from(self.timed_transfers);
+        self.timed_transfers += 1;
+        self.average_speed_kbps = Some((total + speed_kbps) / f64::from(self.timed_transfers));
+    }
+}
+
+// A finished transfer seen in sldl's output
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub peer: String,
+    pub success: bool,
+    pub speed_kbps: Option<f64>,
+}
+
+// Follows a job's output, timing each transfer from its Initialize line to
+// the line that ends it
+#[derive(Default)]
+pub struct PeerTracker {
+    started: HashMap<String, Instant>,
+    transfers: Vec<Transfer>,
+}
+
+impl PeerTracker {
+    pub fn observe(&mut self, line: &SldlLine) {
+        match line {
+            SldlLine::Initialize(path) => {
+                if peer_of(path).is_some() {
+                    self.started.insert(path.clone(), Instant::now());
+                }
+            }
+            SldlLine::Succeeded { path, size_mb, .. } => {
+                let speed_kbps = self
+                    .started
+                    .remove(path)
+                    .map(|started| started.elapsed().as_secs_f64())
+                    .filter(|secs| *secs > 0.0 && *size_mb > 0.0)
+                    .map(|secs| size_mb * 1024.0 / secs);
+                self.push(path, true, speed_kbps);
+            }
+            SldlLine::Failed(path) => {
+                self.started.remove(path);
+                self.push(path, false, None);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn transfers(&self) -> &[Transfer] {
+        &self.transfers
+    }
+
+    fn push(&mut self, path: &str, success: bool, speed_kbps: Option<f64>) {
+        if let Some(peer) = peer_of(path) {
+            self.transfers.push(Transfer {
+                peer: peer.to_string(),
+                success,
+                speed_kbps,
+            });
+        }
+    }
+}
+
+fn peers_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(PEERS_FILE)
+}
+
+// Load every known peer, by username
+pub fn load_peers(app_data_dir: &Path) -> Result<BTreeMap<String, PeerStats>, String> {
+    let path = peers_path(app_data_dir);
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read peers: {}", e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse peers: {}", e))
+}
+
+fn save_peers(app_data_dir: &Path, peers: &BTreeMap<String, PeerStats>) -> Result<(), String> {
+    fs::create_dir_all(app_data_dir).map_err(|e| format!("Failed to create directory: {}", e))?;
+    let data = serde_json::to_string_pretty(peers).map_err(|e| format!("Failed to serialize peers: {}", e))?;
+    fs::write(peers_path(app_data_dir), data).map_err(|e| format!("Failed to write peers: {}", e))
+}
+
+// Load, change and save the peers under the lock
+pub fn update_peers<T>(
+    app_data_dir: &Path,
+    update: impl FnOnce(&mut BTreeMap<String, PeerStats>) -> T,
+) -> Result<T, String> {
+    let _guard = PEERS_LOCK.lock().map_err(|e| format!("Failed to lock peers: {}", e))?;
+    let mut peers = load_peers(app_data_dir)?;
+    let result = update(&mut peers);
+    save_peers(app_data_dir, &peers)?;
+    Ok(result)
+}
+
+// Record the transfers of a sldl run. The peers of an album that failed its
+// checks also get a verification failure, once per run.
+pub fn record_transfers(app_data_dir: &Path, transfers: &[Transfer], download: Option<&Download>) -> Result<(), String> {
+    let mut unverified: Vec<&str> = download
+        .and_then(|download| download.album_mode.as_ref())
+        .filter(|album| matches!(album.status, AlbumStatus::Mismatched | AlbumStatus::Incomplete))
+        .map(|album| album.tracks.iter().filter_map(|track| peer_of(&track.path)).collect())
+        .unwrap_or_default();
+    unverified.sort();
+    unverified.dedup();
+
+    if transfers.is_empty() && unverified.is_empty() {
+        return Ok(());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    update_peers(app_data_dir, |peers| {
+        for transfer in transfers {
+            let stats = peers
+                .entry(transfer.peer.clone())
+                .or_insert_with(|| PeerStats::new(&transfer.peer));
+            if transfer.success {
+                stats.successes += 1;
+            } else {
+                stats.failures += 1;
+            }
+            if let Some(speed_kbps) = transfer.speed_kbps {
+                stats.record_speed(speed_kbps);
+            }
+            stats.last_transfer_at = Some(now);
+        }
+        for peer in unverified {
+            peers
+                .entry(peer.to_string())
+                .or_insert_with(|| PeerStats::new(peer))
+                .verification_failures += 1;
+        }
+    })
+}
+
+// Put a peer on a list, or take it off with None. Unknown peers are added so
+// they can be listed before any transfer.
+pub fn set_list(app_data_dir: &Path, username: &str, list: Option<PeerList>) -> Result<PeerStats, String> {
+    update_peers(app_data_dir, |peers| {
+        let stats = peers
+            .entry(username.to_string())
+            .or_insert_with(|| PeerStats::new(username));
+        stats.list = list;
+        stats.clone()
+    })
+}
+
+// Usernames sldl should skip, sorted
+pub fn banned_peers(app_data_dir: &Path, settings: &PeerSettings) -> Vec<String> {
+    match load_peers(app_data_dir) {
+        Ok(peers) => peers
+            .values()
+            .filter(|stats| stats.is_banned(settings))
+            .map(|stats| stats.username.clone())
+            .collect(),
+        Err(e) => {
+            tracing::warn!("{}", e);
+            Vec::new()
+        }
+    }
+}
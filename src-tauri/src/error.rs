@@ -0,0 +1,114 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::sync::PoisonError;
+
+// Error returned by commands. Serialized as {"code": "...", "message": "..."}
+// so the frontend can tell errors apart without matching their text. The
+// codes are part of the frontend API and must not change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "code", content = "message", rename_all = "snake_case")]
+pub enum SoulsharkError {
+    // Missing, expired or rejected Spotify or Soulseek credentials
+    Auth(String),
+    // Stored credentials are behind a passphrase that hasn't been entered
+    Locked(String),
+    // Settings or credentials that can't be read, saved or migrated
+    Settings(String),
+    // sldl couldn't be started or exited with an error
+    Sidecar(String),
+    // A request to Spotify or Soulseek failed
+    Network(String),
+    // A local port the app has to listen on is taken by another program
+    PortInUse(String),
+    // Reading or writing files outside the settings store
+    Filesystem(String),
+    NotFound(String),
+    // Input the user can fix, e.g. an invalid setting or link
+    Validation(String),
+    // Anything else, e.g. a poisoned lock
+    Internal(String),
+}
+
+impl SoulsharkError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Auth(_) => "auth",
+            Self::Locked(_) => "locked",
+            Self::Settings(_) => "settings",
+            Self::Sidecar(_) => "sidecar",
+            Self::Network(_) => "network",
+            Self::PortInUse(_) => "port_in_use",
+            Self::Filesystem(_) => "filesystem",
+            Self::NotFound(_) => "not_found",
+            Self::Validation(_) => "validation",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Auth(message)
+            | Self::Locked(message)
+            | Self::Settings(message)
+            | Self::Sidecar(message)
+            | Self::Network(message)
+            | Self::PortInUse(message)
+            | Self::Filesystem(message)
+            | Self::NotFound(message)
+            | Self::Validation(message)
+            | Self::Internal(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for SoulsharkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for SoulsharkError {}
+
+// Code outside the commands (CLI, HTTP API, watch folder) still reports
+// errors as plain messages
+impl From<SoulsharkError> for String {
+    fn from(error: SoulsharkError) -> Self {
+        match error {
+            SoulsharkError::Auth(message)
+            | SoulsharkError::Locked(message)
+            | SoulsharkError::Settings(message)
+            | SoulsharkError::Sidecar(message)
+            | SoulsharkError::Network(message)
+            | SoulsharkError::PortInUse(message)
+            | SoulsharkError::Filesystem(message)
+            | SoulsharkError::NotFound(message)
+            | SoulsharkError::Validation(message)
+            | SoulsharkError::Internal(message) => message,
+        }
+    }
+}
+
+impl<T> From<PoisonError<T>> for SoulsharkError {
+    fn from(error: PoisonError<T>) -> Self {
+        Self::Internal(error.to_string())
+    }
+}
+
+// Read a failure reason stored by older versions, which was only a message.
+// Those were nearly all sldl failures.
+pub fn deserialize_failure<'de, D>(deserializer: D) -> Result<SoulsharkError, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Failure {
+        Typed(SoulsharkError),
+        Message(String),
+    }
+
+    Ok(match Failure::deserialize(deserializer)? {
+        Failure::Typed(error) => error,
+        Failure::Message(message) => SoulsharkError::Sidecar(message),
+    })
+}
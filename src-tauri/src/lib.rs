@@ -1,13 +1,23 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-use tauri::Manager;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
 
 // Import modules
-mod commands;
+mod api;
+pub mod cli;
+pub mod commands;
 mod downloads;
-mod settings;
+mod error;
+mod logging;
+mod naming;
+pub mod settings;
+mod sldl;
+mod soulseek;
+pub mod spotify;
+mod watch;
 
 // Re-export types for use in commands
-pub use downloads::{Download, DownloadManagerState, DownloadStatus};
+pub use downloads::{album::AlbumStatus, peers::{PeerList, PeerStats}, Download, DownloadManagerState, DownloadStatus};
+pub use error::SoulsharkError;
 pub use settings::{AppSettings, Credentials, SettingsState};
 
 #[tauri::command]
@@ -15,6 +25,19 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+// Register the settings, download and token state and open the settings
+// store. Generic over the runtime so the tests can run on the mock runtime.
+pub fn init_state<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), SoulsharkError> {
+    app_handle.manage(settings::init_settings_state::<R>());
+    app_handle.manage(downloads::init_download_manager());
+    app_handle.manage(commands::sldl::SldlInfoState::default());
+
+    // Shared Spotify token cache
+    app_handle.manage(spotify::token::SpotifyTokenManager::new());
+
+    settings::store::init_settings_store(app_handle)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -22,23 +45,55 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .setup(|app| {
-            // Initialize settings state
-            let settings_state = settings::init_settings_state();
-            app.manage(settings_state);
-
-            // Initialize download manager state
-            let download_manager_state = downloads::init_download_manager();
-            app.manage(download_manager_state);
+            // Log to the app data dir from the start; the configured level is
+            // applied once the settings are loaded
+            let app_data_dir = app.handle().path().app_data_dir().unwrap();
+            // Without the log files, keep the error until there is a
+            // subscriber to report it to. The fallback only fails if one is
+            // installed already, which then gets the error instead.
+            if let Err(e) = logging::init(&app_data_dir, "info", true) {
+                let _ = logging::init_console("info");
+                tracing::error!("Logging to files is off: {}", e);
+            }
 
-            // Initialize settings store
-            if let Err(e) = settings::store::init_settings_store(&app.handle()) {
-                eprintln!("Failed to initialize settings store: {}", e);
+            if let Err(e) = init_state(app.handle()) {
+                tracing::error!("Failed to initialize settings store: {}", e);
             }
 
             // Ensure app data directory exists for encryption key
-            let app_data_dir = app.handle().path().app_data_dir().unwrap();
             std::fs::create_dir_all(&app_data_dir).unwrap();
 
+            // Start the local HTTP API and the watch folder if enabled
+            if let Ok(settings) = settings::store::get_settings(app.state::<SettingsState>()) {
+                if let Err(e) = logging::set_level(&settings.logging.level) {
+                    tracing::warn!("{}", e);
+                }
+
+                // Check that the parser understands this sldl build
+                let app_handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    commands::sldl::detect_sldl_version(&app_handle).await;
+                });
+
+                // Passphrase-protected credentials have to be unlocked first
+                if settings.security.key_storage == settings::keystore::KeyStorageKind::Passphrase
+                    && settings::keystore::PassphraseKeyStore::new(&app_data_dir).is_locked()
+                {
+                    let _ = app.emit("credentials:locked", ());
+                } else if let Err(e) = tauri::async_runtime::block_on(settings::store::get_credentials(app.handle())) {
+                    // A missing, corrupted or tampered key
+                    tracing::error!("Failed to read credentials: {}", e);
+                    let _ = app.emit("credentials:corrupted", e);
+                }
+
+                if let Err(e) = api::apply_settings(app.handle(), &settings.api) {
+                    tracing::error!("Failed to start API server: {}", e);
+                }
+                if let Err(e) = watch::apply_settings(app.handle(), &settings.watch) {
+                    tracing::error!("Failed to start watch folder: {}", e);
+                }
+            }
+
             Ok(())
         })
         .plugin(tauri_plugin_opener::init())
@@ -48,16 +103,45 @@ pub fn run() {
             commands::settings::save_settings,
             commands::settings::get_credentials,
             commands::settings::save_credentials,
+            commands::settings::get_key_storage_status,
+            commands::settings::unlock_credentials,
+            commands::settings::set_key_storage,
+            commands::settings::rotate_encryption_key,
+            commands::settings::reset_credentials,
+            commands::settings::validate_settings,
+            commands::settings::preview_name_format,
+            commands::settings::export_settings,
+            commands::settings::import_settings,
+            commands::profiles::list_profiles,
+            commands::profiles::create_profile,
+            commands::profiles::switch_profile,
+            commands::profiles::delete_profile,
+            commands::auth::logout,
+            commands::connection::test_soulseek_login,
+            commands::connection::test_spotify_credentials,
             commands::sldl::execute_sldl,
+            commands::sldl::bulk_enqueue,
+            commands::album::download_album,
+            commands::artist::download_artist,
+            commands::spotify::begin_spotify_auth,
             commands::spotify::exchange_spotify_code,
             commands::spotify::refresh_spotify_token,
             commands::spotify::check_pending_auth,
             commands::spotify::start_spotify_callback_server,
             commands::spotify::stop_spotify_callback_server,
+            commands::spotify::list_spotify_accounts,
+            commands::spotify::switch_spotify_account,
             commands::downloads::get_all_downloads,
             commands::downloads::get_download,
             commands::downloads::cancel_download,
-            commands::downloads::clear_completed_downloads
+            commands::downloads::resume_download,
+            commands::downloads::clear_completed_downloads,
+            commands::downloads::cleanup_download,
+            commands::peers::list_peers,
+            commands::peers::set_peer_list,
+            commands::peers::remove_peer,
+            commands::diagnostics::export_diagnostics,
+            commands::diagnostics::get_system_info
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
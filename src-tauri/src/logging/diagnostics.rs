@@ -0,0 +1,87 @@
+use crate::downloads::Download;
+use crate::settings::AppSettings;
+use crate::sldl::version::SldlInfo;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+// Finished downloads from the history included in a bundle
+pub const RECENT_DOWNLOADS: usize = 50;
+
+const REDACTED: &str = "[redacted]";
+
+// Where the app runs, also returned by the `get_system_info` command
+#[derive(Debug, Serialize)]
+pub struct SystemInfo {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub sldl: SldlInfo,
+}
+
+// Settings safe to attach to a bug report: the API token and the account
+// names are replaced. Credentials are never part of a bundle.
+pub fn redact_settings(settings: &AppSettings) -> AppSettings {
+    let mut settings = settings.clone();
+    for value in [
+        &mut settings.api.token,
+        &mut settings.soulseek.username,
+        &mut settings.spotify.client_id,
+        &mut settings.sldl.config.input.youtube_api_key,
+    ] {
+        if !value.is_empty() {
+            *value = REDACTED.to_string();
+        }
+    }
+    settings
+}
+
+// Write a zip with the log files, the redacted settings, the system info and
+// the given downloads
+pub fn write_bundle(
+    path: &Path,
+    log_files: &[impl AsRef<Path>],
+    settings: &AppSettings,
+    system: &SystemInfo,
+    downloads: &[Download],
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+
+    let mut add = |name: &str, data: &[u8]| -> Result<(), String> {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(data).map_err(Into::into))
+            .map_err(|e| format!("Failed to add {} to the bundle: {}", name, e))
+    };
+
+    for log_file in log_files {
+        let log_file = log_file.as_ref();
+        let name = match log_file.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("logs/{}", name),
+            None => continue,
+        };
+        // A file rotated away meanwhile is skipped
+        if let Ok(data) = fs::read(log_file) {
+            add(&name, &data)?;
+        }
+    }
+
+    add("settings.json", &to_json(&redact_settings(settings))?)?;
+    add("system.json", &to_json(system)?)?;
+    add("downloads.json", &to_json(downloads)?)?;
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn to_json(value: &impl Serialize) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize diagnostics: {}", e))
+}
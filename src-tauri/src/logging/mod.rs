@@ -0,0 +1,115 @@
+use once_cell::sync::OnceCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+// Folder in the app data dir holding the log files
+const LOG_DIR: &str = "logs";
+const LOG_FILE_PREFIX: &str = "soulshark";
+const LOG_FILE_SUFFIX: &str = "log";
+
+// Daily log files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+
+// Levels accepted in the settings, from least to most verbose
+pub const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+// Handle to change the level of the running subscriber
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+
+// Keeps the background writer flushing to the log file for the whole run
+static WRITER_GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+
+pub fn log_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join(LOG_DIR)
+}
+
+// Log files in the log dir, oldest first
+pub fn log_files(app_data_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(log_dir(app_data_dir))
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.is_file()
+                        && path
+                            .file_name()
+                            .and_then(|name| name.to_str())
+                            .map_or(false, |name| name.starts_with(LOG_FILE_PREFIX))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Rotated files are named by date, so this sorts them by age
+    files.sort();
+    files
+}
+
+// Filter for a level from the settings. Only the app's own events go down to
+// that level, dependencies stay at warnings. RUST_LOG overrides it.
+fn filter(level: &str) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,soulshark_lib={}", level)))
+}
+
+// Start logging to daily rotated JSON files in the app data dir, and to
+// stderr in a readable format when `console` is set. Events inside a
+// download's span carry its ID.
+pub fn init(app_data_dir: &Path, level: &str, console: bool) -> Result<(), String> {
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir(app_data_dir))
+        .map_err(|e| format!("Failed to open the log file: {}", e))?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    let (filter_layer, filter_handle) = reload::Layer::new(filter(level));
+    let file_layer = fmt::layer().json().with_writer(writer);
+    let console_layer = console.then(|| fmt::layer().with_writer(std::io::stderr));
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(file_layer)
+        .with(console_layer)
+        .try_init()
+        .map_err(|e| format!("Failed to start logging: {}", e))?;
+
+    let _ = FILTER_HANDLE.set(filter_handle);
+    let _ = WRITER_GUARD.set(guard);
+    Ok(())
+}
+
+// Log to stderr only, for when the log files can't be opened. The level from
+// the settings still applies.
+pub fn init_console(level: &str) -> Result<(), String> {
+    let (filter_layer, filter_handle) = reload::Layer::new(filter(level));
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| format!("Failed to start logging: {}", e))?;
+
+    let _ = FILTER_HANDLE.set(filter_handle);
+    Ok(())
+}
+
+// Apply a new level from the settings to the running subscriber
+pub fn set_level(level: &str) -> Result<(), String> {
+    match FILTER_HANDLE.get() {
+        Some(handle) => handle
+            .reload(filter(level))
+            .map_err(|e| format!("Failed to change the log level: {}", e)),
+        None => Ok(()),
+    }
+}
+
+// Module exports
+pub mod diagnostics;
@@ -2,5 +2,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // Subcommands like `soulshark download <url>` run headless without the GUI
+    if soulshark_lib::cli::is_cli_invocation() {
+        attach_console();
+        std::process::exit(soulshark_lib::cli::run());
+    }
+
     soulshark_lib::run()
 }
+
+// A GUI-subsystem build starts without a console, so CLI output would go
+// nowhere. Write to the console of the shell that started us instead.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // Fails when there is already a console, as in debug builds, or no
+    // parent console at all; either way there is nothing more to do
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
@@ -0,0 +1,476 @@
+use crate::settings::AppSettings;
+use serde::{Deserialize, Serialize};
+
+// Variables sldl substitutes in --name-format
+pub const NAME_FORMAT_TAGS: &[&str] = &[
+    "artist",
+    "artists",
+    "albumartist",
+    "albumartists",
+    "title",
+    "album",
+    "year",
+    "track",
+    "disc",
+    "filename",
+    "foldername",
+    "default-folder",
+    "extractor",
+    "item-name",
+    "length",
+];
+
+// Characters that are invalid in a file name on at least one platform
+const SPECIAL_CHARS: &[char] = &['\\', '/', ':', '*', '?', '"', '<', '>', '|'];
+
+// Device names Windows reserves regardless of extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Tag values of a track, as sldl names them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackTags {
+    pub artist: String,
+    pub artists: String,
+    pub albumartist: String,
+    pub albumartists: String,
+    pub title: String,
+    pub album: String,
+    pub year: String,
+    pub track: String,
+    pub disc: String,
+    pub filename: String,
+    pub foldername: String,
+    #[serde(rename = "default-folder")]
+    pub default_folder: String,
+    pub extractor: String,
+    #[serde(rename = "item-name")]
+    pub item_name: String,
+    pub length: String,
+}
+
+impl TrackTags {
+    // Track used for previews when none is given
+    pub fn sample() -> Self {
+        Self {
+            artist: "Daft Punk".to_string(),
+            artists: "Daft Punk".to_string(),
+            albumartist: "Daft Punk".to_string(),
+            albumartists: "Daft Punk".to_string(),
+            title: "One More Time".to_string(),
+            album: "Discovery".to_string(),
+            year: "2001".to_string(),
+            track: "1".to_string(),
+            disc: "1".to_string(),
+            filename: "01 - One More Time".to_string(),
+            foldername: "Discovery".to_string(),
+            default_folder: "Discovery".to_string(),
+            extractor: "Spotify".to_string(),
+            item_name: "Discovery".to_string(),
+            length: "320".to_string(),
+        }
+    }
+
+    fn get(&self, tag: &str) -> &str {
+        match tag {
+            "artist" => &self.artist,
+            "artists" => &self.artists,
+            "albumartist" => &self.albumartist,
+            "albumartists" => &self.albumartists,
+            "title" => &self.title,
+            "album" => &self.album,
+            "year" => &self.year,
+            "track" => &self.track,
+            "disc" => &self.disc,
+            "filename" => &self.filename,
+            "foldername" => &self.foldername,
+            "default-folder" => &self.default_folder,
+            "extractor" => &self.extractor,
+            "item-name" => &self.item_name,
+            "length" => &self.length,
+            _ => "",
+        }
+    }
+}
+
+// Platform whose file name rules apply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Windows,
+    MacOs,
+    Linux,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        }
+    }
+
+    fn forbids(self, c: char) -> bool {
+        match self {
+            Platform::Windows => SPECIAL_CHARS.contains(&c),
+            Platform::MacOs => c == '/' || c == ':',
+            Platform::Linux => c == '/',
+        }
+    }
+}
+
+// How rendered names are turned into file names
+#[derive(Debug, Clone, Copy)]
+pub struct NamingOptions {
+    // Drop characters that are special on any platform, so names stay
+    // portable; otherwise only the current platform's are replaced
+    pub remove_special_chars: bool,
+    pub platform: Platform,
+}
+
+impl NamingOptions {
+    pub fn from_settings(settings: &AppSettings) -> Self {
+        Self {
+            remove_special_chars: settings.soulseek.remove_special_chars,
+            platform: Platform::current(),
+        }
+    }
+
+    // Whether a name has to be valid on Windows too
+    fn portable(&self) -> bool {
+        self.remove_special_chars || self.platform == Platform::Windows
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Case {
+    Upper,
+    Lower,
+    Title,
+}
+
+// A tag with its modifiers, e.g. {track:02} or {artist:upper}
+#[derive(Debug, Clone)]
+struct TagRef {
+    name: String,
+    pad: Option<usize>,
+    case: Option<Case>,
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Tag(TagRef),
+    Literal(String),
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    // Alternatives separated by '|'; the first whose tags all have a value
+    // is used, so "(text)" next to a tag only shows up together with it
+    Group(Vec<Vec<Part>>),
+}
+
+// A parsed name format such as "{albumartist|artist}/{album} ({year})/{track:02}. {title}"
+#[derive(Debug, Clone)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    // Parse a name format. Braces group tags, '|' separates fallbacks, text in
+    // parentheses inside braces is literal and ':' adds modifiers to a tag:
+    // a width to zero-pad numbers to, or upper, lower or title case.
+    pub fn parse(format: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut rest = format;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err("Unmatched '}'".to_string());
+            }
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| "Unclosed '{'".to_string())?;
+            let inner = &rest[start + 1..end];
+            if inner.contains('{') {
+                return Err("Nested '{' is not supported".to_string());
+            }
+
+            let alternatives = inner
+                .split('|')
+                .map(parse_alternative)
+                .collect::<Result<Vec<_>, _>>()?;
+            segments.push(Segment::Group(alternatives));
+
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(Self { segments })
+    }
+
+    // Parse a name format sldl is given. sldl has no modifiers, so a tag with
+    // one is refused rather than previewed differently than sldl names files.
+    pub fn parse_for_sldl(format: &str) -> Result<Self, String> {
+        let template = Self::parse(format)?;
+
+        for segment in &template.segments {
+            if let Segment::Group(alternatives) = segment {
+                for part in alternatives.iter().flatten() {
+                    if let Part::Tag(tag) = part {
+                        if tag.pad.is_some() || tag.case.is_some() {
+                            return Err(format!("sldl doesn't support modifiers, use '{{{}}}' without them", tag.name));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(template)
+    }
+
+    // Render the relative path for a track, with '/' between directories.
+    // Tag values can't introduce directories, and every path component is
+    // made a valid file name.
+    pub fn render(&self, tags: &TrackTags, options: &NamingOptions) -> String {
+        let mut rendered = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => rendered.push_str(text),
+                Segment::Group(alternatives) => {
+                    if let Some(text) = alternatives
+                        .iter()
+                        .find_map(|alternative| render_alternative(alternative, tags, options))
+                    {
+                        rendered.push_str(&text);
+                    }
+                }
+            }
+        }
+
+        rendered
+            .split(['/', '\\'])
+            .map(|component| sanitize_component(component, options))
+            .filter(|component| !component.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    // The format as sldl understands it, without modifiers
+    pub fn to_sldl_format(&self) -> String {
+        let mut format = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => format.push_str(text),
+                Segment::Group(alternatives) => {
+                    let alternatives: Vec<String> = alternatives
+                        .iter()
+                        .map(|alternative| {
+                            alternative
+                                .iter()
+                                .map(|part| match part {
+                                    Part::Tag(tag) => tag.name.clone(),
+                                    Part::Literal(text) => format!("({})", text),
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    format.push('{');
+                    format.push_str(&alternatives.join("|"));
+                    format.push('}');
+                }
+            }
+        }
+
+        format
+    }
+}
+
+// Parse a name format and render it for a track the way sldl will name it
+pub fn render_name(format: &str, tags: &TrackTags, options: &NamingOptions) -> Result<String, String> {
+    Ok(Template::parse_for_sldl(format)?.render(tags, options))
+}
+
+// Split one alternative into tags and "(literal)" parts
+fn parse_alternative(alternative: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut chars = alternative.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '(' => {
+                chars.next();
+                let mut literal = String::new();
+                let mut depth = 0;
+                loop {
+                    match chars.next() {
+                        Some('(') => {
+                            depth += 1;
+                            literal.push('(');
+                        }
+                        Some(')') if depth == 0 => break,
+                        Some(')') => {
+                            depth -= 1;
+                            literal.push(')');
+                        }
+                        Some(c) => literal.push(c),
+                        None => return Err("Unclosed '('".to_string()),
+                    }
+                }
+                parts.push(Part::Literal(literal));
+            }
+            ')' => return Err("Unmatched ')'".to_string()),
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '(' || c == ')' || c.is_whitespace() {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                parts.push(Part::Tag(parse_tag(&token)?));
+            }
+        }
+    }
+
+    Ok(parts)
+}
+
+fn parse_tag(token: &str) -> Result<TagRef, String> {
+    let mut pieces = token.split(':');
+    let name = pieces.next().unwrap_or_default();
+    if !NAME_FORMAT_TAGS.contains(&name) {
+        return Err(format!("Unknown tag '{{{}}}'", name));
+    }
+
+    let mut tag = TagRef {
+        name: name.to_string(),
+        pad: None,
+        case: None,
+    };
+    for modifier in pieces {
+        match modifier {
+            "upper" => tag.case = Some(Case::Upper),
+            "lower" => tag.case = Some(Case::Lower),
+            "title" => tag.case = Some(Case::Title),
+            width if !width.is_empty() && width.chars().all(|c| c.is_ascii_digit()) => {
+                tag.pad = width.parse().ok();
+            }
+            _ => return Err(format!("Unknown modifier ':{}' on '{{{}}}'", modifier, name)),
+        }
+    }
+
+    Ok(tag)
+}
+
+// Render an alternative, or None if one of its tags has no value
+fn render_alternative(parts: &[Part], tags: &TrackTags, options: &NamingOptions) -> Option<String> {
+    let mut rendered = String::new();
+
+    for part in parts {
+        match part {
+            Part::Literal(text) => rendered.push_str(text),
+            Part::Tag(tag) => {
+                let value = tags.get(&tag.name).trim();
+                if value.is_empty() {
+                    return None;
+                }
+                rendered.push_str(&clean_chars(&apply_modifiers(value, tag), options, true));
+            }
+        }
+    }
+
+    Some(rendered)
+}
+
+fn apply_modifiers(value: &str, tag: &TagRef) -> String {
+    let mut value = value.to_string();
+
+    // Pad the leading number, so "3/12" becomes "03/12"
+    if let Some(width) = tag.pad {
+        let digits = value.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits > 0 && digits < width {
+            value.insert_str(0, &"0".repeat(width - digits));
+        }
+    }
+
+    match tag.case {
+        Some(Case::Upper) => value.to_uppercase(),
+        Some(Case::Lower) => value.to_lowercase(),
+        Some(Case::Title) => value
+            .split(' ')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        None => value,
+    }
+}
+
+// Drop or replace characters a file name can't contain. Path separators in
+// tag values are always replaced, since they would create directories.
+fn clean_chars(text: &str, options: &NamingOptions, is_value: bool) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if c.is_control() {
+            continue;
+        }
+        if options.remove_special_chars && SPECIAL_CHARS.contains(&c) {
+            continue;
+        }
+        if options.platform.forbids(c) || (is_value && (c == '/' || c == '\\')) {
+            cleaned.push('_');
+        } else {
+            cleaned.push(c);
+        }
+    }
+
+    cleaned
+}
+
+// Make one path component a valid file name
+pub fn sanitize_component(component: &str, options: &NamingOptions) -> String {
+    let cleaned = clean_chars(component, options, false);
+    let mut name = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    // Windows ignores trailing dots and spaces, which breaks round trips
+    if options.portable() {
+        name = name.trim_end_matches(['.', ' ']).to_string();
+
+        let stem = name.split('.').next().unwrap_or_default();
+        if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+            name.push('_');
+        }
+    }
+
+    if name == "." || name == ".." {
+        return "_".to_string();
+    }
+    name
+}
@@ -2,39 +2,6 @@ use base64::{decode, encode};
 use chacha20poly1305::aead::Aead;
 use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
 use rand::{rngs::OsRng, RngCore};
-use std::fs;
-use std::path::Path;
-
-// Generate or load the encryption key
-pub fn get_encryption_key(app_data_dir: &str) -> Result<[u8; 32], String> {
-    let key_path = format!("{}/encryption_key.bin", app_data_dir);
-
-    if Path::new(&key_path).exists() {
-        // Load existing key
-        let key_data = fs::read(&key_path).map_err(|e| format!("Failed to read key: {}", e))?;
-        if key_data.len() != 32 {
-            return Err("Invalid key length".to_string());
-        }
-
-        let mut key = [0u8; 32];
-        key.copy_from_slice(&key_data);
-        Ok(key)
-    } else {
-        // Generate new key
-        let mut key = [0u8; 32];
-        OsRng.fill_bytes(&mut key);
-
-        // Ensure directory exists
-        if let Some(parent) = Path::new(&key_path).parent() {
-            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
-        }
-
-        // Write key to file
-        fs::write(&key_path, &key).map_err(|e| format!("Failed to write key: {}", e))?;
-
-        Ok(key)
-    }
-}
 
 // Encrypt data
 pub fn encrypt(key: &[u8; 32], data: &str) -> Result<String, String> {
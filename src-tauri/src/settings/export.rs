@@ -0,0 +1,95 @@
+use crate::settings::{crypto, keystore, migrations, AppSettings, Credentials};
+use base64::{decode, encode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+// Marks a file as a SoulShark settings export
+const EXPORT_FORMAT: &str = "soulshark-settings";
+
+// Settings file shared between machines. The settings keep their schema
+// version so older exports can be migrated on import.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsExport {
+    pub format: String,
+    pub exported_at: i64,
+    pub settings: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<EncryptedSecrets>,
+}
+
+// Credentials encrypted with a key derived from the export passphrase
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedSecrets {
+    pub salt: String,
+    pub data: String,
+}
+
+// Build an export, including the credentials only when a passphrase is given
+pub fn build_export(settings: &AppSettings, secrets: Option<(&Credentials, &str)>) -> Result<SettingsExport, String> {
+    let mut settings = settings.clone();
+
+    let secrets = match secrets {
+        Some((credentials, passphrase)) => {
+            if passphrase.is_empty() {
+                return Err("Passphrase must not be empty".to_string());
+            }
+
+            let salt = keystore::generate_salt();
+            let key = keystore::derive_key(passphrase, &salt)?;
+            let creds_json = serde_json::to_string(credentials)
+                .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+
+            Some(EncryptedSecrets {
+                salt: encode(&salt),
+                data: crypto::encrypt(&key, &creds_json)?,
+            })
+        }
+        None => {
+            // The API token and the YouTube API key are secrets too
+            settings.api.token.clear();
+            settings.sldl.config.input.youtube_api_key.clear();
+            None
+        }
+    };
+
+    Ok(SettingsExport {
+        format: EXPORT_FORMAT.to_string(),
+        exported_at: chrono::Utc::now().timestamp(),
+        settings: json!(settings),
+        secrets,
+    })
+}
+
+pub fn write_export(path: &Path, export: &SettingsExport) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(export)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn read_export(path: &Path) -> Result<SettingsExport, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let export: SettingsExport =
+        serde_json::from_str(&data).map_err(|e| format!("Not a SoulShark settings file: {}", e))?;
+
+    if export.format != EXPORT_FORMAT {
+        return Err("Not a SoulShark settings file".to_string());
+    }
+    Ok(export)
+}
+
+// Settings from an export, migrated to the current schema
+pub fn exported_settings(export: &SettingsExport) -> Result<AppSettings, String> {
+    let settings = migrations::migrate(export.settings.clone())?;
+    serde_json::from_value::<AppSettings>(settings).map_err(|e| format!("Failed to deserialize settings: {}", e))
+}
+
+// Decrypt the credentials in an export
+pub fn decrypt_secrets(secrets: &EncryptedSecrets, passphrase: &str) -> Result<Credentials, String> {
+    let salt = decode(&secrets.salt).map_err(|e| format!("Invalid salt: {}", e))?;
+    let key = keystore::derive_key(passphrase, &salt)?;
+    let decrypted = crypto::decrypt(&key, &secrets.data).map_err(|_| "Wrong passphrase".to_string())?;
+
+    serde_json::from_str::<Credentials>(&decrypted).map_err(|e| format!("Failed to deserialize credentials: {}", e))
+}
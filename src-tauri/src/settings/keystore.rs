@@ -0,0 +1,428 @@
+use crate::settings::crypto;
+use argon2::Argon2;
+use base64::{decode, encode};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zeroize::{Zeroize, Zeroizing};
+
+// Legacy location of the plain key, still used by the file backend
+const KEY_FILE: &str = "encryption_key.bin";
+// Salt and passphrase-wrapped key for the passphrase backend
+const PASSPHRASE_FILE: &str = "passphrase_key.json";
+
+const KEYRING_SERVICE: &str = "com.soulshark.app";
+const KEYRING_USER: &str = "credentials-key";
+
+// Wiped from memory when dropped, so keys are passed by reference or cloned
+// into another wiping buffer, never copied as plain arrays
+pub type Key = Zeroizing<[u8; 32]>;
+
+// Where the credentials encryption key is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStorageKind {
+    // OS keyring (Keychain, Secret Service, Credential Manager)
+    #[default]
+    Keyring,
+    // Plain key file next to the settings
+    File,
+    // Key wrapped with an Argon2-derived key, unlocked at startup
+    Passphrase,
+}
+
+// Backend holding the key that encrypts the stored credentials.
+// Every backend stores the same random key, so switching backends only moves
+// the key and never touches the encrypted credentials themselves.
+pub trait KeyStore {
+    // Load the key, or None if this backend doesn't hold one yet
+    fn load_key(&self) -> Result<Option<Key>, String>;
+    // Store the key, replacing any previous one
+    fn store_key(&self, key: &Key) -> Result<(), String>;
+    // Remove the key from this backend
+    fn delete_key(&self) -> Result<(), String>;
+}
+
+// Write a file only the current user can read, atomically: the data goes to a
+// temp file that is renamed over the target, so a crash never leaves a
+// half-written key behind
+pub fn write_private_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    let temp_path = parent.join(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let result = options
+        .open(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+// Copy exactly 32 bytes into a key, wiping the source buffer
+fn key_from_bytes(mut data: Vec<u8>) -> Result<Key, String> {
+    if data.len() != 32 {
+        data.zeroize();
+        return Err("Invalid key length".to_string());
+    }
+
+    let mut key = Key::default();
+    key.copy_from_slice(&data);
+    data.zeroize();
+    Ok(key)
+}
+
+// Key stored as raw bytes in the app data dir
+pub struct FileKeyStore {
+    path: PathBuf,
+}
+
+impl FileKeyStore {
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            path: app_data_dir.join(KEY_FILE),
+        }
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn load_key(&self) -> Result<Option<Key>, String> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let key_data = fs::read(&self.path).map_err(|e| format!("Failed to read key: {}", e))?;
+        key_from_bytes(key_data)
+            .map(Some)
+            .map_err(|e| format!("Key file {} is corrupted: {}", self.path.display(), e))
+    }
+
+    fn store_key(&self, key: &Key) -> Result<(), String> {
+        write_private_file(&self.path, &key[..])
+    }
+
+    fn delete_key(&self) -> Result<(), String> {
+        if self.path.exists() {
+            fs::remove_file(&self.path).map_err(|e| format!("Failed to delete key file: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+// Key stored base64-encoded in the OS keyring
+pub struct KeyringKeyStore {
+    entry: keyring::Entry,
+}
+
+impl KeyringKeyStore {
+    pub fn new() -> Result<Self, String> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .map_err(|e| format!("Failed to open keyring: {}", e))?;
+        Ok(Self { entry })
+    }
+}
+
+impl KeyStore for KeyringKeyStore {
+    fn load_key(&self) -> Result<Option<Key>, String> {
+        match self.entry.get_password() {
+            Ok(mut encoded) => {
+                let key_data = decode(&encoded);
+                encoded.zeroize();
+                let key_data = key_data.map_err(|e| format!("Keyring entry is corrupted: {}", e))?;
+                key_from_bytes(key_data)
+                    .map(Some)
+                    .map_err(|e| format!("Keyring entry is corrupted: {}", e))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(format!("Failed to read keyring: {}", e)),
+        }
+    }
+
+    fn store_key(&self, key: &Key) -> Result<(), String> {
+        let mut encoded = encode(&key[..]);
+        let result = self
+            .entry
+            .set_password(&encoded)
+            .map_err(|e| format!("Failed to write keyring: {}", e));
+        encoded.zeroize();
+        result
+    }
+
+    fn delete_key(&self) -> Result<(), String> {
+        match self.entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("Failed to delete keyring entry: {}", e)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WrappedKey {
+    salt: String,
+    wrapped_key: String,
+}
+
+// Passphrase-derived key encryption key, and the key it unwrapped
+struct Unlocked {
+    kek: Key,
+    salt: Vec<u8>,
+    key: Option<Key>,
+}
+
+// Unlocked passphrase state, only ever held in memory
+static UNLOCKED: once_cell::sync::Lazy<Mutex<Option<Unlocked>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+// Derive a key from a passphrase with Argon2id
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, String> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key[..])
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+// Random salt for `derive_key`
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+// Key wrapped with an Argon2id-derived key; must be unlocked with the
+// passphrase before credentials can be read
+pub struct PassphraseKeyStore {
+    path: PathBuf,
+}
+
+impl PassphraseKeyStore {
+    pub fn new(app_data_dir: &Path) -> Self {
+        Self {
+            path: app_data_dir.join(PASSPHRASE_FILE),
+        }
+    }
+
+    // Whether a wrapped key exists on disk
+    pub fn is_configured(&self) -> bool {
+        self.path.exists()
+    }
+
+    // Whether the key still has to be unlocked with the passphrase
+    pub fn is_locked(&self) -> bool {
+        self.is_configured() && UNLOCKED.lock().unwrap().as_ref().map_or(true, |u| u.key.is_none())
+    }
+
+    // Set a new passphrase. The next `store_key` wraps the key with it, and an
+    // already unlocked key stays available.
+    pub fn set_passphrase(&self, passphrase: &str) -> Result<(), String> {
+        if passphrase.is_empty() {
+            return Err("Passphrase must not be empty".to_string());
+        }
+
+        let salt = generate_salt();
+        let kek = derive_key(passphrase, &salt)?;
+
+        let mut unlocked = UNLOCKED.lock().unwrap();
+        let key = unlocked.take().and_then(|u| u.key);
+        *unlocked = Some(Unlocked { kek, salt, key });
+        Ok(())
+    }
+
+    // Unwrap the stored key with the passphrase and keep it in memory
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        let data = fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read passphrase key: {}", e))?;
+        let wrapped: WrappedKey = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse passphrase key: {}", e))?;
+
+        let salt = decode(&wrapped.salt).map_err(|e| format!("Invalid salt: {}", e))?;
+        let kek = derive_key(passphrase, &salt)?;
+        let mut encoded = crypto::decrypt(&kek, &wrapped.wrapped_key).map_err(|_| "Wrong passphrase".to_string())?;
+
+        let key_data = decode(&encoded);
+        encoded.zeroize();
+        let key = key_from_bytes(key_data.map_err(|e| format!("Invalid wrapped key: {}", e))?)?;
+        *UNLOCKED.lock().unwrap() = Some(Unlocked { kek, salt, key: Some(key) });
+        Ok(())
+    }
+}
+
+impl KeyStore for PassphraseKeyStore {
+    fn load_key(&self) -> Result<Option<Key>, String> {
+        if let Some(key) = UNLOCKED.lock().unwrap().as_ref().and_then(|u| u.key.clone()) {
+            return Ok(Some(key));
+        }
+
+        if self.is_configured() {
+            return Err("Credentials are locked. Unlock them with your passphrase".to_string());
+        }
+        Ok(None)
+    }
+
+    fn store_key(&self, key: &Key) -> Result<(), String> {
+        let mut unlocked = UNLOCKED.lock().unwrap();
+        let state = unlocked
+            .as_mut()
+            .ok_or_else(|| "No passphrase has been set".to_string())?;
+
+        let mut encoded = encode(&key[..]);
+        let wrapped_key = crypto::encrypt(&state.kek, &encoded);
+        encoded.zeroize();
+
+        let wrapped = WrappedKey {
+            salt: encode(&state.salt),
+            wrapped_key: wrapped_key?,
+        };
+        let data = serde_json::to_string(&wrapped)
+            .map_err(|e| format!("Failed to serialize passphrase key: {}", e))?;
+
+        write_private_file(&self.path, data.as_bytes())?;
+        state.key = Some(key.clone());
+        Ok(())
+    }
+
+    fn delete_key(&self) -> Result<(), String> {
+        *UNLOCKED.lock().unwrap() = None;
+        if self.path.exists() {
+            fs::remove_file(&self.path).map_err(|e| format!("Failed to delete passphrase key: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+// Create the backend for a storage kind
+pub fn key_store(kind: KeyStorageKind, app_data_dir: &Path) -> Result<Box<dyn KeyStore>, String> {
+    Ok(match kind {
+        KeyStorageKind::Keyring => Box::new(KeyringKeyStore::new()?),
+        KeyStorageKind::File => Box::new(FileKeyStore::new(app_data_dir)),
+        KeyStorageKind::Passphrase => Box::new(PassphraseKeyStore::new(app_data_dir)),
+    })
+}
+
+pub fn generate_key() -> Key {
+    let mut key = Key::default();
+    OsRng.fill_bytes(&mut key[..]);
+    key
+}
+
+// Get the encryption key from the configured backend. With `create`, a new
+// key is generated if there is none yet.
+//
+// With the keyring backend, a key left in the legacy key file is moved into
+// the keyring; the file is only deleted once the keyring gives the key back.
+// Without a reachable keyring, e.g. the CLI run from cron without a desktop
+// session, the key file is used instead.
+pub fn get_key(app_data_dir: &Path, kind: KeyStorageKind, create: bool) -> Result<Option<Key>, String> {
+    if kind != KeyStorageKind::Keyring {
+        return load_or_create(key_store(kind, app_data_dir)?.as_ref(), create);
+    }
+
+    let file_store = FileKeyStore::new(app_data_dir);
+    let keyring = match KeyringKeyStore::new().and_then(|keyring| keyring.load_key().map(|key| (keyring, key))) {
+        Ok((_, Some(key))) => return Ok(Some(key)),
+        Ok((keyring, None)) => keyring,
+        Err(e) => {
+            tracing::warn!("Keyring unavailable, falling back to the key file: {}", e);
+            return load_or_create(&file_store, create);
+        }
+    };
+
+    if let Some(key) = file_store.load_key()? {
+        match keyring.store_key(&key).and_then(|_| keyring.load_key()) {
+            Ok(Some(stored)) if stored == key => {
+                if let Err(e) = file_store.delete_key() {
+                    tracing::warn!("Moved the key into the keyring but kept the key file: {}", e);
+                }
+            }
+            Ok(_) => tracing::warn!("The keyring didn't return the key it was given, keeping the key file"),
+            Err(e) => tracing::warn!("Failed to move the key into the keyring, keeping the key file: {}", e),
+        }
+        return Ok(Some(key));
+    }
+
+    if !create {
+        return Ok(None);
+    }
+    let key = generate_key();
+    if let Err(e) = keyring.store_key(&key) {
+        tracing::warn!("Failed to store the key in the keyring, falling back to the key file: {}", e);
+        file_store.store_key(&key)?;
+    }
+    Ok(Some(key))
+}
+
+fn load_or_create(store: &dyn KeyStore, create: bool) -> Result<Option<Key>, String> {
+    if let Some(key) = store.load_key()? {
+        return Ok(Some(key));
+    }
+    if !create {
+        return Ok(None);
+    }
+
+    let key = generate_key();
+    store.store_key(&key)?;
+    Ok(Some(key))
+}
+
+// Replace the key in the configured backend, falling back to the key file
+// when the keyring is unavailable, the same way `get_key` reads it
+pub fn replace_key(app_data_dir: &Path, kind: KeyStorageKind, key: &Key) -> Result<(), String> {
+    if kind == KeyStorageKind::Keyring {
+        match KeyringKeyStore::new().and_then(|keyring| keyring.store_key(key)) {
+            Ok(()) => {
+                // A leftover key file would otherwise be migrated over the new key
+                return FileKeyStore::new(app_data_dir).delete_key();
+            }
+            Err(e) => tracing::warn!("Keyring unavailable, falling back to the key file: {}", e),
+        }
+        return FileKeyStore::new(app_data_dir).store_key(key);
+    }
+
+    key_store(kind, app_data_dir)?.store_key(key)
+}
+
+// Move the key from one backend to another. Switching to the passphrase
+// backend requires `set_passphrase` to have been called first.
+pub fn migrate_key(app_data_dir: &Path, from: KeyStorageKind, to: KeyStorageKind) -> Result<(), String> {
+    let key = match get_key(app_data_dir, from, false)? {
+        Some(key) => key,
+        None => generate_key(),
+    };
+    key_store(to, app_data_dir)?.store_key(&key)?;
+
+    if from != to {
+        // The keyring may have fallen back to the key file, so clear both
+        if from == KeyStorageKind::Keyring && to != KeyStorageKind::File {
+            let _ = FileKeyStore::new(app_data_dir).delete_key();
+        }
+        if let Ok(old_store) = key_store(from, app_data_dir) {
+            old_store.delete_key()?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use serde_json::{json, Map, Value};
+
+// Current settings schema version. Bump it and append a migration to
+// `MIGRATIONS` whenever the stored layout changes.
+pub const SETTINGS_VERSION: u32 = 2;
+
+// Migration from version N to N + 1 is `MIGRATIONS[N]`
+type Migration = fn(&mut Map<String, Value>);
+
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1, migrate_v1_to_v2];
+
+// Version of a stored settings object, 0 for the original unversioned layout
+pub fn version_of(settings: &Value) -> u32 {
+    settings
+        .get("version")
+        .and_then(Value::as_u64)
+        .map_or(0, |version| version as u32)
+}
+
+// Whether the stored settings need migrating
+pub fn needs_migration(settings: &Value) -> bool {
+    version_of(settings) < SETTINGS_VERSION
+}
+
+// Run every migration from the stored version up to the current one
+pub fn migrate(mut settings: Value) -> Result<Value, String> {
+    let from = version_of(&settings);
+    if from > SETTINGS_VERSION {
+        return Err(format!(
+            "Settings were saved by a newer version of SoulShark (schema {}, this version supports {})",
+            from, SETTINGS_VERSION
+        ));
+    }
+
+    let object = settings
+        .as_object_mut()
+        .ok_or_else(|| "Settings are not a JSON object".to_string())?;
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(object);
+        object.insert("version".to_string(), json!(version as u32 + 1));
+    }
+
+    Ok(settings)
+}
+
+// Make sure `key` holds an object, replacing anything else
+fn section<'a>(settings: &'a mut Map<String, Value>, key: &str) -> &'a mut Map<String, Value> {
+    let value = settings.entry(key).or_insert_with(|| json!({}));
+    if !value.is_object() {
+        *value = json!({});
+    }
+    value.as_object_mut().unwrap()
+}
+
+// v0 -> v1: the unversioned layout. Files written by older builds lack the
+// sections added since, and missing fields are filled by the serde defaults,
+// so this only makes sure every section is an object.
+fn migrate_v0_to_v1(settings: &mut Map<String, Value>) {
+    for key in ["soulseek", "spotify", "output", "api", "watch", "security"] {
+        section(settings, key);
+    }
+}
+
+// v1 -> v2: adds the endpoints section with the public servers
+fn migrate_v1_to_v2(settings: &mut Map<String, Value>) {
+    let endpoints = section(settings, "endpoints");
+    endpoints
+        .entry("soulseek_server")
+        .or_insert_with(|| json!("server.slsknet.org:2242"));
+    endpoints
+        .entry("spotify_accounts_url")
+        .or_insert_with(|| json!("https://accounts.spotify.com"));
+    endpoints
+        .entry("spotify_api_url")
+        .or_insert_with(|| json!("https://api.spotify.com/v1"));
+}
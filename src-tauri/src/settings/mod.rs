@@ -1,33 +1,226 @@
+use keystore::KeyStorageKind;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use std::sync::Mutex;
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime, Wry};
+
+// Bundle identifier from tauri.conf.json, used to locate the app data dir
+// when running without a Tauri app handle
+pub const APP_IDENTIFIER: &str = "com.soulshark.app";
 
 // Define the settings structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct SoulseekSettings {
     pub username: String,
     pub downloads_path: String,
     pub remove_special_chars: bool,
     pub preferred_format: String,
+    // Jobs are refused or paused when the downloads volume has less free
+    // space than this, 0 to never pause
+    pub min_free_space_mb: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct SpotifySettings {
     pub client_id: String,
     pub redirect_uri: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct OutputSettings {
     pub m3u_path: String,
     pub name_format: String,
+    pub cleanup: CleanupMode,
+}
+
+// What happens to leftovers (sldl index files, empty folders) in the folders
+// a download created once it finishes
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CleanupMode {
+    // Move files to the trash folder and remove empty folders
+    #[default]
+    Trash,
+    // Only log what would be removed
+    DryRun,
+    Off,
+}
+
+// Local HTTP API for automation tools
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
 }
 
+// Folder watched for import lists (.txt, .csv or files with links)
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub enabled: bool,
+    pub path: String,
+    pub poll_interval_secs: u64,
+}
+
+// The sldl binary to run and the config file handed to it
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlSettings {
+    // Path of an sldl binary to run instead of the bundled sidecar, e.g. a
+    // newer build or the fake used by the tests. Empty for the sidecar.
+    pub path: String,
+    pub config: SldlConfig,
+}
+
+// Options written to an sldl config file for every run, see `sldl::config`.
+// Unset options keep sldl's defaults.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlConfig {
+    // Files not meeting these are never downloaded
+    pub required: SldlConditions,
+    // Files meeting these are picked first
+    pub preferred: SldlConditions,
+    pub search: SldlSearchSettings,
+    pub album: SldlAlbumSettings,
+    pub input: SldlInputSettings,
+    // Lines appended to the config file as they are, for options not
+    // covered above, e.g. "max-retries = 5" or a "[profile]" section
+    pub extra_lines: Vec<String>,
+}
+
+// File conditions, the same set for required and preferred ones
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlConditions {
+    // Accepted formats, any if empty. The preferred formats fall back to
+    // `soulseek.preferred_format`.
+    pub formats: Vec<String>,
+    pub min_bitrate: Option<u32>,
+    pub max_bitrate: Option<u32>,
+    pub min_samplerate: Option<u32>,
+    pub max_samplerate: Option<u32>,
+    pub min_bitdepth: Option<u32>,
+    pub max_bitdepth: Option<u32>,
+    // Allowed difference from the track length in seconds
+    pub length_tol: Option<u32>,
+    pub strict_title: bool,
+    pub strict_artist: bool,
+    pub strict_album: bool,
+    pub banned_users: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlSearchSettings {
+    pub search_timeout_ms: Option<u32>,
+    // Give up on a transfer that hasn't progressed for this long
+    pub max_stale_time_ms: Option<u32>,
+    pub concurrent_downloads: Option<u32>,
+    // Searches allowed per `searches_renew_time_secs`, to avoid a ban
+    pub searches_per_time: Option<u32>,
+    pub searches_renew_time_secs: Option<u32>,
+    // Start downloading the first good result without waiting for the search
+    // to finish
+    pub fast_search: bool,
+    pub fast_search_delay_ms: Option<u32>,
+    pub fast_search_min_up_speed: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlAlbumSettings {
+    // Download every query as an album
+    pub enabled: bool,
+    // Required number of tracks, e.g. "12", or "12+" / "12-" for at least /
+    // at most
+    pub track_count: String,
+    // Which cover art to get: "default", "largest" or "most"
+    pub art: String,
+    pub art_only: bool,
+    // Keep the album when some of its tracks fail
+    pub ignore_fails: bool,
+    // Let album downloads get the tracks one by one when no folder has the
+    // whole album. Applied by the app, not written to the config file.
+    pub fallback_to_tracks: bool,
+}
+
+// Options for YouTube and Bandcamp links. The input type itself is detected
+// from each query.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SldlInputSettings {
+    pub youtube_api_key: String,
+    // Also fetch deleted playlist videos via the Wayback Machine
+    pub youtube_get_deleted: bool,
+    pub youtube_deleted_only: bool,
+    // Download from YouTube with yt-dlp when Soulseek has no match
+    pub yt_dlp: bool,
+    pub yt_dlp_argument: String,
+    // Also search without the artist, for YouTube or Bandcamp uploads whose
+    // "artist" may just be the uploader
+    pub artist_maybe_wrong: bool,
+}
+
+// Automatic blacklisting of peers with a poor track record. Peers listed by
+// hand are managed with the peer commands, see `downloads::peers`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PeerSettings {
+    pub auto_blacklist: bool,
+    // Transfers a peer needs before it can be blacklisted automatically
+    pub min_transfers: u32,
+    // Share of failed or broken transfers, from 0 to 1, at which a peer is
+    // blacklisted
+    pub max_failure_rate: f64,
+}
+
+// Log files in the app data dir
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LoggingSettings {
+    // One of `logging::LEVELS`
+    pub level: String,
+}
+
+// Where the key encrypting the stored credentials is kept
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SecuritySettings {
+    pub key_storage: KeyStorageKind,
+}
+
+// Servers the app talks to, overridable to point at local stand-ins
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EndpointSettings {
+    // Soulseek server as host:port
+    pub soulseek_server: String,
+    pub spotify_accounts_url: String,
+    pub spotify_api_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AppSettings {
+    // Schema version, see `migrations`. Missing means the unversioned layout.
+    #[serde(default)]
+    pub version: u32,
     pub soulseek: SoulseekSettings,
     pub spotify: SpotifySettings,
     pub output: OutputSettings,
+    pub api: ApiSettings,
+    pub watch: WatchSettings,
+    pub security: SecuritySettings,
+    pub endpoints: EndpointSettings,
+    pub logging: LoggingSettings,
+    pub sldl: SldlSettings,
+    pub peers: PeerSettings,
 }
 
 // Default settings
@@ -38,6 +231,7 @@ impl Default for SoulseekSettings {
             downloads_path: String::new(),
             remove_special_chars: true,
             preferred_format: "flac".to_string(),
+            min_free_space_mb: 2048,
         }
     }
 }
@@ -56,6 +250,55 @@ impl Default for OutputSettings {
         Self {
             m3u_path: "playlists/".to_string(),
             name_format: "{albumartist|artist}/{album} ({year})/{track}. {title}".to_string(),
+            cleanup: CleanupMode::default(),
+        }
+    }
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 9872,
+            token: String::new(),
+        }
+    }
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: String::new(),
+            poll_interval_secs: 10,
+        }
+    }
+}
+
+impl Default for EndpointSettings {
+    fn default() -> Self {
+        Self {
+            soulseek_server: "server.slsknet.org:2242".to_string(),
+            spotify_accounts_url: "https://accounts.spotify.com".to_string(),
+            spotify_api_url: "https://api.spotify.com/v1".to_string(),
+        }
+    }
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+impl Default for PeerSettings {
+    fn default() -> Self {
+        Self {
+            auto_blacklist: false,
+            min_transfers: 3,
+            max_failure_rate: 0.5,
         }
     }
 }
@@ -63,31 +306,144 @@ impl Default for OutputSettings {
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
+            version: migrations::SETTINGS_VERSION,
             soulseek: SoulseekSettings::default(),
             spotify: SpotifySettings::default(),
             output: OutputSettings::default(),
+            api: ApiSettings::default(),
+            watch: WatchSettings::default(),
+            security: SecuritySettings::default(),
+            endpoints: EndpointSettings::default(),
+            logging: LoggingSettings::default(),
+            sldl: SldlSettings::default(),
+            peers: PeerSettings::default(),
         }
     }
 }
 
 // Sensitive credentials that will be encrypted and stored
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Credentials {
     pub soulseek_password: Option<String>,
     pub spotify_client_secret: Option<String>,
     pub spotify_access_token: Option<String>,
     pub spotify_refresh_token: Option<String>,
     pub spotify_token_expires_at: Option<u64>, // Unix timestamp
+    // Every linked Spotify account. The spotify_* token fields above mirror
+    // the active account, for code that only knows about one.
+    #[serde(default)]
+    pub spotify_accounts: Vec<SpotifyAccount>,
+    #[serde(default)]
+    pub active_spotify_account: Option<String>,
+}
+
+// A linked Spotify account with its own tokens
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SpotifyAccount {
+    // Spotify user ID, empty until the profile has been fetched (tokens from
+    // before accounts were supported)
+    pub id: String,
+    pub display_name: String,
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub token_expires_at: Option<u64>, // Unix timestamp
+}
+
+impl Credentials {
+    // The account with the given ID, or the active account
+    pub fn spotify_account(&self, id: Option<&str>) -> Option<&SpotifyAccount> {
+        match id.or(self.active_spotify_account.as_deref()) {
+            Some(id) => self.spotify_accounts.iter().find(|account| account.id == id),
+            None => self.spotify_accounts.first(),
+        }
+    }
+
+    pub fn spotify_account_mut(&mut self, id: &str) -> Option<&mut SpotifyAccount> {
+        self.spotify_accounts.iter_mut().find(|account| account.id == id)
+    }
+
+    // Add an account, or replace the one with the same ID
+    pub fn upsert_spotify_account(&mut self, account: SpotifyAccount) {
+        match self.spotify_account_mut(&account.id) {
+            Some(existing) => *existing = account,
+            None => self.spotify_accounts.push(account),
+        }
+    }
+
+    // Remove an account, making the first remaining one active if it was the
+    // active account. Returns whether the account existed.
+    pub fn remove_spotify_account(&mut self, id: &str) -> bool {
+        let count = self.spotify_accounts.len();
+        self.spotify_accounts.retain(|account| account.id != id);
+
+        if self.active_spotify_account.as_deref() == Some(id) {
+            self.active_spotify_account = self.spotify_accounts.first().map(|account| account.id.clone());
+        }
+        self.spotify_accounts.len() != count
+    }
+
+    // Bring the account list and the single-token fields in line: tokens
+    // saved before accounts existed become an (unnamed) account, and the
+    // token fields are then set from the active account
+    pub fn sync_spotify_accounts(&mut self) {
+        if self.spotify_accounts.is_empty() && self.spotify_access_token.is_some() {
+            self.spotify_accounts.push(SpotifyAccount {
+                id: String::new(),
+                display_name: String::new(),
+                access_token: self.spotify_access_token.clone(),
+                refresh_token: self.spotify_refresh_token.clone(),
+                token_expires_at: self.spotify_token_expires_at,
+            });
+        }
+
+        let is_known = |id: &String| self.spotify_accounts.iter().any(|account| &account.id == id);
+        if !self.active_spotify_account.as_ref().map_or(false, is_known) {
+            self.active_spotify_account = self.spotify_accounts.first().map(|account| account.id.clone());
+        }
+
+        let active = self.spotify_account(None).cloned().unwrap_or_default();
+        self.spotify_access_token = active.access_token;
+        self.spotify_refresh_token = active.refresh_token;
+        self.spotify_token_expires_at = active.token_expires_at;
+    }
+
+    // A copy whose single-token fields hold the tokens of the given account
+    // (the active one if None), for handing to sldl
+    pub fn for_spotify_account(&self, id: Option<&str>) -> Result<Credentials, String> {
+        let mut credentials = self.clone();
+        match self.spotify_account(id) {
+            Some(account) => {
+                credentials.spotify_access_token = account.access_token.clone();
+                credentials.spotify_refresh_token = account.refresh_token.clone();
+                credentials.spotify_token_expires_at = account.token_expires_at;
+            }
+            None if id.is_some() => return Err(format!("Unknown Spotify account: {}", id.unwrap_or_default())),
+            None => {}
+        }
+        Ok(credentials)
+    }
 }
 
-// State to hold the app handle for accessing the store
-pub struct SettingsState(pub Mutex<Option<AppHandle>>);
+// State to hold the app handle for accessing the store. Generic over the
+// runtime so the download flow can run on the mock runtime in tests.
+pub struct SettingsState<R: Runtime = Wry>(pub Mutex<Option<AppHandle<R>>>);
 
 // Initialize the settings state
-pub fn init_settings_state() -> SettingsState {
+pub fn init_settings_state<R: Runtime>() -> SettingsState<R> {
     SettingsState(Mutex::new(None))
 }
 
+// Resolve the app data dir the same way Tauri does, for headless use
+pub fn default_app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
 // Module exports
 pub mod crypto;
+pub mod export;
+pub mod keystore;
+pub mod migrations;
+pub mod profiles;
 pub mod store;
+pub mod validation;
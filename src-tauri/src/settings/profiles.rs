@@ -0,0 +1,182 @@
+use crate::error::SoulsharkError;
+use crate::settings::store::{CREDENTIALS_KEY, SETTINGS_FILE, SETTINGS_KEY};
+use crate::settings::{migrations, AppSettings};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::{Store, StoreExt};
+
+// Inactive profiles and the name of the active one. The active profile
+// always lives in the regular settings and credentials keys.
+const PROFILES_KEY: &str = "profiles";
+const ACTIVE_PROFILE_KEY: &str = "active_profile";
+
+pub const DEFAULT_PROFILE: &str = "Default";
+
+// A stored profile: its settings and credentials, encrypted with the same key
+// as the active credentials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredProfile {
+    settings: Value,
+    #[serde(default)]
+    encrypted_credentials: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileList {
+    pub active: String,
+    pub profiles: Vec<String>,
+}
+
+fn open_store<R: Runtime>(app_handle: &AppHandle<R>) -> Result<std::sync::Arc<Store<R>>, SoulsharkError> {
+    app_handle
+        .store(SETTINGS_FILE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))
+}
+
+fn active_profile<R: Runtime>(store: &Store<R>) -> String {
+    store
+        .get(ACTIVE_PROFILE_KEY)
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+fn read_profiles<R: Runtime>(store: &Store<R>) -> Result<BTreeMap<String, StoredProfile>, SoulsharkError> {
+    match store.get(PROFILES_KEY) {
+        Some(value) => serde_json::from_value(value).map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize profiles: {}", e))),
+        None => Ok(BTreeMap::new()),
+    }
+}
+
+fn current_profile<R: Runtime>(store: &Store<R>) -> StoredProfile {
+    StoredProfile {
+        settings: store.get(SETTINGS_KEY).unwrap_or_else(|| json!(AppSettings::default())),
+        encrypted_credentials: store
+            .get(CREDENTIALS_KEY)
+            .and_then(|value| value.as_str().map(str::to_string)),
+    }
+}
+
+fn check_name(name: &str) -> Result<String, SoulsharkError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(SoulsharkError::Validation("Profile name must not be empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+pub fn list_profiles<R: Runtime>(app_handle: &AppHandle<R>) -> Result<ProfileList, SoulsharkError> {
+    let store = open_store(app_handle)?;
+    let active = active_profile(&store);
+
+    let mut profiles: Vec<String> = read_profiles(&store)?.into_keys().collect();
+    profiles.push(active.clone());
+    profiles.sort();
+
+    Ok(ProfileList { active, profiles })
+}
+
+// Add a profile, starting from the current settings and credentials or from
+// the defaults
+pub fn create_profile<R: Runtime>(app_handle: &AppHandle<R>, name: &str, copy_current: bool) -> Result<(), SoulsharkError> {
+    let name = check_name(name)?;
+    let store = open_store(app_handle)?;
+    let mut profiles = read_profiles(&store)?;
+
+    if name == active_profile(&store) || profiles.contains_key(&name) {
+        return Err(SoulsharkError::Validation(format!("Profile '{}' already exists", name)));
+    }
+
+    let profile = if copy_current {
+        current_profile(&store)
+    } else {
+        StoredProfile {
+            settings: json!(AppSettings::default()),
+            encrypted_credentials: None,
+        }
+    };
+
+    profiles.insert(name, profile);
+    store.set(PROFILES_KEY, json!(profiles));
+    store.save().map_err(|e| SoulsharkError::Settings(format!("Failed to save profiles: {}", e)))
+}
+
+// Make a profile active, storing the current one under its name. Returns the
+// settings of the new profile.
+pub fn switch_profile<R: Runtime>(app_handle: &AppHandle<R>, name: &str) -> Result<AppSettings, SoulsharkError> {
+    let name = check_name(name)?;
+    let store = open_store(app_handle)?;
+    let active = active_profile(&store);
+
+    let current = current_profile(&store);
+    let current_settings = serde_json::from_value::<AppSettings>(migrations::migrate(current.settings.clone()).map_err(SoulsharkError::Settings)?)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize settings: {}", e)))?;
+
+    if name == active {
+        return Ok(current_settings);
+    }
+
+    let mut profiles = read_profiles(&store)?;
+    let target = profiles
+        .remove(&name)
+        .ok_or_else(|| SoulsharkError::NotFound(format!("Profile '{}' not found", name)))?;
+
+    let mut settings = serde_json::from_value::<AppSettings>(migrations::migrate(target.settings).map_err(SoulsharkError::Settings)?)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize settings: {}", e)))?;
+    // Key storage belongs to the machine, not the profile
+    settings.security = current_settings.security;
+
+    profiles.insert(active, current);
+
+    store.set(SETTINGS_KEY, json!(settings));
+    match target.encrypted_credentials {
+        Some(encrypted) => store.set(CREDENTIALS_KEY, json!(encrypted)),
+        None => {
+            store.delete(CREDENTIALS_KEY);
+        }
+    }
+    store.set(PROFILES_KEY, json!(profiles));
+    store.set(ACTIVE_PROFILE_KEY, json!(name));
+
+    store.save().map_err(|e| SoulsharkError::Settings(format!("Failed to save profiles: {}", e)))?;
+    Ok(settings)
+}
+
+// Remove an inactive profile
+pub fn delete_profile<R: Runtime>(app_handle: &AppHandle<R>, name: &str) -> Result<(), SoulsharkError> {
+    let name = check_name(name)?;
+    let store = open_store(app_handle)?;
+    if name == active_profile(&store) {
+        return Err(SoulsharkError::Validation("Cannot delete the active profile".to_string()));
+    }
+
+    let mut profiles = read_profiles(&store)?;
+    if profiles.remove(&name).is_none() {
+        return Err(SoulsharkError::NotFound(format!("Profile '{}' not found", name)));
+    }
+
+    store.set(PROFILES_KEY, json!(profiles));
+    store.save().map_err(|e| SoulsharkError::Settings(format!("Failed to save profiles: {}", e)))
+}
+
+// Replace the encrypted credentials of every inactive profile, dropping them
+// where `f` returns None. Only updates the store in memory; the caller saves.
+pub fn map_credentials<R: Runtime>(
+    store: &Store<R>,
+    f: impl Fn(&str) -> Result<Option<String>, SoulsharkError>,
+) -> Result<(), SoulsharkError> {
+    let mut profiles = read_profiles(store)?;
+    if profiles.is_empty() {
+        return Ok(());
+    }
+
+    for profile in profiles.values_mut() {
+        if let Some(encrypted) = profile.encrypted_credentials.take() {
+            profile.encrypted_credentials = f(&encrypted)?;
+        }
+    }
+
+    store.set(PROFILES_KEY, json!(profiles));
+    Ok(())
+}
@@ -1,16 +1,22 @@
+use crate::error::SoulsharkError;
 use crate::settings::crypto;
+use crate::settings::keystore::{self, KeyStorageKind};
+use crate::settings::{migrations, profiles};
 use crate::settings::{AppSettings, Credentials, SettingsState};
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager, Runtime, State};
 use tauri_plugin_store::StoreExt;
+use zeroize::{Zeroize, Zeroizing};
 
-const SETTINGS_FILE: &str = "settings.json";
-const SETTINGS_KEY: &str = "app_settings";
+pub const SETTINGS_FILE: &str = "settings.json";
+pub const SETTINGS_KEY: &str = "app_settings";
 
 // Initialize the settings store
-pub fn init_settings_store(app_handle: &AppHandle) -> Result<(), String> {
+pub fn init_settings_store<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), SoulsharkError> {
     // Update the settings state with the app handle
-    if let Some(state) = app_handle.try_state::<SettingsState>() {
+    if let Some(state) = app_handle.try_state::<SettingsState<R>>() {
         let mut state = state.0.lock().unwrap();
         *state = Some(app_handle.clone());
     }
@@ -18,13 +24,13 @@ pub fn init_settings_store(app_handle: &AppHandle) -> Result<(), String> {
     // Create or load the settings store
     let store = app_handle
         .store(SETTINGS_FILE)
-        .map_err(|e| format!("Failed to create settings store: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to create settings store: {}", e)))?;
 
     // Initialize with default settings if not already set
     let has_settings = store.has(SETTINGS_KEY);
 
     if !has_settings {
-        println!("No settings found, initializing with defaults");
+        tracing::info!("No settings found, initializing with defaults");
         let default_settings = AppSettings::default();
 
         // Set the default settings
@@ -33,164 +39,295 @@ pub fn init_settings_store(app_handle: &AppHandle) -> Result<(), String> {
         // Save the store to persist the default settings
         store
             .save()
-            .map_err(|e| format!("Failed to save default settings: {}", e))?;
+            .map_err(|e| SoulsharkError::Settings(format!("Failed to save default settings: {}", e)))?;
 
-        println!("Initialized store with default settings");
+        tracing::info!("Initialized store with default settings");
     } else {
-        println!("Store already has settings");
+        tracing::debug!("Store already has settings");
+        migrate_settings_store(app_handle, &store)?;
     }
 
     Ok(())
 }
 
+// Bring stored settings up to the current schema version, keeping a copy of
+// the store file as it was before the migration
+fn migrate_settings_store<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    store: &tauri_plugin_store::Store<R>,
+) -> Result<(), SoulsharkError> {
+    let settings = match store.get(SETTINGS_KEY) {
+        Some(settings) => settings,
+        None => return Ok(()),
+    };
+
+    if !migrations::needs_migration(&settings) {
+        return Ok(());
+    }
+
+    let from = migrations::version_of(&settings);
+    tracing::info!(
+        "Migrating settings from version {} to {}",
+        from,
+        migrations::SETTINGS_VERSION
+    );
+
+    let store_path = app_data_dir(app_handle)?.join(SETTINGS_FILE);
+    if store_path.exists() {
+        let backup_path = store_path.with_file_name(format!("settings.v{}.backup.json", from));
+        std::fs::copy(&store_path, &backup_path)
+            .map_err(|e| SoulsharkError::Filesystem(format!("Failed to back up settings before migration: {}", e)))?;
+    }
+
+    let migrated = migrations::migrate(settings).map_err(SoulsharkError::Settings)?;
+
+    // Make sure the result deserializes before replacing the stored settings
+    serde_json::from_value::<AppSettings>(migrated.clone())
+        .map_err(|e| SoulsharkError::Settings(format!("Migrated settings are invalid: {}", e)))?;
+
+    store.set(SETTINGS_KEY, migrated);
+    store
+        .save()
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to save migrated settings: {}", e)))?;
+
+    tracing::info!("Settings migrated");
+    Ok(())
+}
+
 // Constants for encrypted credentials
-const CREDENTIALS_KEY: &str = "encrypted_credentials";
+pub const CREDENTIALS_KEY: &str = "encrypted_credentials";
+
+// Appended to errors about unreadable credentials
+const RESET_HINT: &str = "Reset the stored credentials and enter them again to continue";
+
+pub fn app_data_dir<R: Runtime>(app_handle: &AppHandle<R>) -> Result<PathBuf, SoulsharkError> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| SoulsharkError::Filesystem(format!("Failed to get app data dir: {}", e)))
+}
+
+// Key storage backend from the saved settings, the default if unreadable
+pub fn key_storage_kind<R: Runtime>(app_handle: &AppHandle<R>) -> KeyStorageKind {
+    app_handle
+        .store(SETTINGS_FILE)
+        .ok()
+        .and_then(|store| store.get(SETTINGS_KEY))
+        .and_then(|value| serde_json::from_value::<AppSettings>(value).ok())
+        .map(|settings| settings.security.key_storage)
+        .unwrap_or_default()
+}
 
 // Save credentials to the store with encryption
 pub async fn save_credentials<R: Runtime>(
     app_handle: &AppHandle<R>,
-    credentials: Credentials,
-) -> Result<(), String> {
+    mut credentials: Credentials,
+) -> Result<(), SoulsharkError> {
+    credentials.sync_spotify_accounts();
+
     // Get app data dir
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?
-        .to_string_lossy()
-        .to_string();
+    let app_data_dir = app_data_dir(app_handle)?;
 
     // Get or generate encryption key
-    let key = crypto::get_encryption_key(&app_data_dir)?;
+    let key = keystore::get_key(&app_data_dir, key_storage_kind(app_handle), true)
+        .map_err(SoulsharkError::Settings)?
+        .ok_or_else(|| SoulsharkError::Settings("Failed to create encryption key".to_string()))?;
 
     // Serialize credentials
     let creds_json = serde_json::to_string(&credentials)
-        .map_err(|e| format!("Failed to serialize credentials: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to serialize credentials: {}", e)))?;
 
     // Encrypt credentials
-    let encrypted = crypto::encrypt(&key, &creds_json)?;
+    let encrypted = crypto::encrypt(&key, &creds_json).map_err(SoulsharkError::Settings)?;
 
     // Store encrypted credentials
     let store = app_handle
         .store(SETTINGS_FILE)
-        .map_err(|e| format!("Failed to access settings store: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
 
     store.set(CREDENTIALS_KEY, json!(encrypted));
     store
         .save()
-        .map_err(|e| format!("Failed to save credentials: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to save credentials: {}", e)))?;
 
-    println!("Credentials saved successfully");
+    tracing::debug!("Credentials saved successfully");
     Ok(())
 }
 
 // Get credentials from the store and decrypt
-pub async fn get_credentials<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Credentials, String> {
+pub async fn get_credentials<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Credentials, SoulsharkError> {
     // Get app data dir
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to get app data dir: {}", e))?
-        .to_string_lossy()
-        .to_string();
-
-    // Get encryption key
-    let key = match crypto::get_encryption_key(&app_data_dir) {
-        Ok(key) => key,
-        Err(e) => {
-            println!("Warning: Could not get encryption key: {}", e);
-            // If we can't get a key, return empty credentials
-            return Ok(Credentials {
-                soulseek_password: None,
-                spotify_client_secret: None,
-                spotify_access_token: None,
-                spotify_refresh_token: None,
-                spotify_token_expires_at: None,
-            });
-        }
-    };
+    let app_data_dir = app_data_dir(app_handle)?;
 
     // Get store
     let store = app_handle
         .store(SETTINGS_FILE)
-        .map_err(|e| format!("Failed to access settings store: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
 
     // Get encrypted credentials
     let encrypted = match store.get(CREDENTIALS_KEY) {
         Some(value) => {
             // Convert from JSON value to String
             serde_json::from_value::<String>(value)
-                .map_err(|e| format!("Failed to deserialize encrypted data: {}", e))?
+                .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize encrypted data: {}", e)))?
         }
         None => {
-            println!("No credentials found in store");
+            tracing::debug!("No credentials found in store");
             // No credentials stored yet
-            return Ok(Credentials {
-                soulseek_password: None,
-                spotify_client_secret: None,
-                spotify_access_token: None,
-                spotify_refresh_token: None,
-                spotify_token_expires_at: None,
-            });
+            return Ok(Credentials::default());
         }
     };
 
-    // Decrypt credentials
-    let decrypted = match crypto::decrypt(&key, &encrypted) {
-        Ok(data) => data,
-        Err(e) => {
-            println!("Warning: Failed to decrypt credentials: {}", e);
-            // If decryption fails, return empty credentials
-            return Ok(Credentials {
-                soulseek_password: None,
-                spotify_client_secret: None,
-                spotify_access_token: None,
-                spotify_refresh_token: None,
-                spotify_token_expires_at: None,
-            });
-        }
-    };
+    // Credentials behind a passphrase can't be read until they are unlocked
+    let kind = key_storage_kind(app_handle);
+    if kind == KeyStorageKind::Passphrase && keystore::PassphraseKeyStore::new(&app_data_dir).is_locked() {
+        return Err(SoulsharkError::Locked(
+            "Credentials are locked. Unlock them with your passphrase".to_string(),
+        ));
+    }
+
+    // Get encryption key. Without it the stored credentials are lost, which
+    // is reported rather than silently returning empty credentials.
+    let key = keystore::get_key(&app_data_dir, kind, false)
+        .map_err(|e| SoulsharkError::Settings(format!("{}. {}", e, RESET_HINT)))?
+        .ok_or_else(|| {
+            SoulsharkError::Settings(format!(
+                "The encryption key for the stored credentials is missing. {}",
+                RESET_HINT
+            ))
+        })?;
+
+    // Decrypt credentials. The cipher is authenticated, so a wrong key or
+    // modified data fails here instead of producing garbage.
+    let mut decrypted = crypto::decrypt(&key, &encrypted).map_err(|_| {
+        SoulsharkError::Settings(format!(
+            "Stored credentials could not be decrypted, the key or the data is corrupted or was tampered with. {}",
+            RESET_HINT
+        ))
+    })?;
 
     // Deserialize credentials
     let credentials = serde_json::from_str::<Credentials>(&decrypted)
-        .map_err(|e| format!("Failed to deserialize credentials: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize credentials: {}", e)));
+    decrypted.zeroize();
 
-    Ok(credentials)
+    credentials.map(|mut credentials| {
+        credentials.sync_spotify_accounts();
+        credentials
+    })
+}
+
+// Re-encrypt the stored credentials, including those of inactive profiles,
+// under a fresh key. The old key is only replaced once everything has been
+// re-encrypted, and restored if the new credentials can't be saved.
+pub fn rotate_encryption_key<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), SoulsharkError> {
+    let app_data_dir = app_data_dir(app_handle)?;
+    let kind = key_storage_kind(app_handle);
+    let store = app_handle
+        .store(SETTINGS_FILE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
+
+    let old_key = keystore::get_key(&app_data_dir, kind, false)
+        .map_err(SoulsharkError::Settings)?
+        .ok_or_else(|| SoulsharkError::NotFound("There is no encryption key to rotate".to_string()))?;
+    let new_key = keystore::generate_key();
+
+    let reencrypt = |encrypted: &str| -> Result<Option<String>, SoulsharkError> {
+        let mut decrypted = crypto::decrypt(&old_key, encrypted).map_err(|_| {
+            SoulsharkError::Settings(format!("Stored credentials could not be decrypted. {}", RESET_HINT))
+        })?;
+        let result = crypto::encrypt(&new_key, &decrypted);
+        decrypted.zeroize();
+        result.map(Some).map_err(SoulsharkError::Settings)
+    };
+
+    let current = match store.get(CREDENTIALS_KEY) {
+        Some(value) => {
+            let encrypted = serde_json::from_value::<String>(value)
+                .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize encrypted data: {}", e)))?;
+            reencrypt(&encrypted)?
+        }
+        None => None,
+    };
+    let result = profiles::map_credentials(&store, reencrypt).and_then(|_| {
+        keystore::replace_key(&app_data_dir, kind, &new_key).map_err(SoulsharkError::Settings)?;
+
+        if let Some(encrypted) = current {
+            store.set(CREDENTIALS_KEY, json!(encrypted));
+        }
+        store.save().map_err(|e| SoulsharkError::Settings(format!("Failed to save credentials: {}", e))).map_err(|e| {
+            // Put the old key back so the credentials on disk stay readable
+            if let Err(restore_error) = keystore::replace_key(&app_data_dir, kind, &old_key) {
+                tracing::error!("Failed to restore the previous encryption key: {}", restore_error);
+            }
+            e
+        })
+    });
+
+    if result.is_err() {
+        // Drop the unsaved changes from the in-memory store
+        let _ = store.reload();
+    }
+    result
+}
+
+// Delete the stored credentials of every profile and the key protecting
+// them. The way out when the key is lost or corrupted.
+pub fn reset_credentials<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), SoulsharkError> {
+    let app_data_dir = app_data_dir(app_handle)?;
+    let store = app_handle
+        .store(SETTINGS_FILE)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
+
+    store.delete(CREDENTIALS_KEY);
+    profiles::map_credentials(&store, |_| Ok(None))?;
+    store
+        .save()
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to save credentials: {}", e)))?;
+
+    keystore::key_store(key_storage_kind(app_handle), &app_data_dir)
+        .and_then(|key_store| key_store.delete_key())
+        .and_then(|_| keystore::FileKeyStore::new(&app_data_dir).delete_key())
+        .map_err(SoulsharkError::Settings)
 }
 
 // Get the current settings
-pub fn get_settings(state: State<SettingsState>) -> Result<AppSettings, String> {
+pub fn get_settings<R: Runtime>(state: State<SettingsState<R>>) -> Result<AppSettings, SoulsharkError> {
     let state = state.0.lock().unwrap();
     let app_handle = state
         .as_ref()
-        .ok_or_else(|| "App handle not initialized".to_string())?;
+        .ok_or_else(|| SoulsharkError::Internal("App handle not initialized".to_string()))?;
 
     let store = app_handle
         .store(SETTINGS_FILE)
-        .map_err(|e| format!("Failed to access settings store: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
 
     // Get the settings from the store
     let settings = store.get(SETTINGS_KEY);
 
     if settings.is_none() {
-        return Err("Settings not found".to_string());
+        return Err(SoulsharkError::NotFound("Settings not found".to_string()));
     }
 
     let settings = settings.unwrap();
 
     serde_json::from_value::<AppSettings>(settings)
-        .map_err(|e| format!("Failed to deserialize settings: {}", e))
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize settings: {}", e)))
 }
 
 // Save the settings
-pub fn save_settings(state: State<SettingsState>, settings: AppSettings) -> Result<(), String> {
+pub fn save_settings<R: Runtime>(state: State<SettingsState<R>>, mut settings: AppSettings) -> Result<(), SoulsharkError> {
+    // The frontend doesn't track the schema version
+    settings.version = migrations::SETTINGS_VERSION;
+
     let state = state.0.lock().unwrap();
     let app_handle = state
         .as_ref()
-        .ok_or_else(|| "App handle not initialized".to_string())?;
+        .ok_or_else(|| SoulsharkError::Internal("App handle not initialized".to_string()))?;
 
     let store = app_handle
         .store(SETTINGS_FILE)
-        .map_err(|e| format!("Failed to access settings store: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to access settings store: {}", e)))?;
 
     // Set the settings in the store
     store.set(SETTINGS_KEY, json!(settings));
@@ -198,7 +335,85 @@ pub fn save_settings(state: State<SettingsState>, settings: AppSettings) -> Resu
     // Save the store to persist the settings
     store
         .save()
-        .map_err(|e| format!("Failed to persist settings: {}", e))?;
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to persist settings: {}", e)))?;
 
     Ok(())
 }
+
+// Read the settings store file directly, without going through the store plugin.
+// Used by the headless CLI, which runs without a Tauri app handle.
+fn read_store_file(app_data_dir: &Path) -> Result<HashMap<String, Value>, SoulsharkError> {
+    let path = app_data_dir.join(SETTINGS_FILE);
+    let data = std::fs::read(&path)
+        .map_err(|e| SoulsharkError::Filesystem(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    serde_json::from_slice(&data).map_err(|e| SoulsharkError::Settings(format!("Failed to parse settings store: {}", e)))
+}
+
+// Load the settings from the store file in the given app data dir
+pub fn load_settings_from_dir(app_data_dir: &Path) -> Result<AppSettings, SoulsharkError> {
+    let mut store = read_store_file(app_data_dir)?;
+    let settings = store
+        .remove(SETTINGS_KEY)
+        .ok_or_else(|| SoulsharkError::NotFound("Settings not found".to_string()))?;
+
+    // Migrate in memory only; the app rewrites the file on its next start
+    let settings = migrations::migrate(settings).map_err(SoulsharkError::Settings)?;
+
+    serde_json::from_value::<AppSettings>(settings)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize settings: {}", e)))
+}
+
+// Load and decrypt the credentials from the store file in the given app data dir
+pub fn load_credentials_from_dir(app_data_dir: &Path) -> Result<Credentials, SoulsharkError> {
+    let mut store = read_store_file(app_data_dir)?;
+    let encrypted = match store.remove(CREDENTIALS_KEY) {
+        Some(value) => serde_json::from_value::<String>(value)
+            .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize encrypted data: {}", e)))?,
+        None => return Ok(Credentials::default()),
+    };
+
+    let kind = load_settings_from_dir(app_data_dir)
+        .map(|settings| settings.security.key_storage)
+        .unwrap_or_default();
+    let key = keystore::get_key(app_data_dir, kind, false)
+        .map_err(SoulsharkError::Settings)?
+        .ok_or_else(|| SoulsharkError::Settings("The encryption key for the stored credentials is missing".to_string()))?;
+    // Wiped when dropped, whether or not it deserializes
+    let decrypted = Zeroizing::new(crypto::decrypt(&key, &encrypted).map_err(|_| {
+        SoulsharkError::Settings(
+            "Stored credentials could not be decrypted, the key or the data is corrupted or was tampered with".to_string(),
+        )
+    })?);
+
+    let mut credentials = serde_json::from_str::<Credentials>(&decrypted)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to deserialize credentials: {}", e)))?;
+    credentials.sync_spotify_accounts();
+    Ok(credentials)
+}
+
+// Encrypt the credentials into the store file in the given app data dir,
+// keeping everything else in it. Used by the headless CLI to save refreshed
+// Spotify tokens.
+pub fn save_credentials_to_dir(app_data_dir: &Path, mut credentials: Credentials) -> Result<(), SoulsharkError> {
+    credentials.sync_spotify_accounts();
+    let mut store = read_store_file(app_data_dir)?;
+
+    let kind = load_settings_from_dir(app_data_dir)
+        .map(|settings| settings.security.key_storage)
+        .unwrap_or_default();
+    let key = keystore::get_key(app_data_dir, kind, false)
+        .map_err(SoulsharkError::Settings)?
+        .ok_or_else(|| SoulsharkError::Settings("The encryption key for the stored credentials is missing".to_string()))?;
+
+    let mut creds_json = serde_json::to_string(&credentials)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to serialize credentials: {}", e)))?;
+    let encrypted = crypto::encrypt(&key, &creds_json).map_err(SoulsharkError::Settings);
+    creds_json.zeroize();
+    store.insert(CREDENTIALS_KEY.to_string(), json!(encrypted?));
+
+    let data = serde_json::to_vec_pretty(&store)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to serialize settings store: {}", e)))?;
+    keystore::write_private_file(&app_data_dir.join(SETTINGS_FILE), &data)
+        .map_err(|e| SoulsharkError::Settings(format!("Failed to save credentials: {}", e)))
+}
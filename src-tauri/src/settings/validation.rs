@@ -0,0 +1,253 @@
+use crate::logging;
+use crate::naming::Template;
+use crate::settings::{AppSettings, SldlConfig};
+use crate::sldl;
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+use url::Url;
+
+// Audio formats sldl can prefer
+const AUDIO_FORMATS: &[&str] = &["flac", "mp3", "m4a", "aac", "ogg", "opus", "wav", "aiff", "alac", "wma"];
+
+// A problem with a single settings field, keyed by its path (e.g. "soulseek.downloads_path")
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+// Check the settings and return every invalid field
+pub fn validate(settings: &AppSettings) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+
+    // Soulseek
+    if let Some(message) = check_directory(&settings.soulseek.downloads_path) {
+        errors.push(FieldError::new("soulseek.downloads_path", message));
+    }
+
+    for format in settings.soulseek.preferred_format.split(',').map(str::trim) {
+        if !format.is_empty() && !AUDIO_FORMATS.contains(&format.to_lowercase().as_str()) {
+            errors.push(FieldError::new(
+                "soulseek.preferred_format",
+                format!("Unknown format '{}', expected one of {}", format, AUDIO_FORMATS.join(", ")),
+            ));
+        }
+    }
+
+    // Spotify
+    if let Err(message) = check_redirect_uri(&settings.spotify.redirect_uri) {
+        errors.push(FieldError::new("spotify.redirect_uri", message));
+    }
+
+    // Output
+    if let Err(message) = check_name_format(&settings.output.name_format) {
+        errors.push(FieldError::new("output.name_format", message));
+    }
+
+    // API
+    if settings.api.port == 0 {
+        errors.push(FieldError::new("api.port", "Port must be between 1 and 65535"));
+    }
+
+    // Watch folder
+    if settings.watch.enabled && settings.watch.path.trim().is_empty() {
+        errors.push(FieldError::new("watch.path", "Choose a folder to watch"));
+    } else if let Some(message) = check_directory(&settings.watch.path) {
+        errors.push(FieldError::new("watch.path", message));
+    }
+
+    if settings.watch.poll_interval_secs == 0 {
+        errors.push(FieldError::new("watch.poll_interval_secs", "Interval must be at least 1 second"));
+    }
+
+    // Endpoints
+    if let Err(message) = check_host_port(&settings.endpoints.soulseek_server) {
+        errors.push(FieldError::new("endpoints.soulseek_server", message));
+    }
+    if let Err(message) = check_url(&settings.endpoints.spotify_accounts_url) {
+        errors.push(FieldError::new("endpoints.spotify_accounts_url", message));
+    }
+    if let Err(message) = check_url(&settings.endpoints.spotify_api_url) {
+        errors.push(FieldError::new("endpoints.spotify_api_url", message));
+    }
+
+    // sldl
+    if !settings.sldl.path.trim().is_empty() && !Path::new(&settings.sldl.path).is_file() {
+        errors.push(FieldError::new("sldl.path", "No sldl binary at this path"));
+    }
+    validate_sldl_config(&settings.sldl.config, &mut errors);
+
+    // Peers
+    if settings.peers.min_transfers == 0 {
+        errors.push(FieldError::new("peers.min_transfers", "Must be at least 1 transfer"));
+    }
+    if !(settings.peers.max_failure_rate > 0.0 && settings.peers.max_failure_rate <= 1.0) {
+        errors.push(FieldError::new("peers.max_failure_rate", "Must be more than 0 and at most 1"));
+    }
+
+    // Logging
+    if !logging::LEVELS.contains(&settings.logging.level.as_str()) {
+        errors.push(FieldError::new(
+            "logging.level",
+            format!("Unknown level '{}', expected one of {}", settings.logging.level, logging::LEVELS.join(", ")),
+        ));
+    }
+
+    errors
+}
+
+// Check the options written to the sldl config file
+fn validate_sldl_config(config: &SldlConfig, errors: &mut Vec<FieldError>) {
+    for (section, conditions) in [("required", &config.required), ("preferred", &config.preferred)] {
+        let field = |name: &str| format!("sldl.config.{}.{}", section, name);
+
+        for format in &conditions.formats {
+            if !AUDIO_FORMATS.contains(&format.trim().to_lowercase().as_str()) {
+                errors.push(FieldError::new(
+                    &field("formats"),
+                    format!("Unknown format '{}', expected one of {}", format, AUDIO_FORMATS.join(", ")),
+                ));
+            }
+        }
+
+        let ranges = [
+            ("min_bitrate", conditions.min_bitrate, conditions.max_bitrate),
+            ("min_samplerate", conditions.min_samplerate, conditions.max_samplerate),
+            ("min_bitdepth", conditions.min_bitdepth, conditions.max_bitdepth),
+        ];
+        for (name, min, max) in ranges {
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    errors.push(FieldError::new(&field(name), "Minimum is above the maximum"));
+                }
+            }
+        }
+
+        if conditions
+            .banned_users
+            .iter()
+            .any(|user| user.trim().is_empty() || user.contains(',') || user.contains('\n'))
+        {
+            errors.push(FieldError::new(&field("banned_users"), "User names can't be empty or contain commas"));
+        }
+    }
+
+    // Search
+    let search = &config.search;
+    let positive = [
+        ("search_timeout_ms", search.search_timeout_ms),
+        ("max_stale_time_ms", search.max_stale_time_ms),
+        ("concurrent_downloads", search.concurrent_downloads),
+        ("searches_per_time", search.searches_per_time),
+        ("searches_renew_time_secs", search.searches_renew_time_secs),
+    ];
+    for (name, value) in positive {
+        if value == Some(0) {
+            errors.push(FieldError::new(&format!("sldl.config.search.{}", name), "Must be at least 1"));
+        }
+    }
+    if search.fast_search_min_up_speed.map_or(false, |speed| speed < 0.0) {
+        errors.push(FieldError::new("sldl.config.search.fast_search_min_up_speed", "Speed can't be negative"));
+    }
+
+    // Album
+    let track_count = config.album.track_count.trim();
+    if !track_count.is_empty() && !Regex::new(r"^\d+[+-]?$").unwrap().is_match(track_count) {
+        errors.push(FieldError::new(
+            "sldl.config.album.track_count",
+            "Expected a number of tracks, e.g. 12, 12+ or 12-",
+        ));
+    }
+    if !matches!(config.album.art.trim(), "" | "default" | "largest" | "most") {
+        errors.push(FieldError::new(
+            "sldl.config.album.art",
+            format!("Unknown album art option '{}', expected default, largest or most", config.album.art),
+        ));
+    }
+
+    // Input
+    for (name, value) in [
+        ("youtube_api_key", &config.input.youtube_api_key),
+        ("yt_dlp_argument", &config.input.yt_dlp_argument),
+    ] {
+        if value.contains('\n') || value.contains('\r') {
+            errors.push(FieldError::new(&format!("sldl.config.input.{}", name), "Line breaks are not allowed"));
+        }
+    }
+
+    // Passthrough lines
+    for (index, line) in config.extra_lines.iter().enumerate() {
+        if let Err(message) = sldl::config::check_line(line) {
+            errors.push(FieldError::new(
+                "sldl.config.extra_lines",
+                format!("Line {}: {}", index + 1, message),
+            ));
+        }
+    }
+}
+
+// An optional directory must be absolute and exist
+fn check_directory(path: &str) -> Option<String> {
+    if path.trim().is_empty() {
+        return None;
+    }
+
+    let path = Path::new(path);
+    if !path.is_absolute() {
+        Some("Path must be absolute".to_string())
+    } else if !path.is_dir() {
+        Some("Directory does not exist".to_string())
+    } else {
+        None
+    }
+}
+
+fn check_url(value: &str) -> Result<(), String> {
+    let url = Url::parse(value).map_err(|e| format!("Invalid URL: {}", e))?;
+    match url.scheme() {
+        "http" | "https" => Ok(()),
+        scheme => Err(format!("Unsupported URL scheme '{}'", scheme)),
+    }
+}
+
+// The callback server listens on the loopback interface over plain http
+fn check_redirect_uri(value: &str) -> Result<(), String> {
+    let url = Url::parse(value).map_err(|e| format!("Invalid URL: {}", e))?;
+    if url.scheme() != "http" {
+        return Err("Redirect URI must use http".to_string());
+    }
+    if !matches!(url.host_str(), Some("localhost") | Some("127.0.0.1")) {
+        return Err("Redirect URI must point to localhost or 127.0.0.1".to_string());
+    }
+    Ok(())
+}
+
+fn check_host_port(value: &str) -> Result<(), String> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .ok_or_else(|| "Expected host:port".to_string())?;
+
+    if host.is_empty() {
+        return Err("Expected host:port".to_string());
+    }
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(()),
+        _ => Err(format!("Invalid port '{}'", port)),
+    }
+}
+
+// Check the name format parses: balanced braces and parentheses, known tags
+// and no modifiers, which sldl doesn't have
+pub fn check_name_format(format: &str) -> Result<(), String> {
+    Template::parse_for_sldl(format).map(|_| ())
+}
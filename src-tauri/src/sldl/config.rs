@@ -0,0 +1,297 @@
+use crate::downloads::album::AlbumMode;
+use crate::settings::{AppSettings, Credentials, SldlConditions};
+use regex::Regex;
+use std::fmt::Display;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+// Folder in the app data dir holding the config file of each running job
+const CONFIG_DIR: &str = "sldl";
+
+// Options the app sets itself, on the command line or in the config file.
+// They can't be given as extra options or config lines.
+pub const MANAGED_KEYS: &[&str] = &[
+    "user",
+    "pass",
+    "path",
+    "config",
+    "spotify-id",
+    "spotify-secret",
+    "spotify-token",
+    "spotify-refresh",
+    "name-format",
+];
+
+// Options that run commands. Passthrough lines, which may come from a shared
+// settings file or a guide, must not run programs on every download.
+const COMMAND_KEYS: &[&str] = &["on-complete", "yt-dlp-argument"];
+
+// Options `render` writes from the sldl settings, or that replace what sldl
+// downloads. Conditions also have a `pref-` variant.
+const RENDERED_KEYS: &[&str] = &[
+    "input",
+    "input-type",
+    "search-timeout",
+    "max-stale-time",
+    "concurrent-downloads",
+    "searches-per-time",
+    "searches-renew-time",
+    "fast-search",
+    "fast-search-delay",
+    "fast-search-min-up-speed",
+    "album",
+    "album-track-count",
+    "album-art",
+    "album-art-only",
+    "album-ignore-fails",
+    "yt-key",
+    "get-deleted",
+    "deleted-only",
+    "yt-dlp",
+    "artist-maybe-wrong",
+];
+const CONDITION_KEYS: &[&str] = &[
+    "format",
+    "min-bitrate",
+    "max-bitrate",
+    "min-samplerate",
+    "max-samplerate",
+    "min-bitdepth",
+    "max-bitdepth",
+    "length-tol",
+    "strict-title",
+    "strict-artist",
+    "strict-album",
+    "banned-users",
+];
+
+// Whether an option, with or without its leading dashes, is one the app sets
+pub fn is_managed(key: &str) -> bool {
+    let key = key.trim().trim_start_matches('-').to_ascii_lowercase();
+    MANAGED_KEYS.contains(&key.as_str())
+}
+
+// Render the sldl config file for a query from the sldl settings. Only set
+// options are written, so everything else keeps sldl's default. Album
+// downloads pass their album state to run in album mode. Blacklisted peers
+// are banned along with the users banned in the settings.
+pub fn render(
+    settings: &AppSettings,
+    credentials: &Credentials,
+    query: &str,
+    album_mode: Option<&AlbumMode>,
+    banned_peers: &[String],
+) -> String {
+    let config = &settings.sldl.config;
+    let mut lines = vec!["# Written by SoulShark for a single run, edit the sldl settings instead".to_string()];
+
+    // Secrets go in the file rather than on the command line, where other
+    // users of the machine could read them from the process list
+    push_secret(&mut lines, "pass", credentials.soulseek_password.as_deref());
+    if super::uses_spotify(query) {
+        push_secret(&mut lines, "spotify-secret", credentials.spotify_client_secret.as_deref());
+        push_secret(&mut lines, "spotify-token", credentials.spotify_access_token.as_deref());
+        push_secret(&mut lines, "spotify-refresh", credentials.spotify_refresh_token.as_deref());
+    }
+
+    // Conditions. Without preferred formats the preferred format from the
+    // Soulseek settings is used.
+    let mut required = config.required.clone();
+    for peer in banned_peers {
+        if !required.banned_users.contains(peer) {
+            required.banned_users.push(peer.clone());
+        }
+    }
+    push_conditions(&mut lines, "", &required);
+    let mut preferred = config.preferred.clone();
+    if preferred.formats.is_empty() {
+        preferred.formats = settings
+            .soulseek
+            .preferred_format
+            .split(',')
+            .map(|format| format.trim().to_string())
+            .filter(|format| !format.is_empty())
+            .collect();
+    }
+    push_conditions(&mut lines, "pref-", &preferred);
+
+    // Search
+    let search = &config.search;
+    push_value(&mut lines, "search-timeout", search.search_timeout_ms);
+    push_value(&mut lines, "max-stale-time", search.max_stale_time_ms);
+    push_value(&mut lines, "concurrent-downloads", search.concurrent_downloads);
+    push_value(&mut lines, "searches-per-time", search.searches_per_time);
+    push_value(&mut lines, "searches-renew-time", search.searches_renew_time_secs);
+    push_flag(&mut lines, "fast-search", search.fast_search);
+    push_value(&mut lines, "fast-search-delay", search.fast_search_delay_ms);
+    push_value(&mut lines, "fast-search-min-up-speed", search.fast_search_min_up_speed);
+
+    // Album mode, for album downloads until they fall back to single tracks.
+    // They need a folder with at least the Spotify tracklist.
+    let album = &config.album;
+    let enabled = album_mode.map_or(album.enabled, AlbumMode::is_active);
+    push_flag(&mut lines, "album", enabled);
+    if enabled {
+        let track_count = album_mode
+            .and_then(|album_mode| album_mode.expected_tracks)
+            .map_or_else(|| album.track_count.clone(), |count| format!("{}+", count));
+        push_text(&mut lines, "album-track-count", &track_count);
+        push_text(&mut lines, "album-art", &album.art);
+        push_flag(&mut lines, "album-art-only", album.art_only);
+        push_flag(&mut lines, "album-ignore-fails", album.ignore_fails);
+    }
+
+    // Input
+    let input = &config.input;
+    push_value(&mut lines, "input-type", input_type(query));
+    push_text(&mut lines, "yt-key", &input.youtube_api_key);
+    push_flag(&mut lines, "get-deleted", input.youtube_get_deleted);
+    push_flag(&mut lines, "deleted-only", input.youtube_deleted_only);
+    push_flag(&mut lines, "yt-dlp", input.yt_dlp);
+    push_text(&mut lines, "yt-dlp-argument", &input.yt_dlp_argument);
+    push_flag(&mut lines, "artist-maybe-wrong", input.artist_maybe_wrong);
+
+    // Passthrough lines go last so they can open profile sections
+    if !config.extra_lines.is_empty() {
+        lines.push(String::new());
+        lines.extend(config.extra_lines.iter().map(|line| line.trim().to_string()));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+// sldl input type for YouTube and Bandcamp links, None to let sldl decide
+pub fn input_type(query: &str) -> Option<&'static str> {
+    let url = Url::parse(query.trim()).ok()?;
+    let host = url.host_str()?.trim_start_matches("www.");
+
+    if host == "youtu.be" || host == "youtube.com" || host.ends_with(".youtube.com") {
+        Some("youtube")
+    } else if host == "bandcamp.com" || host.ends_with(".bandcamp.com") {
+        Some("bandcamp")
+    } else {
+        None
+    }
+}
+
+// Write the config file of a job to the app data dir, returning its path. It
+// holds the credentials, so on Unix only the owner can read it.
+pub fn write(app_data_dir: &Path, download_id: &str, contents: &str) -> Result<PathBuf, String> {
+    let dir = app_data_dir.join(CONFIG_DIR);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create sldl config folder: {}", e))?;
+
+    let path = dir.join(format!("{}.conf", download_id));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|e| format!("Failed to write sldl config: {}", e))?;
+    Ok(path)
+}
+
+// Remove a job's config file once sldl has exited
+pub fn remove(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        tracing::warn!("Failed to remove sldl config {}: {}", path.display(), e);
+    }
+}
+
+// Check a passthrough line: blank, a comment, a "[profile]" section or
+// "key = value" for an option the app doesn't set itself and that doesn't
+// run commands
+pub fn check_line(line: &str) -> Result<(), String> {
+    let line = line.trim();
+    if line.contains('\n') || line.contains('\r') {
+        return Err("Line breaks are not allowed".to_string());
+    }
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(());
+    }
+    if line.starts_with('[') {
+        return match line.strip_suffix(']') {
+            Some(name) if !name[1..].trim().is_empty() => Ok(()),
+            _ => Err(format!("Invalid section '{}', expected [name]", line)),
+        };
+    }
+
+    let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| format!("Expected 'key = value', got '{}'", line))?;
+    let key = key.trim();
+    let re = Regex::new(r"^[a-z0-9][a-z0-9-]*$").unwrap();
+    if !re.is_match(key) {
+        return Err(format!("Invalid option name '{}'", key));
+    }
+    if is_managed(key) {
+        return Err(format!("'{}' is set by SoulShark", key));
+    }
+    if COMMAND_KEYS.contains(&key) {
+        return Err(format!("'{}' runs commands and can't be set here", key));
+    }
+    if RENDERED_KEYS.contains(&key) || CONDITION_KEYS.contains(&key.strip_prefix("pref-").unwrap_or(key)) {
+        return Err(format!("'{}' has its own setting", key));
+    }
+    if value.trim().is_empty() {
+        return Err(format!("No value for '{}'", key));
+    }
+    Ok(())
+}
+
+fn push_conditions(lines: &mut Vec<String>, prefix: &str, conditions: &SldlConditions) {
+    let key = |name: &str| format!("{}{}", prefix, name);
+
+    if !conditions.formats.is_empty() {
+        let formats: Vec<&str> = conditions.formats.iter().map(|format| format.trim()).collect();
+        lines.push(format!("{} = {}", key("format"), formats.join(",")));
+    }
+    push_value(lines, &key("min-bitrate"), conditions.min_bitrate);
+    push_value(lines, &key("max-bitrate"), conditions.max_bitrate);
+    push_value(lines, &key("min-samplerate"), conditions.min_samplerate);
+    push_value(lines, &key("max-samplerate"), conditions.max_samplerate);
+    push_value(lines, &key("min-bitdepth"), conditions.min_bitdepth);
+    push_value(lines, &key("max-bitdepth"), conditions.max_bitdepth);
+    push_value(lines, &key("length-tol"), conditions.length_tol);
+    push_flag(lines, &key("strict-title"), conditions.strict_title);
+    push_flag(lines, &key("strict-artist"), conditions.strict_artist);
+    push_flag(lines, &key("strict-album"), conditions.strict_album);
+    if !conditions.banned_users.is_empty() {
+        lines.push(format!("{} = {}", key("banned-users"), conditions.banned_users.join(",")));
+    }
+}
+
+fn push_value(lines: &mut Vec<String>, key: &str, value: Option<impl Display>) {
+    if let Some(value) = value {
+        lines.push(format!("{} = {}", key, value));
+    }
+}
+
+fn push_text(lines: &mut Vec<String>, key: &str, value: &str) {
+    if !value.trim().is_empty() {
+        lines.push(format!("{} = {}", key, value.trim()));
+    }
+}
+
+fn push_flag(lines: &mut Vec<String>, key: &str, value: bool) {
+    if value {
+        lines.push(format!("{} = true", key));
+    }
+}
+
+// Secrets are written as they are; one with a line break can't be
+fn push_secret(lines: &mut Vec<String>, key: &str, value: Option<&str>) {
+    match value {
+        Some(value) if value.contains(['\n', '\r']) => {
+            tracing::warn!("Not passing {} to sldl, it contains a line break", key);
+        }
+        Some(value) if !value.is_empty() => lines.push(format!("{} = {}", key, value)),
+        _ => {}
+    }
+}
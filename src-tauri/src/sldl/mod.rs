@@ -0,0 +1,128 @@
+use crate::naming::Template;
+use crate::settings::AppSettings;
+use crate::spotify::link::{is_short_link, SpotifyLink, SpotifyLinkKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// Name of the bundled sidecar binary
+pub const SIDECAR_NAME: &str = "sldl";
+
+// Check if a query expands to several tracks: a Spotify playlist, album or
+// artist, or the liked songs pseudo-playlist
+pub fn is_playlist_query(query: &str) -> bool {
+    query == "spotify-likes" || SpotifyLink::parse(query).map_or(false, |link| link.is_collection())
+}
+
+// Rewrite Spotify links and URIs to the canonical URL sldl understands,
+// dropping locale prefixes and tracking parameters
+pub fn normalize_query(query: &str) -> String {
+    SpotifyLink::parse(query)
+        .map(|link| link.url())
+        .unwrap_or_else(|| query.trim().to_string())
+}
+
+// Default title shown for a download until sldl reports the real one
+pub fn default_title(query: &str, is_playlist: bool) -> String {
+    if query == "spotify-likes" {
+        return "Spotify Liked Songs".to_string();
+    }
+
+    // For Spotify links, use a better default title than the URL
+    match SpotifyLink::parse(query).map(|link| link.kind) {
+        Some(SpotifyLinkKind::Playlist) => "Spotify Playlist (Loading...)".to_string(),
+        Some(SpotifyLinkKind::Album) => "Spotify Album (Loading...)".to_string(),
+        Some(SpotifyLinkKind::Artist) => "Spotify Artist (Loading...)".to_string(),
+        Some(SpotifyLinkKind::Track) => "Spotify Track (Loading...)".to_string(),
+        None if is_playlist => "Spotify Playlist (Loading...)".to_string(),
+        None => query.to_string(),
+    }
+}
+
+// Whether sldl needs the Spotify credentials for a query: a Spotify link or
+// URI, a spotify.link share link or "spotify-likes". Searches that merely
+// mention Spotify don't.
+pub fn uses_spotify(query: &str) -> bool {
+    let query = query.trim();
+    query == "spotify-likes" || SpotifyLink::parse(query).is_some() || is_short_link(query)
+}
+
+// Build the sldl command line for a query from the app settings, pointing
+// sldl at the config file rendered by `config::render`, which holds the
+// credentials
+pub fn build_args(
+    query: &str,
+    options: &HashMap<String, String>,
+    settings: &AppSettings,
+    config_path: &Path,
+) -> Vec<String> {
+    let mut args = Vec::new();
+
+    // Add the query
+    args.push(query.to_string());
+
+    // Add the Soulseek user; the password is in the config file
+    if !settings.soulseek.username.is_empty() {
+        args.push("--user".to_string());
+        args.push(settings.soulseek.username.clone());
+    }
+
+    // Add the Spotify client ID if the query is a Spotify URL or
+    // "spotify-likes"; the secret and tokens are in the config file
+    if uses_spotify(query) && !settings.spotify.client_id.is_empty() {
+        args.push("--spotify-id".to_string());
+        args.push(settings.spotify.client_id.clone());
+    }
+
+    // Add download path
+    if !settings.soulseek.downloads_path.is_empty() {
+        args.push("--path".to_string());
+        args.push(settings.soulseek.downloads_path.clone());
+    }
+
+    // Conditions, search and album options are in the config file
+    args.push("--config".to_string());
+    args.push(config_path.to_string_lossy().to_string());
+
+    // Add name format
+    // Validation refuses modifiers (e.g. {track:02}), which sldl doesn't know;
+    // any left in older settings are dropped
+    if !settings.output.name_format.is_empty() {
+        let name_format = Template::parse(&settings.output.name_format)
+            .map(|template| template.to_sldl_format())
+            .unwrap_or_else(|_| settings.output.name_format.clone());
+        args.push("--name-format".to_string());
+        args.push(name_format);
+    }
+
+    // Add any additional options, except those the app sets itself
+    for (key, value) in options {
+        if config::is_managed(key) {
+            tracing::warn!("Ignoring the option {}, it is set by SoulShark", key);
+            continue;
+        }
+        args.push(format!("--{}", key));
+        args.push(value.clone());
+    }
+
+    args
+}
+
+// The sldl binary set in the settings, or the sidecar next to the current
+// executable, the same place Tauri resolves it from when running inside the app
+pub fn sidecar_path(settings: &AppSettings) -> Result<PathBuf, String> {
+    if !settings.sldl.path.is_empty() {
+        return Ok(PathBuf::from(&settings.sldl.path));
+    }
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate executable: {}", e))?;
+    let dir = exe
+        .parent()
+        .ok_or_else(|| "Executable has no parent directory".to_string())?;
+
+    Ok(dir.join(format!("{}{}", SIDECAR_NAME, std::env::consts::EXE_SUFFIX)))
+}
+
+// Module exports
+pub mod config;
+pub mod parser;
+pub mod version;
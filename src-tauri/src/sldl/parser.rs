@@ -0,0 +1,121 @@
+use regex::Regex;
+
+// A single line of sldl stdout, classified by what it tells us about the job
+#[derive(Debug, Clone, PartialEq)]
+pub enum SldlLine {
+    TrackCount(usize),
+    LoadingPlaylist,
+    PlaylistName { name: String, creator: String },
+    Searching(String),
+    Initialize(String),
+    InProgress(String),
+    NotFound(String),
+    // A transfer that failed after it started
+    Failed(String),
+    Succeeded { path: String, bitrate_kbps: u32, size_mb: f64 },
+    Completed { succeeded: usize, failed: usize },
+    Other,
+}
+
+// Parser holding the compiled regex patterns for sldl output
+pub struct SldlParser {
+    playlist_re: Regex,
+    loading_playlist_re: Regex,
+    playlist_name_re: Regex,
+    searching_re: Regex,
+    initialize_re: Regex,
+    progress_re: Regex,
+    success_re: Regex,
+    completed_re: Regex,
+    not_found_re: Regex,
+    failed_re: Regex,
+}
+
+impl SldlParser {
+    pub fn new() -> Self {
+        Self {
+            playlist_re: Regex::new(r"Downloading (\d+) tracks:").unwrap(),
+            loading_playlist_re: Regex::new(r"Loading Spotify playlist").unwrap(),
+            playlist_name_re: Regex::new(r"Playlist: (.+) by (.+)").unwrap(),
+            searching_re: Regex::new(r"Searching: (.+)").unwrap(),
+            initialize_re: Regex::new(r"Initialize:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap(),
+            progress_re: Regex::new(r"InProgress:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap(),
+            success_re: Regex::new(r"Succeeded:\s+(.+)\s+\[(\d+)s/(\d+)kbps/([0-9.]+)MB\]").unwrap(),
+            completed_re: Regex::new(r"Completed: (\d+) succeeded, (\d+) failed").unwrap(),
+            not_found_re: Regex::new(r"Not found: (.+)").unwrap(),
+            failed_re: Regex::new(r"^\s*Failed:\s+(.+?)(?:\s+\[[^\]]*\])?\s*$").unwrap(),
+        }
+    }
+
+    // Classify a line of output. Patterns are checked in a fixed order since
+    // some lines could match more than one of them.
+    pub fn parse(&self, line: &str) -> SldlLine {
+        if let Some(caps) = self.playlist_re.captures(line) {
+            if let Ok(count) = caps[1].parse::<usize>() {
+                return SldlLine::TrackCount(count);
+            }
+            return SldlLine::Other;
+        }
+
+        if self.loading_playlist_re.is_match(line) {
+            return SldlLine::LoadingPlaylist;
+        }
+
+        if let Some(caps) = self.playlist_name_re.captures(line) {
+            return SldlLine::PlaylistName {
+                name: caps[1].to_string(),
+                creator: caps[2].to_string(),
+            };
+        }
+
+        if let Some(caps) = self.searching_re.captures(line) {
+            return SldlLine::Searching(caps[1].to_string());
+        }
+
+        if let Some(caps) = self.initialize_re.captures(line) {
+            return SldlLine::Initialize(caps[1].to_string());
+        }
+
+        if let Some(caps) = self.progress_re.captures(line) {
+            return SldlLine::InProgress(caps[1].to_string());
+        }
+
+        if let Some(caps) = self.not_found_re.captures(line) {
+            return SldlLine::NotFound(caps[1].to_string());
+        }
+
+        if let Some(caps) = self.success_re.captures(line) {
+            return SldlLine::Succeeded {
+                path: caps[1].to_string(),
+                bitrate_kbps: caps[3].parse().unwrap_or(0),
+                size_mb: caps[4].parse().unwrap_or(0.0),
+            };
+        }
+
+        if let Some(caps) = self.failed_re.captures(line) {
+            return SldlLine::Failed(caps[1].to_string());
+        }
+
+        if let Some(caps) = self.completed_re.captures(line) {
+            if let (Ok(succeeded), Ok(failed)) = (caps[1].parse::<usize>(), caps[2].parse::<usize>()) {
+                return SldlLine::Completed { succeeded, failed };
+            }
+        }
+
+        SldlLine::Other
+    }
+}
+
+// Soulseek user a transfer is from. sldl shows remote files as
+// `user\folder\file`, so anything without a backslash has no known peer.
+pub fn peer_of(path: &str) -> Option<&str> {
+    path.split_once('\\')
+        .map(|(user, _)| user.trim())
+        .filter(|user| !user.is_empty())
+}
+
+impl Default for SldlParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,182 @@
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// sldl versions whose output the parser is known to understand: from
+// MIN_SUPPORTED up to, but not including, the next major version
+pub const MIN_SUPPORTED: SldlVersion = SldlVersion::new(2, 3, 0);
+pub const MAX_SUPPORTED: SldlVersion = SldlVersion::new(3, 0, 0);
+
+// How long a probe may run before the binary is given up on
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A line holding only the version or the banner, e.g. "2.4.6",
+// "sldl v2.4.6+a1b2c3" or "slsk-batchdl version 2.4.6"
+static VERSION_LINE: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+    Regex::new(r"(?i)^\s*(?:sldl|slsk-batchdl)?[\s:]*(?:version)?[\s:]*v?(\d+)\.(\d+)\.(\d+)(?:[+-]\S*)?\s*$").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SldlVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl SldlVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    // The version from the version or banner line of sldl's output. Numbers
+    // elsewhere, like option defaults or paths in the help text, are ignored.
+    pub fn parse(output: &str) -> Option<Self> {
+        let caps = output.lines().find_map(|line| VERSION_LINE.captures(line))?;
+        Some(Self {
+            major: caps[1].parse().ok()?,
+            minor: caps[2].parse().ok()?,
+            patch: caps[3].parse().ok()?,
+        })
+    }
+
+    pub fn is_supported(&self) -> bool {
+        *self >= MIN_SUPPORTED && *self < MAX_SUPPORTED
+    }
+}
+
+impl fmt::Display for SldlVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    Supported,
+    Unsupported,
+    // No version could be read, e.g. the binary is missing or too old to
+    // report one
+    Unknown,
+}
+
+// The sldl binary in use and what it reported
+#[derive(Debug, Clone, Serialize)]
+pub struct SldlInfo {
+    // Configured path, or None for the bundled sidecar
+    pub path: Option<String>,
+    pub version: Option<String>,
+    pub compatibility: Compatibility,
+    // Range the app supports, for showing next to a warning
+    pub supported: String,
+    // What went wrong running the binary, if anything
+    pub error: Option<String>,
+}
+
+impl SldlInfo {
+    // Info from the output of a version probe
+    pub fn from_output(path: Option<String>, output: &str) -> Self {
+        let version = SldlVersion::parse(output);
+        Self {
+            path,
+            version: version.map(|version| version.to_string()),
+            compatibility: match version {
+                Some(version) if version.is_supported() => Compatibility::Supported,
+                Some(_) => Compatibility::Unsupported,
+                None => Compatibility::Unknown,
+            },
+            supported: supported_range(),
+            error: None,
+        }
+    }
+
+    // Info for a binary that couldn't be run
+    pub fn from_error(path: Option<String>, error: String) -> Self {
+        Self {
+            path,
+            version: None,
+            compatibility: Compatibility::Unknown,
+            supported: supported_range(),
+            error: Some(error),
+        }
+    }
+}
+
+fn supported_range() -> String {
+    format!(">= {}, < {}", MIN_SUPPORTED, MAX_SUPPORTED)
+}
+
+// Ask an sldl binary for its version: `--version` first, then the help text,
+// which builds without that option still print. `configured` is the path
+// from the settings, None for the bundled sidecar.
+pub fn probe(binary: &Path, configured: Option<String>) -> SldlInfo {
+    for arg in ["--version", "--help"] {
+        let output = match run_with_timeout(Command::new(binary).arg(arg), PROBE_TIMEOUT) {
+            Ok(output) => output,
+            Err(e) => {
+                return SldlInfo::from_error(configured, format!("Failed to run {}: {}", binary.display(), e));
+            }
+        };
+
+        let text = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let info = SldlInfo::from_output(configured.clone(), &text);
+        if info.version.is_some() {
+            return info;
+        }
+    }
+
+    SldlInfo::from_output(configured, "")
+}
+
+// Like `Command::output`, but kill the process if it runs longer than the
+// timeout, e.g. a binary waiting for input or the network
+fn run_with_timeout(command: &mut Command, timeout: Duration) -> Result<Output, String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Drain both pipes so a chatty binary doesn't block on a full pipe
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+    let stdout_thread = thread::spawn(move || read_all(stdout.as_mut()));
+    let stderr_thread = thread::spawn(move || read_all(stderr.as_mut()));
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("no answer within {} seconds", timeout.as_secs()));
+            }
+            None => thread::sleep(Duration::from_millis(20)),
+        }
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout_thread.join().unwrap_or_default(),
+        stderr: stderr_thread.join().unwrap_or_default(),
+    })
+}
+
+fn read_all(pipe: Option<&mut impl Read>) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    if let Some(pipe) = pipe {
+        let _ = pipe.read_to_end(&mut buffer);
+    }
+    buffer
+}
@@ -0,0 +1,111 @@
+use crate::error::SoulsharkError;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// Server message codes
+const LOGIN_CODE: u32 = 1;
+
+// Client version reported at login, the same as current Soulseek clients
+const CLIENT_VERSION: u32 = 160;
+const MINOR_VERSION: u32 = 1;
+
+// Upper bound for a server message, anything larger is not a login reply
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+fn write_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) {
+    write_u32(buf, value.len() as u32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+fn read_u32(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let bytes = data.get(*pos..*pos + 4)?;
+    *pos += 4;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> Option<String> {
+    let len = read_u32(data, pos)? as usize;
+    let bytes = data.get(*pos..*pos + len)?;
+    *pos += len;
+    Some(String::from_utf8_lossy(bytes).to_string())
+}
+
+// Build a login message: length prefix, code, then the payload
+fn login_message(username: &str, password: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_u32(&mut body, LOGIN_CODE);
+    write_string(&mut body, username);
+    write_string(&mut body, password);
+    write_u32(&mut body, CLIENT_VERSION);
+    write_string(&mut body, &format!("{:x}", md5::compute(format!("{}{}", username, password))));
+    write_u32(&mut body, MINOR_VERSION);
+
+    let mut message = Vec::with_capacity(body.len() + 4);
+    write_u32(&mut message, body.len() as u32);
+    message.extend_from_slice(&body);
+    message
+}
+
+// Log in to a Soulseek server and disconnect again, returning the server
+// greeting. Note that the server registers unknown usernames on first login.
+pub fn test_login(server: &str, username: &str, password: &str, timeout: Duration) -> Result<String, SoulsharkError> {
+    let addr = server
+        .to_socket_addrs()
+        .map_err(|e| SoulsharkError::Network(format!("Failed to resolve {}: {}", server, e)))?
+        .next()
+        .ok_or_else(|| SoulsharkError::Network(format!("Failed to resolve {}", server)))?;
+
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| SoulsharkError::Network(format!("Failed to connect to {}: {}", server, e)))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(|e| SoulsharkError::Network(format!("Failed to configure connection: {}", e)))?;
+
+    stream
+        .write_all(&login_message(username, password))
+        .map_err(|e| SoulsharkError::Network(format!("Failed to send login: {}", e)))?;
+
+    // Skip anything the server sends before the login reply
+    loop {
+        let mut len = [0u8; 4];
+        stream
+            .read_exact(&mut len)
+            .map_err(|e| SoulsharkError::Network(format!("No login reply from server: {}", e)))?;
+
+        let len = u32::from_le_bytes(len) as usize;
+        if !(4..=MAX_MESSAGE_LEN).contains(&len) {
+            return Err(SoulsharkError::Network("Unexpected reply from server".to_string()));
+        }
+
+        let mut data = vec![0u8; len];
+        stream
+            .read_exact(&mut data)
+            .map_err(|e| SoulsharkError::Network(format!("Failed to read login reply: {}", e)))?;
+
+        let mut pos = 0;
+        if read_u32(&data, &mut pos) != Some(LOGIN_CODE) {
+            continue;
+        }
+
+        let success = data.get(pos).copied().unwrap_or(0) == 1;
+        pos += 1;
+        let message = read_string(&data, &mut pos).unwrap_or_default();
+
+        if success {
+            return Ok(message);
+        }
+
+        return Err(SoulsharkError::Auth(match message.as_str() {
+            "INVALIDPASS" => "Wrong password".to_string(),
+            "INVALIDUSERNAME" => "Invalid username".to_string(),
+            "" => "Login rejected".to_string(),
+            reason => format!("Login rejected: {}", reason),
+        }));
+    }
+}
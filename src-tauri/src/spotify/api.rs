@@ -0,0 +1,166 @@
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const API_BASE: &str = "https://api.spotify.com/v1";
+
+// Current user from /me
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+// Artist as returned by the Web API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artist {
+    pub id: String,
+    pub name: String,
+}
+
+// Simplified album object from /artists/{id}/albums, also read from /albums/{id}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Album {
+    pub id: String,
+    pub name: String,
+    pub album_type: String,
+    #[serde(default)]
+    pub album_group: Option<String>,
+    pub release_date: String,
+    pub total_tracks: usize,
+    #[serde(default)]
+    pub artists: Vec<Artist>,
+}
+
+// Paging object read only for its total, e.g. a playlist's tracks
+#[derive(Debug, Deserialize)]
+struct Total {
+    total: usize,
+}
+
+// Album read only for its track count
+#[derive(Debug, Deserialize)]
+struct AlbumTrackCount {
+    total_tracks: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<String>,
+}
+
+// Minimal Spotify Web API client for the endpoints the backend needs
+pub struct SpotifyApi {
+    client: Client,
+    access_token: String,
+    base_url: String,
+}
+
+impl SpotifyApi {
+    pub fn new(access_token: String) -> Self {
+        Self::with_base_url(access_token, API_BASE)
+    }
+
+    // Client for another API base URL, e.g. a local stand-in
+    pub fn with_base_url(access_token: String, base_url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            access_token,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, String> {
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.access_token)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to send Spotify request: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Spotify request failed ({}): {}", status, error_text));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse Spotify response: {}", e))
+    }
+
+    pub async fn get_current_user(&self) -> Result<User, String> {
+        self.get(&format!("{}/me", self.base_url)).await
+    }
+
+    pub async fn get_artist(&self, artist_id: &str) -> Result<Artist, String> {
+        self.get(&format!("{}/artists/{}", self.base_url, artist_id)).await
+    }
+
+    // Fetch every album of an artist in the given groups (album, single, compilation...)
+    pub async fn get_artist_albums(&self, artist_id: &str, groups: &[&str]) -> Result<Vec<Album>, String> {
+        let mut albums = Vec::new();
+        let mut next = Some(format!(
+            "{}/artists/{}/albums?include_groups={}&limit=50",
+            self.base_url,
+            artist_id,
+            groups.join(",")
+        ));
+
+        while let Some(url) = next {
+            let page: Page<Album> = self.get(&url).await?;
+            albums.extend(page.items);
+            next = page.next;
+        }
+
+        Ok(albums)
+    }
+
+    pub async fn get_playlist_track_count(&self, playlist_id: &str) -> Result<usize, String> {
+        let tracks: Total = self
+            .get(&format!("{}/playlists/{}/tracks?limit=1&fields=total", self.base_url, playlist_id))
+            .await?;
+        Ok(tracks.total)
+    }
+
+    pub async fn get_album(&self, album_id: &str) -> Result<Album, String> {
+        self.get(&format!("{}/albums/{}", self.base_url, album_id)).await
+    }
+
+    pub async fn get_album_track_count(&self, album_id: &str) -> Result<usize, String> {
+        let album: AlbumTrackCount = self.get(&format!("{}/albums/{}", self.base_url, album_id)).await?;
+        Ok(album.total_tracks)
+    }
+
+    // Number of liked songs
+    pub async fn get_saved_track_count(&self) -> Result<usize, String> {
+        let tracks: Total = self.get(&format!("{}/me/tracks?limit=1", self.base_url)).await?;
+        Ok(tracks.total)
+    }
+}
+
+// Check a client ID and secret with the client credentials grant
+pub async fn check_client_credentials(accounts_url: &str, client_id: &str, client_secret: &str) -> Result<(), String> {
+    let response = Client::new()
+        .post(format!("{}/api/token", accounts_url.trim_end_matches('/')))
+        .basic_auth(client_id, Some(client_secret))
+        .form(&[("grant_type", "client_credentials")])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send token request: {}", e))?;
+
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    let status = response.status();
+    let error_text = response.text().await.unwrap_or_default();
+    if status == reqwest::StatusCode::BAD_REQUEST && error_text.contains("invalid_client") {
+        return Err("Invalid client ID or secret".to_string());
+    }
+    Err(format!("Token request failed ({}): {}", status, error_text))
+}
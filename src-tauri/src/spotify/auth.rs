@@ -0,0 +1,159 @@
+use rand::{rngs::OsRng, Rng};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+// Scopes the app asks for
+pub const SCOPES: &str = "user-read-private user-read-email playlist-read-private user-library-read";
+
+// Characters allowed in a PKCE code verifier (RFC 7636)
+const VERIFIER_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+const VERIFIER_LEN: usize = 128;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    // Refresh responses may omit it, in which case the old one stays valid
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+// PKCE verifier kept until the callback, and the challenge sent to Spotify
+#[derive(Debug, Clone)]
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate() -> Self {
+        let verifier: String = (0..VERIFIER_LEN)
+            .map(|_| VERIFIER_CHARS[OsRng.gen_range(0..VERIFIER_CHARS.len())] as char)
+            .collect();
+        let challenge = challenge_for(&verifier);
+
+        Self { verifier, challenge }
+    }
+}
+
+// Random value for the OAuth state parameter, checked on the callback
+pub fn generate_state() -> String {
+    (0..32)
+        .map(|_| VERIFIER_CHARS[OsRng.gen_range(0..62)] as char)
+        .collect()
+}
+
+// S256 challenge: unpadded base64url of the verifier's SHA-256
+pub fn challenge_for(verifier: &str) -> String {
+    base64::encode_config(Sha256::digest(verifier.as_bytes()), base64::URL_SAFE_NO_PAD)
+}
+
+// Build the URL that starts the authorization code flow in the browser
+pub fn authorize_url(
+    accounts_url: &str,
+    client_id: &str,
+    redirect_uri: &str,
+    challenge: &str,
+    state: Option<&str>,
+    show_dialog: bool,
+) -> Result<String, String> {
+    let mut url = Url::parse(&format!("{}/authorize", accounts_url.trim_end_matches('/')))
+        .map_err(|e| format!("Invalid accounts URL: {}", e))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        query
+            .append_pair("client_id", client_id)
+            .append_pair("response_type", "code")
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", SCOPES)
+            .append_pair("code_challenge_method", "S256")
+            .append_pair("code_challenge", challenge);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+        // Ask even if the browser is signed in, to link a different account
+        if show_dialog {
+            query.append_pair("show_dialog", "true");
+        }
+    }
+
+    Ok(url.to_string())
+}
+
+// POST to the token endpoint. With a client secret the app authenticates as a
+// confidential client, without one as a public PKCE client.
+async fn request_token(
+    accounts_url: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+    params: &[(&str, &str)],
+) -> Result<TokenResponse, String> {
+    let mut form = vec![("client_id", client_id)];
+    if let Some(client_secret) = client_secret.filter(|secret| !secret.is_empty()) {
+        form.push(("client_secret", client_secret));
+    }
+    form.extend_from_slice(params);
+
+    let response = Client::new()
+        .post(format!("{}/api/token", accounts_url.trim_end_matches('/')))
+        .form(&form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send token request: {}", e))?;
+
+    // Check if the request was successful
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(format!("Token request failed: {}", error_text));
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse token response: {}", e))
+}
+
+// Exchange an authorization code for tokens
+pub async fn exchange_code(
+    accounts_url: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<TokenResponse, String> {
+    request_token(
+        accounts_url,
+        client_id,
+        client_secret,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", code_verifier),
+        ],
+    )
+    .await
+}
+
+// Get a new access token with a refresh token
+pub async fn refresh_token(
+    accounts_url: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+    refresh_token: &str,
+) -> Result<TokenResponse, String> {
+    request_token(
+        accounts_url,
+        client_id,
+        client_secret,
+        &[("grant_type", "refresh_token"), ("refresh_token", refresh_token)],
+    )
+    .await
+}
@@ -0,0 +1,147 @@
+use crate::spotify::api::Album;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Album groups the Web API can return for an artist
+const DEFAULT_ALBUM_TYPES: &[&str] = &["album", "single", "compilation"];
+
+// Filters for an artist discography download
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscographyFilters {
+    #[serde(default = "default_album_types")]
+    pub album_types: Vec<String>,
+    // Inclusive bounds as YYYY, YYYY-MM or YYYY-MM-DD
+    #[serde(default)]
+    pub from_date: Option<String>,
+    #[serde(default)]
+    pub to_date: Option<String>,
+    #[serde(default)]
+    pub exclude_live: bool,
+    #[serde(default)]
+    pub exclude_remasters: bool,
+}
+
+fn default_album_types() -> Vec<String> {
+    DEFAULT_ALBUM_TYPES.iter().map(|t| t.to_string()).collect()
+}
+
+impl Default for DiscographyFilters {
+    fn default() -> Self {
+        Self {
+            album_types: default_album_types(),
+            from_date: None,
+            to_date: None,
+            exclude_live: false,
+            exclude_remasters: false,
+        }
+    }
+}
+
+// Compare dates of different precision on their common prefix, so "2005"
+// includes "2005-06-01"
+pub fn date_in_range(date: &str, from: Option<&str>, to: Option<&str>) -> bool {
+    let truncate = |a: &str, b: &str| {
+        let len = a.len().min(b.len());
+        (a[..len].to_string(), b[..len].to_string())
+    };
+
+    if let Some(from) = from {
+        let (date, from) = truncate(date, from);
+        if date < from {
+            return false;
+        }
+    }
+    if let Some(to) = to {
+        let (date, to) = truncate(date, to);
+        if date > to {
+            return false;
+        }
+    }
+    true
+}
+
+struct EditionMatcher {
+    edition_re: Regex,
+    live_re: Regex,
+    remaster_re: Regex,
+}
+
+impl EditionMatcher {
+    fn new() -> Self {
+        Self {
+            // Parenthesised or dash-separated edition suffixes
+            edition_re: Regex::new(
+                r"(?i)\s*(\([^)]*(edition|deluxe|remaster|expanded|version|anniversary|bonus)[^)]*\)|\[[^\]]*(edition|deluxe|remaster|expanded|version|anniversary|bonus)[^\]]*\]|\s-\s.*(edition|deluxe|remaster|expanded|version|anniversary).*)$",
+            )
+            .unwrap(),
+            live_re: Regex::new(r"(?i)(\(live\b|\[live\b|\blive (at|in|from|on)\b|\s-\slive\b|\(.*\blive\)|\bunplugged\b)").unwrap(),
+            remaster_re: Regex::new(r"(?i)\bremaster(ed)?\b").unwrap(),
+        }
+    }
+
+    // Album title with edition suffixes removed, used to group editions
+    fn base_title(&self, name: &str) -> String {
+        let mut title = name.to_string();
+        // Some titles stack several suffixes
+        loop {
+            let stripped = self.edition_re.replace(&title, "").to_string();
+            if stripped == title {
+                break;
+            }
+            title = stripped;
+        }
+        title.trim().to_lowercase()
+    }
+
+    fn is_live(&self, name: &str) -> bool {
+        self.live_re.is_match(name)
+    }
+
+    fn is_remaster(&self, name: &str) -> bool {
+        self.remaster_re.is_match(name)
+    }
+}
+
+// Apply the filters and collapse editions of the same release into one,
+// keeping the edition with the most tracks (the earliest on a tie).
+// The result is sorted by release date.
+pub fn select_albums(albums: Vec<Album>, filters: &DiscographyFilters) -> Vec<Album> {
+    let matcher = EditionMatcher::new();
+
+    let mut groups: HashMap<(String, String), Vec<Album>> = HashMap::new();
+    for album in albums {
+        let group = album.album_group.clone().unwrap_or_else(|| album.album_type.clone());
+        if !filters.album_types.iter().any(|t| t == &group || t == &album.album_type) {
+            continue;
+        }
+        if !date_in_range(&album.release_date, filters.from_date.as_deref(), filters.to_date.as_deref()) {
+            continue;
+        }
+        if filters.exclude_live && matcher.is_live(&album.name) {
+            continue;
+        }
+
+        let key = (matcher.base_title(&album.name), album.album_type.clone());
+        groups.entry(key).or_default().push(album);
+    }
+
+    let mut selected: Vec<Album> = groups
+        .into_values()
+        .filter_map(|mut editions| {
+            // Prefer originals over remasters when both are present
+            if filters.exclude_remasters && editions.iter().any(|a| !matcher.is_remaster(&a.name)) {
+                editions.retain(|a| !matcher.is_remaster(&a.name));
+            }
+
+            editions.into_iter().max_by(|a, b| {
+                a.total_tracks
+                    .cmp(&b.total_tracks)
+                    .then_with(|| b.release_date.cmp(&a.release_date))
+            })
+        })
+        .collect();
+
+    selected.sort_by(|a, b| a.release_date.cmp(&b.release_date).then_with(|| a.name.cmp(&b.name)));
+    selected
+}
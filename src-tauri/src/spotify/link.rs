@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+// Kind of Spotify resource a link points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotifyLinkKind {
+    Track,
+    Album,
+    Playlist,
+    Artist,
+}
+
+impl SpotifyLinkKind {
+    fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "track" => Some(Self::Track),
+            "album" => Some(Self::Album),
+            "playlist" => Some(Self::Playlist),
+            "artist" => Some(Self::Artist),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Track => "track",
+            Self::Album => "album",
+            Self::Playlist => "playlist",
+            Self::Artist => "artist",
+        }
+    }
+}
+
+// A parsed Spotify link or URI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpotifyLink {
+    pub kind: SpotifyLinkKind,
+    pub id: String,
+}
+
+// Hosts serving spotify.link share links, which redirect to open.spotify.com
+const SHORT_LINK_HOSTS: &[&str] = &["spotify.link", "spotify.app.link"];
+
+impl SpotifyLink {
+    // Parse any of:
+    //   spotify:track:<id>, spotify:user:<name>:playlist:<id>
+    //   https://open.spotify.com/track/<id>?si=...
+    //   https://open.spotify.com/intl-de/album/<id>
+    //   https://open.spotify.com/embed/playlist/<id>
+    //   https://open.spotify.com/user/<name>/playlist/<id>
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+
+        if let Some(rest) = input.strip_prefix("spotify:") {
+            let parts: Vec<&str> = rest.split(':').collect();
+            return match parts.as_slice() {
+                [kind, id] => Self::new(kind, id),
+                ["user", _, kind, id] => Self::new(kind, id),
+                _ => None,
+            };
+        }
+
+        let url = Url::parse(input).ok()?;
+        match url.host_str()? {
+            "open.spotify.com" | "play.spotify.com" => {}
+            _ => return None,
+        }
+
+        let mut segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+        // Strip locale and embed prefixes
+        if segments.first().map_or(false, |s| s.starts_with("intl-")) {
+            segments.remove(0);
+        }
+        if segments.first() == Some(&"embed") {
+            segments.remove(0);
+        }
+
+        match segments.as_slice() {
+            [kind, id, ..] if *kind != "user" => Self::new(kind, id),
+            ["user", _, kind, id, ..] => Self::new(kind, id),
+            _ => None,
+        }
+    }
+
+    fn new(kind: &str, id: &str) -> Option<Self> {
+        let kind = SpotifyLinkKind::parse(kind)?;
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            id: id.to_string(),
+        })
+    }
+
+    // Canonical URL without locale prefix or tracking parameters
+    pub fn url(&self) -> String {
+        format!("https://open.spotify.com/{}/{}", self.kind.as_str(), self.id)
+    }
+
+    // Whether sldl expands this link into several tracks
+    pub fn is_collection(&self) -> bool {
+        self.kind != SpotifyLinkKind::Track
+    }
+}
+
+// Check if the input is a spotify.link share link that has to be resolved first
+pub fn is_short_link(input: &str) -> bool {
+    Url::parse(input.trim())
+        .ok()
+        .and_then(|url| url.host_str().map(|host| SHORT_LINK_HOSTS.contains(&host)))
+        .unwrap_or(false)
+}
+
+// Follow a spotify.link share link to the open.spotify.com URL it points to
+pub async fn resolve_short_link(input: &str) -> Result<SpotifyLink, String> {
+    let response = reqwest::Client::new()
+        .get(input.trim())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", input, e))?;
+
+    SpotifyLink::parse(response.url().as_str())
+        .ok_or_else(|| format!("{} does not point to a Spotify track, album, playlist or artist", input))
+}
@@ -0,0 +1,6 @@
+// Module exports
+pub mod api;
+pub mod auth;
+pub mod discography;
+pub mod link;
+pub mod token;
@@ -0,0 +1,274 @@
+use crate::settings::{self, AppSettings, SettingsState, SpotifyAccount};
+use crate::spotify::api::SpotifyApi;
+use crate::spotify::auth::{self, TokenResponse};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::Mutex;
+
+// Refresh tokens that expire within this many seconds
+const EXPIRY_MARGIN_SECS: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub(crate) struct CachedToken {
+    access_token: String,
+    expires_at: u64,
+}
+
+impl CachedToken {
+    fn is_valid(&self, now: u64) -> bool {
+        !self.access_token.is_empty() && self.expires_at > now + EXPIRY_MARGIN_SECS
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+// Put a token response into an account. Refresh responses may omit the
+// refresh token, in which case the old one is kept.
+fn apply_token(account: &mut SpotifyAccount, token: TokenResponse, now: u64) -> CachedToken {
+    let expires_at = now + token.expires_in;
+
+    account.access_token = Some(token.access_token.clone());
+    if let Some(refresh_token) = token.refresh_token {
+        account.refresh_token = Some(refresh_token);
+    }
+    account.token_expires_at = Some(expires_at);
+
+    CachedToken {
+        access_token: token.access_token,
+        expires_at,
+    }
+}
+
+// The access token stored with an account, if it has one
+fn stored_token(account: &SpotifyAccount) -> Option<CachedToken> {
+    match (&account.access_token, account.token_expires_at) {
+        (Some(access_token), Some(expires_at)) => Some(CachedToken {
+            access_token: access_token.clone(),
+            expires_at,
+        }),
+        // Without an expiry the token's age is unknown: refresh it if the
+        // account can, otherwise try it as it is
+        (Some(access_token), None) => Some(CachedToken {
+            access_token: access_token.clone(),
+            expires_at: if account.refresh_token.is_some() { 0 } else { u64::MAX },
+        }),
+        _ => None,
+    }
+}
+
+// Whether an account's stored access token is about to expire and can be
+// refreshed. Used by the headless CLI, which has no token manager.
+pub(crate) fn needs_refresh(account: &SpotifyAccount) -> bool {
+    account.refresh_token.is_some() && !stored_token(account).is_some_and(|stored| stored.is_valid(now()))
+}
+
+// Refresh an account with its stored refresh token, putting the new tokens
+// into the account. The caller saves the credentials.
+pub(crate) async fn refresh_account(
+    settings: &AppSettings,
+    client_secret: Option<&str>,
+    account: &mut SpotifyAccount,
+) -> Result<CachedToken, String> {
+    let refresh_token = account
+        .refresh_token
+        .clone()
+        .ok_or_else(|| "No refresh token available".to_string())?;
+
+    // With the client secret if one is set
+    let token = auth::refresh_token(
+        &settings.endpoints.spotify_accounts_url,
+        &settings.spotify.client_id,
+        client_secret,
+        &refresh_token,
+    )
+    .await?;
+
+    Ok(apply_token(account, token, now()))
+}
+
+// Owns the Spotify tokens for the backend: exchanges codes, refreshes and
+// caches the current access token of each linked account. Held in Tauri
+// state. The cache lock is kept across a refresh, so concurrent callers wait
+// for the one refresh in flight instead of racing with the same refresh token.
+pub struct SpotifyTokenManager {
+    cache: Mutex<HashMap<String, CachedToken>>,
+}
+
+impl SpotifyTokenManager {
+    pub fn new() -> Self {
+        Self {
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Forget the cached tokens, e.g. after the stored credentials changed
+    pub async fn clear(&self) {
+        self.cache.lock().await.clear();
+    }
+
+    // Exchange an authorization code from the login callback for tokens, and
+    // link (or update) the account they belong to. The account becomes the
+    // active one.
+    pub async fn exchange_code<R: Runtime>(
+        &self,
+        app_handle: &AppHandle<R>,
+        code: &str,
+        code_verifier: &str,
+        redirect_uri: &str,
+    ) -> Result<SpotifyAccount, String> {
+        let mut cache = self.cache.lock().await;
+
+        let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+        let mut credentials = settings::store::get_credentials(app_handle).await?;
+
+        // With the client secret if one is set
+        let token = auth::exchange_code(
+            &settings.endpoints.spotify_accounts_url,
+            &settings.spotify.client_id,
+            credentials.spotify_client_secret.as_deref(),
+            code,
+            redirect_uri,
+            code_verifier,
+        )
+        .await?;
+
+        // The profile identifies the account and gives it a label
+        let user = SpotifyApi::with_base_url(token.access_token.clone(), &settings.endpoints.spotify_api_url)
+            .get_current_user()
+            .await
+            .map_err(|e| format!("Failed to fetch the Spotify profile: {}", e))?;
+
+        let mut account = SpotifyAccount {
+            display_name: user.display_name.unwrap_or_else(|| user.id.clone()),
+            id: user.id,
+            ..Default::default()
+        };
+        let cached = apply_token(&mut account, token, now());
+
+        credentials.upsert_spotify_account(account.clone());
+        credentials.active_spotify_account = Some(account.id.clone());
+        settings::store::save_credentials(app_handle, credentials)
+            .await
+            .map_err(|e| format!("Failed to save credentials: {}", e))?;
+
+        cache.insert(account.id.clone(), cached);
+        Ok(account)
+    }
+
+    // Refresh the access token of an account (the active one if None) even if
+    // the current one is still valid
+    pub async fn refresh<R: Runtime>(&self, app_handle: &AppHandle<R>, account_id: Option<&str>) -> Result<String, String> {
+        let mut cache = self.cache.lock().await;
+
+        let credentials = settings::store::get_credentials(app_handle).await?;
+        let account = credentials
+            .spotify_account(account_id)
+            .ok_or_else(|| unknown_account(account_id))?;
+
+        let cached = Self::refresh_locked(app_handle, &account.id).await?;
+        cache.insert(account.id.clone(), cached.clone());
+        Ok(cached.access_token)
+    }
+
+    // A usable access token of an account (the active one if None) for Web
+    // API calls and sldl, refreshed first if it is about to expire
+    pub async fn get_valid_token<R: Runtime>(&self, app_handle: &AppHandle<R>, account_id: Option<&str>) -> Result<String, String> {
+        let mut cache = self.cache.lock().await;
+        let now = now();
+
+        let credentials = settings::store::get_credentials(app_handle).await?;
+        let account = credentials
+            .spotify_account(account_id)
+            .ok_or_else(|| unknown_account(account_id))?;
+
+        if let Some(cached) = cache.get(&account.id).filter(|cached| cached.is_valid(now)) {
+            return Ok(cached.access_token.clone());
+        }
+
+        // The stored token may still be good, e.g. right after startup
+        let cached = match stored_token(account) {
+            Some(stored) if stored.is_valid(now) => stored,
+            _ if account.refresh_token.is_some() => Self::refresh_locked(app_handle, &account.id).await?,
+            _ => return Err("Not connected to Spotify".to_string()),
+        };
+
+        cache.insert(account.id.clone(), cached.clone());
+        Ok(cached.access_token)
+    }
+
+    // Fetch the profile of accounts linked before accounts had IDs, so they
+    // can be told apart. An account that turns out to be linked already is
+    // dropped in favour of the existing entry.
+    pub async fn identify_accounts<R: Runtime>(&self, app_handle: &AppHandle<R>) -> Result<(), String> {
+        let credentials = settings::store::get_credentials(app_handle).await?;
+        if credentials.spotify_account(Some("")).is_none() {
+            return Ok(());
+        }
+
+        let access_token = self.get_valid_token(app_handle, Some("")).await?;
+        let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+        let user = SpotifyApi::with_base_url(access_token, &settings.endpoints.spotify_api_url)
+            .get_current_user()
+            .await?;
+
+        let _cache = self.cache.lock().await;
+        let mut credentials = settings::store::get_credentials(app_handle).await?;
+        let mut unnamed = match credentials.spotify_account(Some("")) {
+            Some(account) => account.clone(),
+            None => return Ok(()),
+        };
+        let was_active = credentials.active_spotify_account.as_deref() == Some("");
+        credentials.remove_spotify_account("");
+
+        if credentials.spotify_account(Some(&user.id)).is_none() {
+            unnamed.display_name = user.display_name.unwrap_or_else(|| user.id.clone());
+            unnamed.id = user.id.clone();
+            credentials.spotify_accounts.push(unnamed);
+        }
+        if was_active {
+            credentials.active_spotify_account = Some(user.id);
+        }
+
+        settings::store::save_credentials(app_handle, credentials)
+            .await
+            .map_err(String::from)
+    }
+
+    // Refresh an account with its stored refresh token; the caller holds the
+    // cache lock
+    async fn refresh_locked<R: Runtime>(app_handle: &AppHandle<R>, account_id: &str) -> Result<CachedToken, String> {
+        let settings = settings::store::get_settings(app_handle.state::<SettingsState<R>>())?;
+        let mut credentials = settings::store::get_credentials(app_handle).await?;
+        let client_secret = credentials.spotify_client_secret.clone();
+
+        let account = credentials
+            .spotify_account_mut(account_id)
+            .ok_or_else(|| unknown_account(Some(account_id)))?;
+
+        let cached = refresh_account(&settings, client_secret.as_deref(), account).await?;
+        settings::store::save_credentials(app_handle, credentials)
+            .await
+            .map_err(|e| format!("Failed to save credentials: {}", e))?;
+
+        Ok(cached)
+    }
+}
+
+fn unknown_account(account_id: Option<&str>) -> String {
+    match account_id {
+        Some(id) => format!("Unknown Spotify account: {}", id),
+        None => "Not connected to Spotify".to_string(),
+    }
+}
+
+impl Default for SpotifyTokenManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,206 @@
+use crate::commands::sldl::{create_download, run_queued_blocking};
+use crate::downloads::{import, DownloadManagerState};
+use crate::settings::WatchSettings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+// Subfolders processed files are moved into
+const DONE_DIR: &str = "done";
+const FAILED_DIR: &str = "failed";
+
+// Files modified more recently than this may still be being written
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+// A running watcher
+struct Watcher {
+    path: PathBuf,
+    poll_interval_secs: u64,
+    is_running: Arc<AtomicBool>,
+}
+
+// Global watcher state
+static WATCHER: once_cell::sync::Lazy<Mutex<Option<Watcher>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+// Start, stop or restart the watcher to match the settings
+pub fn apply_settings(app_handle: &AppHandle, settings: &WatchSettings) -> Result<(), String> {
+    if !settings.enabled {
+        stop();
+        return Ok(());
+    }
+
+    {
+        let guard = WATCHER.lock().unwrap();
+        if let Some(watcher) = guard.as_ref() {
+            if watcher.path == Path::new(&settings.path)
+                && watcher.poll_interval_secs == settings.poll_interval_secs
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    stop();
+    start(app_handle, settings)
+}
+
+// Start polling the watch folder for import lists
+pub fn start(app_handle: &AppHandle, settings: &WatchSettings) -> Result<(), String> {
+    let path = PathBuf::from(&settings.path);
+    if settings.path.is_empty() || !path.is_dir() {
+        return Err(format!("Watch folder {} does not exist", settings.path));
+    }
+
+    for subdir in [DONE_DIR, FAILED_DIR] {
+        fs::create_dir_all(path.join(subdir))
+            .map_err(|e| format!("Failed to create {} folder: {}", subdir, e))?;
+    }
+
+    let is_running = Arc::new(AtomicBool::new(true));
+    let poll_interval = Duration::from_secs(settings.poll_interval_secs.max(1));
+
+    {
+        let mut guard = WATCHER.lock().unwrap();
+        *guard = Some(Watcher {
+            path: path.clone(),
+            poll_interval_secs: settings.poll_interval_secs,
+            is_running: is_running.clone(),
+        });
+    }
+
+    let app_handle = app_handle.clone();
+    thread::spawn(move || {
+        tracing::info!("Watching {} for import lists", path.display());
+
+        while is_running.load(Ordering::SeqCst) {
+            for file in pending_files(&path) {
+                if !is_running.load(Ordering::SeqCst) {
+                    break;
+                }
+                process_file(&app_handle, &path, &file, &is_running);
+            }
+
+            // Sleep in short steps so stopping doesn't wait a full interval
+            let mut waited = Duration::ZERO;
+            while waited < poll_interval && is_running.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(250));
+                waited += Duration::from_millis(250);
+            }
+        }
+
+        tracing::info!("Stopped watching {}", path.display());
+    });
+
+    Ok(())
+}
+
+// Stop the watcher. A job that is already running finishes on its own.
+pub fn stop() {
+    let mut guard = WATCHER.lock().unwrap();
+    if let Some(watcher) = guard.take() {
+        watcher.is_running.store(false, Ordering::SeqCst);
+    }
+}
+
+// Files in the watch folder that are ready to be processed
+fn pending_files(dir: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::warn!("Failed to read watch folder {}: {}", dir.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            !path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map_or(true, |n| n.starts_with('.'))
+        })
+        .filter(|path| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .map_or(false, |age| age >= SETTLE_TIME)
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+// Queue every entry of an import file, run them one after another and move
+// the file to done/ or failed/
+fn process_file(app_handle: &AppHandle, dir: &Path, file: &Path, is_running: &AtomicBool) {
+    tracing::info!("Importing {}", file.display());
+
+    let entries = match import::parse_import_file(file) {
+        Ok(entries) if !entries.is_empty() => entries,
+        Ok(_) => {
+            tracing::warn!("No entries found in {}", file.display());
+            move_to(dir, file, FAILED_DIR);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("Failed to import {}: {}", file.display(), e);
+            move_to(dir, file, FAILED_DIR);
+            return;
+        }
+    };
+
+    let state = app_handle.state::<DownloadManagerState>();
+
+    // Create all jobs up front so the whole list shows up as queued
+    let mut download_ids = Vec::new();
+    for entry in entries {
+        match create_download(app_handle, &state, entry.query, entry.title, entry.artist, entry.album, None) {
+            Ok(id) => download_ids.push(id),
+            Err(e) => tracing::error!("Failed to queue {}: {}", file.display(), e),
+        }
+    }
+
+    let all_completed = run_queued_blocking(
+        app_handle,
+        &state,
+        &download_ids,
+        || is_running.load(Ordering::SeqCst),
+        |_, _| {},
+    );
+
+    // Leave the file in place if we were stopped part way through
+    if !is_running.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let succeeded = all_completed && !download_ids.is_empty();
+    move_to(dir, file, if succeeded { DONE_DIR } else { FAILED_DIR });
+}
+
+// Move a processed file into a subfolder, adding a timestamp if the name is taken
+fn move_to(dir: &Path, file: &Path, subdir: &str) {
+    let file_name = match file.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return,
+    };
+
+    let mut target = dir.join(subdir).join(&file_name);
+    if target.exists() {
+        let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+        target = dir.join(subdir).join(format!("{}-{}", timestamp, file_name));
+    }
+
+    if let Err(e) = fs::rename(file, &target) {
+        tracing::error!("Failed to move {} to {}: {}", file.display(), target.display(), e);
+    }
+}
@@ -0,0 +1,109 @@
+// Stand-in for the Spotify accounts service's token endpoint, recording the
+// requests it gets and answering with queued responses
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+// A request to the mock, with its form fields
+#[derive(Debug, Clone)]
+pub struct TokenRequest {
+    pub method: String,
+    pub path: String,
+    pub form: HashMap<String, String>,
+}
+
+#[derive(Default)]
+struct Replies {
+    queued: VecDeque<(u16, String)>,
+    delay: Duration,
+}
+
+pub struct MockAccounts {
+    // Base URL to put in `endpoints.spotify_accounts_url`
+    pub url: String,
+    server: Arc<Server>,
+    requests: Arc<Mutex<Vec<TokenRequest>>>,
+    replies: Arc<Mutex<Replies>>,
+}
+
+impl MockAccounts {
+    // Listen on a free local port. Without a queued reply the mock hands out
+    // a fresh token, "access-<n>" for the n-th request.
+    pub fn start() -> Self {
+        let server = Arc::new(Server::http("127.0.0.1:0").expect("failed to start the mock accounts service"));
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let replies = Arc::new(Mutex::new(Replies::default()));
+
+        let (thread_server, thread_requests, thread_replies) = (server.clone(), requests.clone(), replies.clone());
+        std::thread::spawn(move || {
+            for mut request in thread_server.incoming_requests() {
+                let mut body = String::new();
+                let _ = request.as_reader().read_to_string(&mut body);
+                let count = {
+                    let mut requests = thread_requests.lock().unwrap();
+                    requests.push(TokenRequest {
+                        method: request.method().to_string(),
+                        path: request.url().to_string(),
+                        form: url::form_urlencoded::parse(body.as_bytes()).into_owned().collect(),
+                    });
+                    requests.len()
+                };
+
+                let (delay, reply) = {
+                    let mut replies = thread_replies.lock().unwrap();
+                    (replies.delay, replies.queued.pop_front())
+                };
+                let (status, body) = reply.unwrap_or_else(|| (200, token_json(&format!("access-{}", count), None)));
+
+                std::thread::sleep(delay);
+                let response = Response::from_string(body)
+                    .with_status_code(status)
+                    .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+                let _ = request.respond(response);
+            }
+        });
+
+        Self {
+            url,
+            server,
+            requests,
+            replies,
+        }
+    }
+
+    // Answer the next request with this status and body
+    pub fn reply(&self, status: u16, body: &str) {
+        self.replies.lock().unwrap().queued.push_back((status, body.to_string()));
+    }
+
+    // Hold every answer back this long, so callers overlap
+    pub fn delay(&self, delay: Duration) {
+        self.replies.lock().unwrap().delay = delay;
+    }
+
+    pub fn requests(&self) -> Vec<TokenRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockAccounts {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+// Body of a successful token response lasting an hour
+pub fn token_json(access_token: &str, refresh_token: Option<&str>) -> String {
+    serde_json::json!({
+        "access_token": access_token,
+        "token_type": "Bearer",
+        "expires_in": 3600,
+        "refresh_token": refresh_token,
+        "scope": "user-read-private",
+    })
+    .to_string()
+}
@@ -0,0 +1,238 @@
+// Harness running the download flow on Tauri's mock runtime, with the fake
+// sldl from `examples/fake_sldl.rs` replaying a script from `tests/fixtures`
+
+// Each test binary uses only part of the harness
+#![allow(dead_code)]
+
+pub mod accounts;
+
+use soulshark_lib::commands;
+use soulshark_lib::settings::store;
+use soulshark_lib::{AppSettings, Download, DownloadManagerState, DownloadStatus, SettingsState, SoulsharkError};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant};
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::{App, Listener, Manager};
+
+// Events the download flow emits
+const DOWNLOAD_EVENTS: &[&str] = &[
+    "download:started",
+    "download:progress",
+    "download:completed",
+    "download:failed",
+    "download:canceled",
+    "download:paused",
+];
+
+const ALBUM_URL: &str = "https://open.spotify.com/album/4aawyAB9vmqN3uQ7FjRGTy";
+
+// How long a job may take before a test gives up
+pub const TIMEOUT: Duration = Duration::from_secs(20);
+
+static DATA_HOME: Once = Once::new();
+
+pub struct TestApp {
+    pub app: App<MockRuntime>,
+    pub app_data_dir: PathBuf,
+    pub downloads_dir: PathBuf,
+    events: Arc<Mutex<Vec<(String, Download)>>>,
+}
+
+impl TestApp {
+    // An app with its own data dir and downloads folder, running sldl as the
+    // fake
+    pub fn new() -> Self {
+        Self::build(None)
+    }
+
+    // An app whose settings store starts out as a copy of a fixture, e.g. a
+    // file written by an older version
+    pub fn with_settings_file(fixture: &str) -> Self {
+        Self::build(Some(fixture_path(fixture)))
+    }
+
+    fn build(settings_file: Option<PathBuf>) -> Self {
+        // Keep the app data of every test out of the user's data dir
+        DATA_HOME.call_once(|| {
+            let data_home = std::env::temp_dir().join(format!("soulshark-tests-{}", std::process::id()));
+            std::env::set_var("XDG_DATA_HOME", data_home);
+        });
+
+        let mut context = mock_context(noop_assets());
+        context.config_mut().identifier = format!("com.soulshark.test.{}", uuid::Uuid::new_v4());
+
+        let app = mock_builder()
+            .plugin(tauri_plugin_shell::init())
+            .plugin(tauri_plugin_store::Builder::default().build())
+            .build(context)
+            .expect("failed to build the mock app");
+
+        let app_data_dir = app.path().app_data_dir().expect("no app data dir");
+        if let Some(settings_file) = settings_file {
+            std::fs::create_dir_all(&app_data_dir).unwrap();
+            std::fs::copy(settings_file, app_data_dir.join(store::SETTINGS_FILE)).unwrap();
+        }
+        soulshark_lib::init_state(app.handle()).expect("failed to initialize the app state");
+
+        let downloads_dir = app_data_dir.join("downloads");
+        std::fs::create_dir_all(&downloads_dir).unwrap();
+
+        let mut settings = store::get_settings(app.state::<SettingsState<MockRuntime>>()).unwrap();
+        settings.sldl.path = fake_sldl_path().to_string_lossy().to_string();
+        settings.soulseek.downloads_path = downloads_dir.to_string_lossy().to_string();
+        settings.soulseek.min_free_space_mb = 0;
+        store::save_settings(app.state::<SettingsState<MockRuntime>>(), settings).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        for name in DOWNLOAD_EVENTS {
+            let events = events.clone();
+            app.listen_any(*name, move |event| {
+                let download: Download = serde_json::from_str(event.payload()).unwrap();
+                events.lock().unwrap().push((name.to_string(), download));
+            });
+        }
+
+        Self {
+            app,
+            app_data_dir,
+            downloads_dir,
+            events,
+        }
+    }
+
+    pub fn update_settings(&self, update: impl FnOnce(&mut AppSettings)) {
+        let state = self.app.state::<SettingsState<MockRuntime>>();
+        let mut settings = store::get_settings(state.clone()).unwrap();
+        update(&mut settings);
+        store::save_settings(state, settings).unwrap();
+    }
+
+    // Start a download the way the `execute_sldl` command does, replaying the
+    // given fixture
+    pub fn start(&self, query: &str, fixture: &str) -> String {
+        self.try_start(query, fixture).expect("failed to start the download")
+    }
+
+    pub fn try_start(&self, query: &str, fixture: &str) -> Result<String, SoulsharkError> {
+        let options = fake_options(fixture);
+        let state = self.app.state::<DownloadManagerState>();
+        tauri::async_runtime::block_on(commands::sldl::start_download(
+            self.app.handle(),
+            &state,
+            query.to_string(),
+            options,
+            None,
+            None,
+            None,
+            None,
+        ))
+    }
+
+    // Start an album download the way the `download_album` command does once
+    // it has looked the album up on Spotify
+    pub fn start_album(&self, fixture: &str, expected_tracks: usize, allow_fallback: bool) -> String {
+        let download = commands::album::build_album_download(
+            ALBUM_URL.to_string(),
+            "Album One".to_string(),
+            Some("Artist A".to_string()),
+            Some(expected_tracks),
+            allow_fallback,
+        );
+        let state = self.app.state::<DownloadManagerState>();
+        let id = commands::sldl::add_download(self.app.handle(), &state, download).unwrap();
+        tauri::async_runtime::block_on(commands::sldl::run_download(
+            self.app.handle(),
+            &state,
+            &id,
+            fake_options(fixture),
+        ))
+        .expect("failed to start the album download");
+        id
+    }
+
+    pub fn cancel(&self, id: &str) {
+        let state = self.app.state::<DownloadManagerState>();
+        commands::downloads::cancel(self.app.handle(), &state, id).unwrap();
+    }
+
+    pub fn download(&self, id: &str) -> Download {
+        let state = self.app.state::<DownloadManagerState>();
+        let download_manager = state.0.lock().unwrap();
+        download_manager.get_download(id).cloned().expect("unknown download")
+    }
+
+    // Wait until the download's status matches
+    pub fn wait_for(&self, id: &str, matches: impl Fn(&DownloadStatus) -> bool) -> Download {
+        wait_until(|| {
+            let download = self.download(id);
+            matches(&download.status).then_some(download)
+        })
+    }
+
+    // Wait until sldl has exited and the job was recorded in the history
+    pub fn wait_for_history(&self, id: &str) {
+        let history_path = self.app_data_dir.join("download_history.json");
+        wait_until(|| {
+            let history = std::fs::read_to_string(&history_path).ok()?;
+            history.contains(id).then_some(())
+        });
+    }
+
+    // Names of the events emitted for a download, in order
+    pub fn events(&self, id: &str) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, download)| download.id == id)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.app_data_dir);
+    }
+}
+
+// Poll until `check` returns something, failing the test after `TIMEOUT`
+pub fn wait_until<T>(check: impl Fn() -> Option<T>) -> T {
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        if let Some(value) = check() {
+            return value;
+        }
+        assert!(Instant::now() < deadline, "timed out waiting for the download");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+// Options making the fake replay a fixture
+fn fake_options(fixture: &str) -> HashMap<String, String> {
+    HashMap::from([(
+        "fake-script".to_string(),
+        fixture_path(fixture).to_string_lossy().to_string(),
+    )])
+}
+
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+// The fake is built as an example, next to the test binaries
+fn fake_sldl_path() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let target_dir = exe.parent().and_then(Path::parent).unwrap();
+    let path = target_dir
+        .join("examples")
+        .join(format!("fake_sldl{}", std::env::consts::EXE_SUFFIX));
+    assert!(
+        path.exists(),
+        "{} is missing, run the tests with `cargo test` so the fake_sldl example is built",
+        path.display()
+    );
+    path
+}
@@ -0,0 +1,129 @@
+// Tests of the filters and edition grouping of artist discography downloads
+
+use soulshark_lib::spotify::api::Album;
+use soulshark_lib::spotify::discography::{date_in_range, select_albums, DiscographyFilters};
+
+fn album(name: &str, album_type: &str, release_date: &str, total_tracks: usize) -> Album {
+    Album {
+        id: format!("{}-{}", name, release_date),
+        name: name.to_string(),
+        album_type: album_type.to_string(),
+        album_group: None,
+        release_date: release_date.to_string(),
+        total_tracks,
+        artists: Vec::new(),
+    }
+}
+
+fn names(albums: &[Album]) -> Vec<&str> {
+    albums.iter().map(|album| album.name.as_str()).collect()
+}
+
+#[test]
+fn dates_compare_on_their_common_precision() {
+    assert!(date_in_range("2005-06-01", Some("2005"), Some("2005")));
+    assert!(date_in_range("2005", Some("2005-06-01"), None));
+    assert!(date_in_range("2005-06", Some("2005-06-15"), Some("2005-06-15")));
+    assert!(!date_in_range("2004-12-31", Some("2005"), None));
+    assert!(!date_in_range("2006-01-01", None, Some("2005-12")));
+    assert!(date_in_range("1999-01-01", None, None));
+}
+
+#[test]
+fn editions_are_grouped_keeping_the_fullest() {
+    let albums = vec![
+        album("Album One", "album", "2001-03-01", 10),
+        album("Album One (Deluxe Edition)", "album", "2011-03-01", 14),
+        album("Album One [Expanded Edition] (Remastered 2021 Version)", "album", "2021-03-01", 14),
+        album("Album Two - 10th Anniversary Edition", "album", "2015-05-01", 12),
+        album("Album Two", "album", "2005-05-01", 12),
+        // A single with the same title is a release of its own
+        album("Album One", "single", "2000-12-01", 1),
+    ];
+
+    let selected = select_albums(albums, &DiscographyFilters::default());
+    // Sorted by release date
+    assert_eq!(
+        names(&selected),
+        vec!["Album One", "Album Two", "Album One (Deluxe Edition)"]
+    );
+    assert_eq!(selected[0].album_type, "single");
+    // Among editions with as many tracks the earliest wins
+    assert_eq!(selected[1].release_date, "2005-05-01");
+    assert_eq!(selected[2].release_date, "2011-03-01");
+}
+
+#[test]
+fn live_albums_are_left_out_when_asked() {
+    let albums = vec![
+        album("Album One", "album", "2001-03-01", 10),
+        album("Live at the Arena", "album", "2003-01-01", 18),
+        album("Album One (Live)", "album", "2004-01-01", 10),
+        album("MTV Unplugged", "album", "2005-01-01", 12),
+        album("Album Two - Live", "album", "2006-01-01", 11),
+        // "Live" inside a title doesn't make it a live album
+        album("Live Forever", "album", "2007-01-01", 9),
+    ];
+
+    let all = select_albums(albums.clone(), &DiscographyFilters::default());
+    assert_eq!(all.len(), 6);
+
+    let filters = DiscographyFilters {
+        exclude_live: true,
+        ..DiscographyFilters::default()
+    };
+    assert_eq!(names(&select_albums(albums, &filters)), vec!["Album One", "Live Forever"]);
+}
+
+#[test]
+fn remasters_give_way_to_originals() {
+    let albums = vec![
+        album("Album One", "album", "2001-03-01", 10),
+        album("Album One (2021 Remaster)", "album", "2021-03-01", 12),
+        // Only remastered, so it stays
+        album("Album Two (Remastered)", "album", "2019-05-01", 11),
+    ];
+
+    let filters = DiscographyFilters {
+        exclude_remasters: true,
+        ..DiscographyFilters::default()
+    };
+    assert_eq!(
+        names(&select_albums(albums.clone(), &filters)),
+        vec!["Album One", "Album Two (Remastered)"]
+    );
+
+    // Without the filter the fuller remaster wins
+    assert_eq!(
+        names(&select_albums(albums, &DiscographyFilters::default())),
+        vec!["Album Two (Remastered)", "Album One (2021 Remaster)"]
+    );
+}
+
+#[test]
+fn types_and_dates_are_filtered() {
+    let mut appears_on = album("Various Hits", "compilation", "2010-01-01", 20);
+    appears_on.album_group = Some("appears_on".to_string());
+    let albums = vec![
+        album("Album One", "album", "2001-03-01", 10),
+        album("Single One", "single", "2002-01-01", 2),
+        album("Best Of", "compilation", "2012-01-01", 18),
+        appears_on,
+    ];
+
+    let filters = DiscographyFilters {
+        album_types: vec!["album".to_string(), "single".to_string()],
+        from_date: Some("2002".to_string()),
+        to_date: None,
+        ..DiscographyFilters::default()
+    };
+    assert_eq!(names(&select_albums(albums.clone(), &filters)), vec!["Single One"]);
+
+    // The album type also counts, so an appears_on compilation passes
+    let filters = DiscographyFilters {
+        album_types: vec!["compilation".to_string()],
+        to_date: Some("2011".to_string()),
+        ..DiscographyFilters::default()
+    };
+    assert_eq!(names(&select_albums(albums, &filters)), vec!["Various Hits"]);
+}
@@ -0,0 +1,303 @@
+// End-to-end tests of the download flow: a download is started as the
+// `execute_sldl` command does, the fake sldl replays a recorded session and
+// the tests check the download's status, its events and the cleanup
+
+mod common;
+
+use common::TestApp;
+use soulshark_lib::commands::peers::{list_peers, set_peer_list, PeerEntry};
+use soulshark_lib::settings::keystore::KeyStorageKind;
+use soulshark_lib::settings::{store, CleanupMode};
+use soulshark_lib::{AlbumStatus, DownloadStatus, PeerList};
+use tauri::async_runtime::block_on;
+
+const PLAYLIST_URL: &str = "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M";
+
+#[test]
+fn playlist_reports_progress_and_completes() {
+    let app = TestApp::new();
+    let id = app.start(PLAYLIST_URL, "playlist.txt");
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert_eq!(download.status, DownloadStatus::Completed);
+    assert!(download.is_playlist);
+    assert_eq!(download.title, "Test Playlist by Tester");
+    assert_eq!(download.total_tracks, Some(2));
+    assert_eq!(download.completed_tracks, Some(2));
+    assert_eq!(download.failed_tracks, Some(0));
+    assert_eq!(download.progress, Some(1.0));
+
+    let events = app.events(&id);
+    assert_eq!(events.first().map(String::as_str), Some("download:started"));
+    assert!(events.iter().filter(|name| *name == "download:progress").count() >= 2);
+    assert!(events.contains(&"download:completed".to_string()));
+    assert!(!events.contains(&"download:failed".to_string()));
+}
+
+#[test]
+fn single_track_completes_with_its_file() {
+    let app = TestApp::new();
+    let id = app.start("Artist A - Song One", "single_track.txt");
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert_eq!(download.status, DownloadStatus::Completed);
+    assert!(!download.is_playlist);
+    assert_eq!(download.title, "Artist A - Song One");
+    assert_eq!(download.file_path.as_deref(), Some("Artist A - Song One.mp3"));
+    assert!(app.events(&id).contains(&"download:completed".to_string()));
+}
+
+#[test]
+fn failed_run_is_reported_as_a_sidecar_error() {
+    let app = TestApp::new();
+    let id = app.start("Artist A - Song One", "failure.txt");
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    match &download.status {
+        DownloadStatus::Failed(error) => assert_eq!(error.code(), "sidecar"),
+        status => panic!("expected a failure, got {:?}", status),
+    }
+    assert!(download
+        .console_logs
+        .iter()
+        .any(|line| line == "ERROR: Login failed: INVALIDPASS"));
+    assert!(app.events(&id).contains(&"download:failed".to_string()));
+}
+
+#[test]
+fn missing_binary_fails_the_download() {
+    let app = TestApp::new();
+    app.update_settings(|settings| {
+        settings.sldl.path = app.app_data_dir.join("no-such-sldl").to_string_lossy().to_string();
+    });
+
+    let error = app
+        .try_start("Artist A - Song One", "single_track.txt")
+        .expect_err("starting a missing binary should fail");
+    assert_eq!(error.code(), "sidecar");
+}
+
+#[test]
+fn cancel_stops_sldl_and_keeps_the_status() {
+    let app = TestApp::new();
+    let id = app.start("Artist A - Song One", "hang.txt");
+
+    app.wait_for(&id, |status| *status == DownloadStatus::Searching);
+    app.cancel(&id);
+
+    // The killed process must not turn the job into a failure, and the
+    // success line after the pause in the script must never arrive
+    app.wait_for_history(&id);
+    let download = app.download(&id);
+    assert_eq!(download.status, DownloadStatus::Canceled);
+    assert!(!download.console_logs.iter().any(|line| line.starts_with("Succeeded")));
+
+    let events = app.events(&id);
+    assert!(events.contains(&"download:canceled".to_string()));
+    assert!(!events.contains(&"download:completed".to_string()));
+    assert!(!events.contains(&"download:failed".to_string()));
+}
+
+#[test]
+fn cleanup_trashes_index_files_and_removes_empty_folders() {
+    let app = TestApp::new();
+    let id = app.start(PLAYLIST_URL, "playlist.txt");
+    app.wait_for(&id, DownloadStatus::is_finished);
+
+    // Cleanup runs once sldl has exited, after the status is settled
+    let playlist_dir = app.downloads_dir.join("Test Playlist");
+    common::wait_until(|| (!playlist_dir.join("_index.sldl").exists()).then_some(()));
+    assert!(!playlist_dir.join("Artwork").exists());
+    assert!(playlist_dir.join("Artist A - Song One.mp3").exists());
+    assert!(playlist_dir.join("Artist B - Song Two.mp3").exists());
+
+    let trashed = walk(&app.app_data_dir.join("trash"));
+    assert!(trashed.iter().any(|path| path.ends_with("Test Playlist/_index.sldl")));
+    assert!(app.app_data_dir.join("cleanup_audit.log").exists());
+}
+
+#[test]
+fn dry_run_cleanup_leaves_the_files() {
+    let app = TestApp::new();
+    app.update_settings(|settings| settings.output.cleanup = CleanupMode::DryRun);
+    let id = app.start(PLAYLIST_URL, "playlist.txt");
+    app.wait_for(&id, DownloadStatus::is_finished);
+
+    app.wait_for_history(&id);
+    let playlist_dir = app.downloads_dir.join("Test Playlist");
+    assert!(playlist_dir.join("_index.sldl").exists());
+    assert!(playlist_dir.join("Artwork").exists());
+}
+
+#[test]
+fn sldl_gets_a_config_file_from_the_settings() {
+    let app = TestApp::new();
+    app.update_settings(|settings| {
+        let config = &mut settings.sldl.config;
+        config.required.formats = vec!["flac".to_string()];
+        config.required.min_bitrate = Some(320);
+        config.search.fast_search = true;
+        config.extra_lines = vec!["max-retries = 5".to_string()];
+    });
+    let id = app.start("https://artist.bandcamp.com/album/test", "config.txt");
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    for line in [
+        "format = flac",
+        "min-bitrate = 320",
+        "pref-format = flac",
+        "fast-search = true",
+        "input-type = bandcamp",
+        "max-retries = 5",
+    ] {
+        assert!(download.console_logs.iter().any(|log| log.trim_end() == line), "missing '{}'", line);
+    }
+
+    // The file only lives as long as the run
+    app.wait_for_history(&id);
+    assert!(walk(&app.app_data_dir.join("sldl")).is_empty());
+}
+
+#[test]
+fn credentials_go_in_the_config_file_not_the_arguments() {
+    let app = TestApp::new();
+    app.update_settings(|settings| {
+        settings.security.key_storage = KeyStorageKind::File;
+        settings.soulseek.username = "listener".to_string();
+        settings.spotify.client_id = "client".to_string();
+    });
+    let mut credentials = block_on(store::get_credentials(app.app.handle())).unwrap();
+    credentials.soulseek_password = Some("hunter2".to_string());
+    credentials.spotify_client_secret = Some("client-secret".to_string());
+    block_on(store::save_credentials(app.app.handle(), credentials)).unwrap();
+
+    let id = app.start(PLAYLIST_URL, "credentials.txt");
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    let logs: Vec<&str> = download.console_logs.iter().map(|log| log.trim_end()).collect();
+
+    assert!(logs.contains(&"pass = hunter2"));
+    assert!(logs.contains(&"spotify-secret = client-secret"));
+    assert!(logs.contains(&"arg listener"));
+    assert!(logs.contains(&"arg client"));
+    let args: Vec<&&str> = logs.iter().filter(|log| log.starts_with("arg ")).collect();
+    assert!(!args.is_empty());
+    for arg in args {
+        assert!(!arg.contains("hunter2") && !arg.contains("client-secret"), "secret on the command line: {}", arg);
+    }
+}
+
+#[test]
+fn album_comes_from_one_folder() {
+    let app = TestApp::new();
+    let id = app.start_album("album.txt", 3, false);
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert_eq!(download.status, DownloadStatus::Completed);
+    assert_eq!(download.completed_tracks, Some(3));
+
+    let album = download.album_mode.expect("no album state");
+    assert_eq!(album.status, AlbumStatus::Complete);
+    assert_eq!(album.tracks.len(), 3);
+    assert!(album.tracks.iter().all(|track| track.format == "flac"));
+    assert!(album.problems.is_empty());
+}
+
+#[test]
+fn album_with_mixed_formats_is_flagged() {
+    let app = TestApp::new();
+    let id = app.start_album("album_mixed.txt", 3, false);
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert_eq!(download.status, DownloadStatus::Completed);
+    let album = download.album_mode.expect("no album state");
+    assert_eq!(album.status, AlbumStatus::Mismatched);
+    assert_eq!(album.problems, vec!["Mixed formats: flac, mp3".to_string()]);
+
+    // The folder's peer is held responsible
+    app.wait_for_history(&id);
+    let peer = peer(&app, "folderuser");
+    assert_eq!(peer.stats.successes, 3);
+    assert_eq!(peer.stats.verification_failures, 1);
+}
+
+#[test]
+fn peers_are_tracked_and_blacklisted_peers_banned() {
+    let app = TestApp::new();
+    block_on(set_peer_list(app.app.handle().clone(), "spammer".to_string(), Some(PeerList::Blacklist))).unwrap();
+    let id = app.start(PLAYLIST_URL, "peers.txt");
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert!(download.console_logs.iter().any(|log| log.trim_end() == "banned-users = spammer"));
+    app.wait_for_history(&id);
+
+    let good = peer(&app, "gooduser");
+    assert_eq!((good.stats.successes, good.stats.failures), (1, 0));
+    assert!(good.stats.average_speed_kbps.is_some());
+    let bad = peer(&app, "baduser");
+    assert_eq!((bad.stats.successes, bad.stats.failures), (0, 1));
+    assert!(!bad.banned);
+    assert!(peer(&app, "spammer").banned);
+
+    // Enough failures get a peer banned automatically, unless whitelisted
+    app.update_settings(|settings| {
+        settings.peers.auto_blacklist = true;
+        settings.peers.min_transfers = 1;
+    });
+    assert!(peer(&app, "baduser").banned);
+    assert!(!peer(&app, "gooduser").banned);
+    block_on(set_peer_list(app.app.handle().clone(), "baduser".to_string(), Some(PeerList::Whitelist))).unwrap();
+    assert!(!peer(&app, "baduser").banned);
+}
+
+#[test]
+fn missing_album_falls_back_to_tracks_when_allowed() {
+    let app = TestApp::new();
+    let id = app.start_album("album_fallback.txt", 3, true);
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    assert_eq!(download.status, DownloadStatus::Completed);
+    assert_eq!(download.completed_tracks, Some(3));
+    assert_eq!(download.failed_tracks, Some(0));
+    assert_eq!(download.album_mode.expect("no album state").status, AlbumStatus::FellBack);
+    assert!(download
+        .console_logs
+        .iter()
+        .any(|line| line.trim_end() == "Completed: 3 succeeded, 0 failed"));
+}
+
+#[test]
+fn missing_album_fails_without_fallback() {
+    let app = TestApp::new();
+    let id = app.start_album("album_fallback.txt", 3, false);
+
+    let download = app.wait_for(&id, DownloadStatus::is_finished);
+    match &download.status {
+        DownloadStatus::Failed(error) => assert_eq!(error.code(), "not_found"),
+        status => panic!("expected a failure, got {:?}", status),
+    }
+    assert_eq!(download.album_mode.expect("no album state").status, AlbumStatus::NotFound);
+    assert!(!download.console_logs.iter().any(|line| line.starts_with("Downloading")));
+}
+
+// Every file below a folder
+fn walk(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(walk(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+// A peer's recorded stats
+fn peer(app: &TestApp, username: &str) -> PeerEntry {
+    block_on(list_peers(app.app.handle().clone()))
+        .unwrap()
+        .into_iter()
+        .find(|peer| peer.stats.username == username)
+        .unwrap_or_else(|| panic!("no stats for {}", username))
+}
@@ -0,0 +1,188 @@
+// Tests of the credentials key storage, with an in-memory stand-in for the OS
+// keyring installed as keyring's default credential store
+
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+use soulshark_lib::settings::keystore::{self, FileKeyStore, KeyStorageKind, KeyStore, KeyringKeyStore};
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, Once};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyringMode {
+    Available,
+    // No keyring session, e.g. cron without a desktop
+    Unavailable,
+    // Entries can be read but not written
+    ReadOnly,
+}
+
+struct MemoryKeyring {
+    entries: HashMap<(String, String), Vec<u8>>,
+    mode: KeyringMode,
+}
+
+static KEYRING: once_cell::sync::Lazy<Mutex<MemoryKeyring>> = once_cell::sync::Lazy::new(|| {
+    Mutex::new(MemoryKeyring {
+        entries: HashMap::new(),
+        mode: KeyringMode::Available,
+    })
+});
+
+static INSTALL: Once = Once::new();
+
+// The tests share the app's single keyring entry, so they take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+struct MemoryBuilder;
+
+impl CredentialBuilderApi for MemoryBuilder {
+    fn build(&self, _target: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            id: (service.to_string(), user.to_string()),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct MemoryCredential {
+    id: (String, String),
+}
+
+fn unavailable() -> keyring::Error {
+    keyring::Error::NoStorageAccess(Box::new(std::io::Error::other("no keyring session")))
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        let mut keyring = KEYRING.lock().unwrap();
+        if keyring.mode != KeyringMode::Available {
+            return Err(unavailable());
+        }
+        keyring.entries.insert(self.id.clone(), secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        let keyring = KEYRING.lock().unwrap();
+        if keyring.mode == KeyringMode::Unavailable {
+            return Err(unavailable());
+        }
+        keyring.entries.get(&self.id).cloned().ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        let mut keyring = KEYRING.lock().unwrap();
+        if keyring.mode != KeyringMode::Available {
+            return Err(unavailable());
+        }
+        keyring.entries.remove(&self.id).map(|_| ()).ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Install the in-memory keyring, emptied and in the given mode. Hold the guard
+// for the length of the test.
+fn keyring(mode: KeyringMode) -> MutexGuard<'static, ()> {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    INSTALL.call_once(|| keyring::set_default_credential_builder(Box::new(MemoryBuilder)));
+    set_mode(mode);
+    KEYRING.lock().unwrap().entries.clear();
+    guard
+}
+
+fn set_mode(mode: KeyringMode) {
+    KEYRING.lock().unwrap().mode = mode;
+}
+
+fn keyring_key() -> Option<keystore::Key> {
+    KeyringKeyStore::new().unwrap().load_key().unwrap()
+}
+
+fn data_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soulshark-keystore-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn new_key_goes_to_the_keyring() {
+    let _keyring = keyring(KeyringMode::Available);
+    let dir = data_dir();
+
+    let key = keystore::get_key(&dir, KeyStorageKind::Keyring, true).unwrap().expect("no key");
+    assert_eq!(keyring_key(), Some(key.clone()));
+    assert!(FileKeyStore::new(&dir).load_key().unwrap().is_none());
+    assert_eq!(keystore::get_key(&dir, KeyStorageKind::Keyring, false).unwrap(), Some(key));
+}
+
+#[test]
+fn key_file_moves_into_the_keyring() {
+    let _keyring = keyring(KeyringMode::Available);
+    let dir = data_dir();
+    let file_store = FileKeyStore::new(&dir);
+    let key = keystore::generate_key();
+    file_store.store_key(&key).unwrap();
+
+    assert_eq!(keystore::get_key(&dir, KeyStorageKind::Keyring, false).unwrap(), Some(key.clone()));
+    assert_eq!(keyring_key(), Some(key));
+    assert!(file_store.load_key().unwrap().is_none());
+}
+
+#[test]
+fn key_file_is_kept_when_the_keyring_cant_store_it() {
+    let _keyring = keyring(KeyringMode::ReadOnly);
+    let dir = data_dir();
+    let file_store = FileKeyStore::new(&dir);
+    let key = keystore::generate_key();
+    file_store.store_key(&key).unwrap();
+
+    assert_eq!(keystore::get_key(&dir, KeyStorageKind::Keyring, false).unwrap(), Some(key.clone()));
+    assert_eq!(file_store.load_key().unwrap(), Some(key));
+    assert_eq!(keyring_key(), None);
+
+    // A new key goes to the key file too
+    let dir = data_dir();
+    let key = keystore::get_key(&dir, KeyStorageKind::Keyring, true).unwrap().expect("no key");
+    assert_eq!(FileKeyStore::new(&dir).load_key().unwrap(), Some(key));
+}
+
+#[test]
+fn key_file_is_used_without_a_keyring() {
+    let _keyring = keyring(KeyringMode::Available);
+    let dir = data_dir();
+    let file_store = FileKeyStore::new(&dir);
+    let key = keystore::generate_key();
+    file_store.store_key(&key).unwrap();
+
+    set_mode(KeyringMode::Unavailable);
+    assert_eq!(keystore::get_key(&dir, KeyStorageKind::Keyring, false).unwrap(), Some(key.clone()));
+    assert_eq!(file_store.load_key().unwrap(), Some(key));
+
+    let dir = data_dir();
+    assert_eq!(keystore::get_key(&dir, KeyStorageKind::Keyring, false).unwrap(), None);
+    let key = keystore::get_key(&dir, KeyStorageKind::Keyring, true).unwrap().expect("no key");
+    assert_eq!(FileKeyStore::new(&dir).load_key().unwrap(), Some(key));
+}
+
+#[test]
+fn key_moves_between_keyring_and_file() {
+    let _keyring = keyring(KeyringMode::Available);
+    let dir = data_dir();
+    let file_store = FileKeyStore::new(&dir);
+    let key = keystore::get_key(&dir, KeyStorageKind::Keyring, true).unwrap().expect("no key");
+
+    keystore::migrate_key(&dir, KeyStorageKind::Keyring, KeyStorageKind::File).unwrap();
+    assert_eq!(file_store.load_key().unwrap(), Some(key.clone()));
+    assert_eq!(keyring_key(), None);
+
+    keystore::migrate_key(&dir, KeyStorageKind::File, KeyStorageKind::Keyring).unwrap();
+    assert_eq!(keyring_key(), Some(key));
+    assert!(file_store.load_key().unwrap().is_none());
+}
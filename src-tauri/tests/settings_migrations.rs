@@ -0,0 +1,93 @@
+// Tests of the settings migrations, run on store files written by older
+// versions from `tests/fixtures/settings`
+
+mod common;
+
+use common::{fixture_path, TestApp};
+use serde_json::Value;
+use soulshark_lib::settings::migrations::{self, SETTINGS_VERSION};
+use soulshark_lib::settings::store::{self, SETTINGS_KEY};
+use soulshark_lib::{AppSettings, SettingsState};
+use tauri::test::MockRuntime;
+use tauri::Manager;
+
+// The settings object of a fixture store file
+fn stored_settings(fixture: &str) -> Value {
+    let data = std::fs::read_to_string(fixture_path(fixture)).unwrap();
+    let mut store: Value = serde_json::from_str(&data).unwrap();
+    store[SETTINGS_KEY].take()
+}
+
+fn migrate(fixture: &str) -> (Value, AppSettings) {
+    let migrated = migrations::migrate(stored_settings(fixture)).unwrap();
+    let settings = serde_json::from_value(migrated.clone()).expect("migrated settings don't deserialize");
+    (migrated, settings)
+}
+
+#[test]
+fn v0_gets_every_section() {
+    let (migrated, settings) = migrate("settings/v0.json");
+
+    assert_eq!(migrations::version_of(&migrated), SETTINGS_VERSION);
+    for section in ["soulseek", "spotify", "output", "api", "watch", "security", "endpoints"] {
+        assert!(migrated[section].is_object(), "{} is not an object", section);
+    }
+    assert_eq!(settings.soulseek.username, "olduser");
+    assert_eq!(settings.soulseek.preferred_format, "mp3");
+    assert_eq!(settings.output.m3u_path, "lists/");
+}
+
+#[test]
+fn v1_gets_the_public_endpoints() {
+    let (_, settings) = migrate("settings/v1.json");
+
+    assert_eq!(settings.endpoints.soulseek_server, "server.slsknet.org:2242");
+    assert_eq!(settings.endpoints.spotify_accounts_url, "https://accounts.spotify.com");
+    assert_eq!(settings.endpoints.spotify_api_url, "https://api.spotify.com/v1");
+    assert_eq!(settings.soulseek.username, "v1user");
+    assert!(!settings.soulseek.remove_special_chars);
+}
+
+#[test]
+fn v2_keeps_its_endpoints_and_redirect_uri() {
+    let (_, settings) = migrate("settings/v2.json");
+
+    assert_eq!(settings.endpoints.soulseek_server, "127.0.0.1:2242");
+    assert_eq!(settings.endpoints.spotify_api_url, "http://127.0.0.1:8080/v1");
+    assert_eq!(settings.spotify.redirect_uri, "http://localhost:9871/callback");
+}
+
+#[test]
+fn newer_settings_are_refused() {
+    let mut settings = stored_settings("settings/v2.json");
+    settings["version"] = Value::from(SETTINGS_VERSION + 1);
+    assert!(migrations::migrate(settings).is_err());
+}
+
+#[test]
+fn store_is_backed_up_before_migrating() {
+    let app = TestApp::with_settings_file("settings/v1.json");
+
+    let backup = app.app_data_dir.join("settings.v1.backup.json");
+    assert_eq!(
+        std::fs::read_to_string(backup).expect("no backup"),
+        std::fs::read_to_string(fixture_path("settings/v1.json")).unwrap()
+    );
+
+    let settings = store::get_settings(app.app.state::<SettingsState<MockRuntime>>()).unwrap();
+    assert_eq!(settings.version, SETTINGS_VERSION);
+    assert_eq!(settings.soulseek.username, "v1user");
+}
+
+#[test]
+fn current_store_is_not_backed_up() {
+    // The newest fixture is at the current version, so nothing migrates
+    let app = TestApp::with_settings_file("settings/v2.json");
+
+    let backups: Vec<_> = std::fs::read_dir(&app.app_data_dir)
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".backup.json"))
+        .collect();
+    assert!(backups.is_empty(), "{:?}", backups);
+}
@@ -0,0 +1,51 @@
+// Tests of the checks on what the settings hand to sldl: the name format and
+// the passthrough lines
+
+use soulshark_lib::settings::validation::{check_name_format, validate};
+use soulshark_lib::AppSettings;
+
+// The messages of the errors on the extra lines, for settings with these lines
+fn extra_line_errors(lines: &[&str]) -> Vec<String> {
+    let mut settings = AppSettings::default();
+    settings.sldl.config.extra_lines = lines.iter().map(|line| line.to_string()).collect();
+    validate(&settings)
+        .into_iter()
+        .filter(|error| error.field == "sldl.config.extra_lines")
+        .map(|error| error.message)
+        .collect()
+}
+
+#[test]
+fn plain_options_pass() {
+    assert!(extra_line_errors(&["# retries", "max-retries = 5", "", "[lossless]", "no-skip-existing = true"]).is_empty());
+}
+
+#[test]
+fn command_hooks_are_refused() {
+    let errors = extra_line_errors(&["max-retries = 5", "on-complete = rm -rf ~"]);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].starts_with("Line 2:"), "{}", errors[0]);
+    assert!(errors[0].contains("on-complete"), "{}", errors[0]);
+}
+
+#[test]
+fn app_options_are_refused() {
+    for line in ["user = someone", "--pass = secret", "format = mp3", "pref-min-bitrate = 128", "input = other.txt"] {
+        assert_eq!(extra_line_errors(&[line]).len(), 1, "'{}' passed", line);
+    }
+}
+
+#[test]
+fn name_formats_sldl_understands_pass() {
+    assert!(check_name_format("{albumartist|artist}/{album} ({year})/{track}. {title}").is_ok());
+    assert!(check_name_format("{(Disc )disc}/{artist( - )title|filename}").is_ok());
+}
+
+#[test]
+fn name_format_modifiers_are_refused() {
+    // sldl would drop them, naming files differently than the preview
+    for format in ["{track:02}. {title}", "{artist:upper}/{album}", "{artist|albumartist:title}"] {
+        let error = check_name_format(format).unwrap_err();
+        assert!(error.contains("modifiers"), "{}: {}", format, error);
+    }
+}
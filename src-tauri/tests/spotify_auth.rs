@@ -0,0 +1,89 @@
+// Tests of the Spotify token requests against a mock accounts service
+
+mod common;
+
+use common::accounts::{token_json, MockAccounts};
+use soulshark_lib::spotify::auth;
+use tauri::async_runtime::block_on;
+
+const REDIRECT_URI: &str = "http://localhost:9871/callback";
+
+#[test]
+fn exchange_code_sends_the_pkce_verifier() {
+    let accounts = MockAccounts::start();
+    accounts.reply(200, &token_json("access", Some("refresh")));
+
+    let token = block_on(auth::exchange_code(
+        &accounts.url,
+        "client",
+        None,
+        "the-code",
+        REDIRECT_URI,
+        "the-verifier",
+    ))
+    .unwrap();
+    assert_eq!(token.access_token, "access");
+    assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+    assert_eq!(token.expires_in, 3600);
+
+    let requests = accounts.requests();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/api/token"));
+    assert_eq!(request.form["grant_type"], "authorization_code");
+    assert_eq!(request.form["client_id"], "client");
+    assert_eq!(request.form["code"], "the-code");
+    assert_eq!(request.form["redirect_uri"], REDIRECT_URI);
+    assert_eq!(request.form["code_verifier"], "the-verifier");
+    assert!(!request.form.contains_key("client_secret"));
+}
+
+#[test]
+fn exchange_code_sends_the_client_secret_if_set() {
+    let accounts = MockAccounts::start();
+
+    block_on(auth::exchange_code(&accounts.url, "client", Some("secret"), "code", REDIRECT_URI, "verifier")).unwrap();
+    block_on(auth::exchange_code(&accounts.url, "client", Some(""), "code", REDIRECT_URI, "verifier")).unwrap();
+
+    let requests = accounts.requests();
+    assert_eq!(requests[0].form["client_secret"], "secret");
+    assert!(!requests[1].form.contains_key("client_secret"));
+}
+
+#[test]
+fn exchange_code_reports_the_error_response() {
+    let accounts = MockAccounts::start();
+    accounts.reply(400, r#"{"error":"invalid_grant","error_description":"Invalid authorization code"}"#);
+
+    let error = block_on(auth::exchange_code(&accounts.url, "client", None, "bad", REDIRECT_URI, "verifier")).unwrap_err();
+    assert!(error.starts_with("Token request failed"), "{}", error);
+    assert!(error.contains("invalid_grant"), "{}", error);
+}
+
+#[test]
+fn refresh_token_keeps_the_old_one_when_omitted() {
+    let accounts = MockAccounts::start();
+    accounts.reply(200, &token_json("new-access", None));
+
+    let token = block_on(auth::refresh_token(&accounts.url, "client", None, "old-refresh")).unwrap();
+    assert_eq!(token.access_token, "new-access");
+    assert_eq!(token.refresh_token, None);
+
+    let request = &accounts.requests()[0];
+    assert_eq!(request.form["grant_type"], "refresh_token");
+    assert_eq!(request.form["refresh_token"], "old-refresh");
+    assert_eq!(request.form["client_id"], "client");
+}
+
+#[test]
+fn refresh_token_reports_errors() {
+    let accounts = MockAccounts::start();
+    accounts.reply(400, r#"{"error":"invalid_grant","error_description":"Refresh token revoked"}"#);
+    accounts.reply(200, "not json");
+
+    let error = block_on(auth::refresh_token(&accounts.url, "client", None, "revoked")).unwrap_err();
+    assert!(error.contains("Refresh token revoked"), "{}", error);
+
+    let error = block_on(auth::refresh_token(&accounts.url, "client", None, "refresh")).unwrap_err();
+    assert!(error.starts_with("Failed to parse token response"), "{}", error);
+}
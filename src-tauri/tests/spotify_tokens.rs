@@ -0,0 +1,162 @@
+// Tests of the Spotify token cache, refreshing against a mock accounts service
+
+mod common;
+
+use common::accounts::MockAccounts;
+use common::TestApp;
+use soulshark_lib::settings::keystore::KeyStorageKind;
+use soulshark_lib::settings::{store, SpotifyAccount};
+use soulshark_lib::spotify::token::SpotifyTokenManager;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::block_on;
+use tauri::Manager;
+
+const ACCOUNT_ID: &str = "listener";
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+// An app using the mock accounts service, with one linked account whose
+// access token expires at the given time
+fn app_with_account(accounts: &MockAccounts, token_expires_at: u64) -> TestApp {
+    let app = TestApp::new();
+    app.update_settings(|settings| {
+        settings.endpoints.spotify_accounts_url = accounts.url.clone();
+        settings.spotify.client_id = "client".to_string();
+        // Keep the key out of the OS keyring
+        settings.security.key_storage = KeyStorageKind::File;
+    });
+
+    let mut credentials = block_on(store::get_credentials(app.app.handle())).unwrap();
+    credentials.upsert_spotify_account(SpotifyAccount {
+        id: ACCOUNT_ID.to_string(),
+        display_name: "Listener".to_string(),
+        access_token: Some("stored-access".to_string()),
+        refresh_token: Some("stored-refresh".to_string()),
+        token_expires_at: Some(token_expires_at),
+    });
+    block_on(store::save_credentials(app.app.handle(), credentials)).unwrap();
+    app
+}
+
+fn get_valid_token(app: &TestApp, account_id: Option<&str>) -> Result<String, String> {
+    let tokens = app.app.state::<SpotifyTokenManager>();
+    block_on(tokens.get_valid_token(app.app.handle(), account_id))
+}
+
+fn stored_account(app: &TestApp) -> SpotifyAccount {
+    let credentials = block_on(store::get_credentials(app.app.handle())).unwrap();
+    credentials.spotify_account(Some(ACCOUNT_ID)).cloned().expect("account is gone")
+}
+
+#[test]
+fn valid_token_is_used_without_a_request() {
+    let accounts = MockAccounts::start();
+    let app = app_with_account(&accounts, now() + 3600);
+
+    assert_eq!(get_valid_token(&app, None).unwrap(), "stored-access");
+    assert_eq!(get_valid_token(&app, Some(ACCOUNT_ID)).unwrap(), "stored-access");
+    assert!(accounts.requests().is_empty());
+}
+
+#[test]
+fn expired_token_is_refreshed_once() {
+    let accounts = MockAccounts::start();
+    let app = app_with_account(&accounts, now() - 10);
+
+    assert_eq!(get_valid_token(&app, None).unwrap(), "access-1");
+    // The new token is cached
+    assert_eq!(get_valid_token(&app, None).unwrap(), "access-1");
+
+    let requests = accounts.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].form["grant_type"], "refresh_token");
+    assert_eq!(requests[0].form["refresh_token"], "stored-refresh");
+
+    // and stored, keeping the refresh token the response left out
+    let account = stored_account(&app);
+    assert_eq!(account.access_token.as_deref(), Some("access-1"));
+    assert_eq!(account.refresh_token.as_deref(), Some("stored-refresh"));
+    assert!(account.token_expires_at.unwrap() > now() + 3000);
+}
+
+#[test]
+fn token_about_to_expire_is_refreshed() {
+    let accounts = MockAccounts::start();
+    let app = app_with_account(&accounts, now() + 30);
+
+    assert_eq!(get_valid_token(&app, None).unwrap(), "access-1");
+    assert_eq!(accounts.requests().len(), 1);
+}
+
+#[test]
+fn token_without_expiry_is_refreshed() {
+    let accounts = MockAccounts::start();
+    let app = app_with_account(&accounts, now() + 3600);
+    let mut credentials = block_on(store::get_credentials(app.app.handle())).unwrap();
+    credentials.upsert_spotify_account(SpotifyAccount {
+        token_expires_at: None,
+        ..stored_account(&app)
+    });
+    block_on(store::save_credentials(app.app.handle(), credentials)).unwrap();
+
+    assert_eq!(get_valid_token(&app, None).unwrap(), "access-1");
+    let requests = accounts.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].form["refresh_token"], "stored-refresh");
+    assert!(stored_account(&app).token_expires_at.unwrap() > now() + 3000);
+}
+
+#[test]
+fn concurrent_callers_share_one_refresh() {
+    let accounts = MockAccounts::start();
+    accounts.delay(Duration::from_millis(300));
+    let app = app_with_account(&accounts, now() - 10);
+
+    let callers: Vec<_> = (0..2)
+        .map(|_| {
+            let app_handle = app.app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let tokens = app_handle.state::<SpotifyTokenManager>();
+                tokens.get_valid_token(&app_handle, None).await
+            })
+        })
+        .collect();
+    for caller in callers {
+        assert_eq!(block_on(caller).unwrap().unwrap(), "access-1");
+    }
+
+    assert_eq!(accounts.requests().len(), 1);
+}
+
+#[test]
+fn failed_refresh_is_reported() {
+    let accounts = MockAccounts::start();
+    accounts.reply(400, r#"{"error":"invalid_grant","error_description":"Refresh token revoked"}"#);
+    let app = app_with_account(&accounts, now() - 10);
+
+    let error = get_valid_token(&app, None).unwrap_err();
+    assert!(error.contains("Refresh token revoked"), "{}", error);
+    assert_eq!(stored_account(&app).access_token.as_deref(), Some("stored-access"));
+}
+
+#[test]
+fn unknown_account_is_an_error() {
+    let accounts = MockAccounts::start();
+    let app = app_with_account(&accounts, now() + 3600);
+
+    assert_eq!(
+        get_valid_token(&app, Some("stranger")).unwrap_err(),
+        "Unknown Spotify account: stranger"
+    );
+    assert!(accounts.requests().is_empty());
+}
+
+#[test]
+fn no_account_is_not_connected() {
+    let app = TestApp::new();
+    app.update_settings(|settings| settings.security.key_storage = KeyStorageKind::File);
+
+    assert_eq!(get_valid_token(&app, None).unwrap_err(), "Not connected to Spotify");
+}
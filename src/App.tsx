@@ -10,6 +10,7 @@ import SpotifyContent from "@/components/spotify/SpotifyContent";
 import DownloadsPage from "@/components/downloads/DownloadsPage";
 import { getUserPlaylists, getLikedTracksCount } from "@/lib/spotify";
 import { SpotifyProvider, useSpotify } from "@/lib/SpotifyContext";
+import { getSystemInfo } from "@/lib/system";
 import SimpleBar from 'simplebar-react';
 
 // Types
@@ -58,6 +59,21 @@ function AppContent() {
     };
   }, []);
 
+  // Warn once at startup if sldl is a version the app doesn't understand
+  useEffect(() => {
+    getSystemInfo()
+      .then(({ sldl }) => {
+        if (sldl.compatibility === "unsupported") {
+          toast.warning(
+            `sldl ${sldl.version} is not supported (supported: ${sldl.supported}). Download progress may not be shown.`
+          );
+        } else if (sldl.error) {
+          toast.warning(`Could not run sldl: ${sldl.error}`);
+        }
+      })
+      .catch((error) => console.error("Failed to get system info:", error));
+  }, []);
+
   const [currentPage, setCurrentPage] = useState("home");
   const [spotifyPlaylists, setSpotifyPlaylists] = useState<SpotifyPlaylist[]>([]);
   const [likedSongsCount, setLikedSongsCount] = useState<number | null>(null);
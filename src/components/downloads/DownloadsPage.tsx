@@ -2,9 +2,29 @@ import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { Button } from "@/components/ui/button";
-import { X, RefreshCw, Download, CheckCircle, AlertCircle, Search, ChevronDown, ChevronUp, Terminal } from "lucide-react";
+import type { SoulsharkError } from "@/lib/errors";
+import { X, RefreshCw, Download, CheckCircle, AlertCircle, Search, ChevronDown, ChevronUp, Terminal, Pause, Play } from "lucide-react";
 
 // Types
+// Album-level state of an album download
+interface AlbumMode {
+  status: "searching" | "downloading" | "complete" | "mismatched" | "incomplete" | "not_found" | "fell_back";
+  expected_tracks: number | null;
+  allow_fallback: boolean;
+  tracks: { path: string; format: string; bitrate_kbps: number }[];
+  problems: string[];
+}
+
+const ALBUM_STATUS_LABELS: Record<AlbumMode["status"], string> = {
+  searching: "Looking for a folder with the whole album",
+  downloading: "Downloading the album folder",
+  complete: "Whole album, consistent format",
+  mismatched: "Whole album",
+  incomplete: "Album incomplete",
+  not_found: "Album not found",
+  fell_back: "Downloaded track by track",
+};
+
 interface Download {
   id: string;
   title: string;
@@ -12,7 +32,7 @@ interface Download {
   album?: string;
   query: string;
   started_at: number;
-  status: "Queued" | "Searching" | "InProgress" | "Completed" | { Failed: string } | "Canceled";
+  status: "Queued" | "Searching" | "InProgress" | "Completed" | { Failed: SoulsharkError } | "Canceled" | { Paused: string };
   progress?: number;
   file_path?: string;
   is_playlist: boolean;
@@ -20,6 +40,7 @@ interface Download {
   completed_tracks?: number;
   failed_tracks?: number;
   console_logs: string[];
+  album_mode?: AlbumMode | null;
 }
 
 export default function DownloadsPage() {
@@ -73,6 +94,15 @@ export default function DownloadsPage() {
       );
     });
     
+    const unlisten7 = listen<Download>("download:paused", (event) => {
+      console.log("Download paused:", event.payload);
+      setDownloads(prev => 
+        prev.map(download => 
+          download.id === event.payload.id ? event.payload : download
+        )
+      );
+    });
+    
     // Also listen for stdout events to update console logs in real-time
     const unlisten6 = listen<string>("sldl:stdout", (event) => {
       console.log("SLDL stdout:", event.payload);
@@ -98,6 +128,7 @@ export default function DownloadsPage() {
       unlisten4.then(fn => fn());
       unlisten5.then(fn => fn());
       unlisten6.then(fn => fn());
+      unlisten7.then(fn => fn());
     };
   }, []);
   
@@ -123,6 +154,15 @@ export default function DownloadsPage() {
     }
   };
   
+  // Resume a download paused for low disk space
+  const resumeDownload = async (id: string) => {
+    try {
+      await invoke("resume_download", { id });
+    } catch (error) {
+      console.error("Failed to resume download:", error);
+    }
+  };
+  
   // Clear completed downloads
   const clearCompletedDownloads = async () => {
     try {
@@ -168,10 +208,19 @@ export default function DownloadsPage() {
       return "Completed";
     }
     if (status === "Canceled") return "Canceled";
-    if (typeof status === "object" && "Failed" in status) return `Failed: ${status.Failed}`;
+    if (typeof status === "object" && "Failed" in status) return `Failed: ${status.Failed.message}`;
+    if (typeof status === "object" && "Paused" in status) return `Paused: ${status.Paused}`;
     return "Unknown";
   };
   
+  // Album status with what is wrong with the album, if anything
+  const getAlbumDisplay = (download: Download) => {
+    const album = download.album_mode;
+    if (!album) return null;
+    const label = ALBUM_STATUS_LABELS[album.status];
+    return album.problems.length > 0 ? `${label}: ${album.problems.join("; ")}` : label;
+  };
+
   // Get status icon
   const getStatusIcon = (status: Download["status"]) => {
     if (status === "Queued") return <Download className="h-4 w-4 text-gray-400" />;
@@ -180,17 +229,21 @@ export default function DownloadsPage() {
     if (status === "Completed") return <CheckCircle className="h-4 w-4 text-green-400" />;
     if (status === "Canceled") return <X className="h-4 w-4 text-gray-400" />;
     if (typeof status === "object" && "Failed" in status) return <AlertCircle className="h-4 w-4 text-red-400" />;
+    if (typeof status === "object" && "Paused" in status) return <Pause className="h-4 w-4 text-orange-400" />;
     return null;
   };
   
+  // Paused downloads wait in the active list until resumed
+  const isPaused = (status: Download["status"]) => typeof status === "object" && "Paused" in status;
+  
   // Get active downloads
   const activeDownloads = downloads.filter(d => 
-    d.status === "Queued" || d.status === "Searching" || d.status === "InProgress"
+    d.status === "Queued" || d.status === "Searching" || d.status === "InProgress" || isPaused(d.status)
   );
   
   // Get completed downloads
   const completedDownloads = downloads.filter(d => 
-    d.status === "Completed" || d.status === "Canceled" || typeof d.status === "object"
+    d.status === "Completed" || d.status === "Canceled" || (typeof d.status === "object" && !isPaused(d.status))
   );
 
   return (
@@ -234,15 +287,28 @@ export default function DownloadsPage() {
                           </p>
                         )}
                       </div>
-                      <Button
-                        variant="ghost"
-                        size="icon"
-                        className="h-8 w-8"
-                        onClick={() => cancelDownload(download.id)}
-                        title="Cancel download"
-                      >
-                        <X className="h-4 w-4" />
-                      </Button>
+                      <div className="flex gap-1">
+                        {isPaused(download.status) && (
+                          <Button
+                            variant="ghost"
+                            size="icon"
+                            className="h-8 w-8"
+                            onClick={() => resumeDownload(download.id)}
+                            title="Resume download"
+                          >
+                            <Play className="h-4 w-4" />
+                          </Button>
+                        )}
+                        <Button
+                          variant="ghost"
+                          size="icon"
+                          className="h-8 w-8"
+                          onClick={() => cancelDownload(download.id)}
+                          title="Cancel download"
+                        >
+                          <X className="h-4 w-4" />
+                        </Button>
+                      </div>
                     </div>
                     
                     <div className="flex items-center gap-2 text-sm text-gray-400">
@@ -253,6 +319,9 @@ export default function DownloadsPage() {
                       <span>•</span>
                       <span>Started: {formatTimestamp(download.started_at)}</span>
                     </div>
+                    {download.album_mode && (
+                      <p className="text-xs text-gray-400 mt-1">{getAlbumDisplay(download)}</p>
+                    )}
                     
                     {download.progress !== undefined && (
                       <div className="mt-2">
@@ -357,6 +426,11 @@ export default function DownloadsPage() {
                           <span>•</span>
                           <span>Completed: {formatTimestamp(download.started_at)}</span>
                         </div>
+                        {download.album_mode && (
+                          <p className={`text-xs mt-1 ${download.album_mode.status === "complete" ? "text-gray-400" : "text-yellow-400"}`}>
+                            {getAlbumDisplay(download)}
+                          </p>
+                        )}
                         
                         {/* Console logs toggle button for completed downloads */}
                         {download.console_logs && download.console_logs.length > 0 && (
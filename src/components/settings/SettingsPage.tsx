@@ -1,5 +1,8 @@
 import { useState, useEffect } from "react";
 import { useSpotify } from "@/lib/SpotifyContext";
+import { errorMessage } from "@/lib/errors";
+import { getSystemInfo, SystemInfo } from "@/lib/system";
+import { listPeers, PeerEntry, PeerList, removePeer, setPeerList } from "@/lib/peers";
 import { toast } from "sonner";
 import { invoke } from "@tauri-apps/api/core";
 import { load } from "@tauri-apps/plugin-store";
@@ -17,6 +20,7 @@ interface SoulseekSettings {
   downloads_path: string;
   remove_special_chars: boolean;
   preferred_format: string;
+  min_free_space_mb: number;
 }
 
 interface SpotifySettings {
@@ -29,18 +33,118 @@ interface OutputSettings {
   name_format: string;
 }
 
+interface LoggingSettings {
+  level: string;
+}
+
+// Conditions sldl checks files against, null for sldl's default
+interface SldlConditions {
+  formats: string[];
+  min_bitrate: number | null;
+  max_bitrate: number | null;
+  min_samplerate: number | null;
+  max_samplerate: number | null;
+  min_bitdepth: number | null;
+  max_bitdepth: number | null;
+  length_tol: number | null;
+  strict_title: boolean;
+  strict_artist: boolean;
+  strict_album: boolean;
+  banned_users: string[];
+}
+
+// Written to the sldl config file for every download. Options without a
+// field here (max stale time, album art, YouTube) are kept as loaded.
+interface SldlConfig {
+  required: SldlConditions;
+  preferred: SldlConditions;
+  search: {
+    search_timeout_ms: number | null;
+    concurrent_downloads: number | null;
+    fast_search: boolean;
+    [option: string]: unknown;
+  };
+  album: {
+    enabled: boolean;
+    fallback_to_tracks: boolean;
+    [option: string]: unknown;
+  };
+  input: Record<string, unknown>;
+  extra_lines: string[];
+}
+
+interface SldlSettings {
+  path: string;
+  config: SldlConfig;
+}
+
+interface PeerSettings {
+  auto_blacklist: boolean;
+  min_transfers: number;
+  // 0 to 1
+  max_failure_rate: number;
+}
+
 interface AppSettings {
   soulseek: SoulseekSettings;
   spotify: SpotifySettings;
   output: OutputSettings;
+  logging: LoggingSettings;
+  sldl: SldlSettings;
+  peers: PeerSettings;
 }
 
+const LOG_LEVELS = ["error", "warn", "info", "debug", "trace"];
+
+const DEFAULT_CONDITIONS: SldlConditions = {
+  formats: [],
+  min_bitrate: null,
+  max_bitrate: null,
+  min_samplerate: null,
+  max_samplerate: null,
+  min_bitdepth: null,
+  max_bitdepth: null,
+  length_tol: null,
+  strict_title: false,
+  strict_artist: false,
+  strict_album: false,
+  banned_users: [],
+};
+
+const DEFAULT_SLDL_CONFIG: SldlConfig = {
+  required: DEFAULT_CONDITIONS,
+  preferred: DEFAULT_CONDITIONS,
+  search: { search_timeout_ms: null, concurrent_downloads: null, fast_search: false },
+  album: { enabled: false, fallback_to_tracks: false },
+  input: {},
+  extra_lines: [],
+};
+
+const DEFAULT_PEER_SETTINGS: PeerSettings = {
+  auto_blacklist: false,
+  min_transfers: 3,
+  max_failure_rate: 0.5,
+};
+
+// Comma separated list to an array, dropping empty entries
+const splitList = (value: string) =>
+  value.split(",").map((item) => item.trim()).filter((item) => item !== "");
+
+// Number input to a number, or null when cleared
+const parseOptional = (value: string) => {
+  const number = parseInt(value, 10);
+  return Number.isNaN(number) ? null : Math.max(0, number);
+};
+
 interface Credentials {
   soulseek_password: string | null;
   spotify_client_secret: string | null;
   spotify_access_token: string | null;
   spotify_refresh_token: string | null;
   spotify_token_expires_at: number | null;
+  // Linked Spotify accounts, passed back unchanged when saving
+  spotify_accounts: unknown[];
+  active_spotify_account: string | null;
 }
 
 export default function SettingsPage() {
@@ -51,15 +155,24 @@ export default function SettingsPage() {
       downloads_path: "",
       remove_special_chars: true,
       preferred_format: "flac",
+      min_free_space_mb: 2048,
     },
     spotify: {
       client_id: "",
-      redirect_uri: "http://localhost:5174/callback",
+      redirect_uri: "http://localhost:9871/callback",
     },
     output: {
       m3u_path: "playlists/",
       name_format: "{albumartist|artist}/{album} ({year})/{track}. {title}",
     },
+    logging: {
+      level: "info",
+    },
+    sldl: {
+      path: "",
+      config: DEFAULT_SLDL_CONFIG,
+    },
+    peers: DEFAULT_PEER_SETTINGS,
   });
 
   const [credentials, setCredentials] = useState<Credentials>({
@@ -68,16 +181,36 @@ export default function SettingsPage() {
     spotify_access_token: null,
     spotify_refresh_token: null,
     spotify_token_expires_at: null,
+    spotify_accounts: [],
+    active_spotify_account: null,
   });
 
   const [isSaving, setIsSaving] = useState(false);
+  const [namePreview, setNamePreview] = useState<{ path?: string; error?: string }>({});
+  const [systemInfo, setSystemInfo] = useState<SystemInfo | null>(null);
+  const [peers, setPeers] = useState<PeerEntry[]>([]);
+  const [newPeer, setNewPeer] = useState("");
 
   // Load settings and credentials on component mount
   useEffect(() => {
     loadSettings();
     loadCredentials();
+    loadSystemInfo();
+    loadPeers();
   }, []);
 
+  // Show the path a sample track would be saved under as the format changes
+  useEffect(() => {
+    const template = settings.output.name_format;
+    const timeout = setTimeout(() => {
+      invoke<string>("preview_name_format", { template })
+        .then((path) => setNamePreview({ path }))
+        .catch((error) => setNamePreview({ error: errorMessage(error) }));
+    }, 300);
+
+    return () => clearTimeout(timeout);
+  }, [settings.output.name_format]);
+
   // Load settings from backend
   const loadSettings = async () => {
     try {
@@ -105,7 +238,44 @@ export default function SettingsPage() {
       setSettings(appSettings);
     } catch (error) {
       console.error("Failed to load settings:", error);
-      toast.error(`Failed to load settings: ${error}`);
+      toast.error(`Failed to load settings: ${errorMessage(error)}`);
+    }
+  };
+
+  // Load the detected sldl version
+  const loadSystemInfo = async () => {
+    try {
+      setSystemInfo(await getSystemInfo());
+    } catch (error) {
+      console.error("Failed to load system info:", error);
+    }
+  };
+
+  // Load the recorded peers
+  const loadPeers = async () => {
+    try {
+      setPeers(await listPeers());
+    } catch (error) {
+      console.error("Failed to load peers:", error);
+    }
+  };
+
+  // Blacklist or whitelist a peer, or take it off its list
+  const changePeerList = async (username: string, list: PeerList | null) => {
+    try {
+      await setPeerList(username, list);
+      await loadPeers();
+    } catch (error) {
+      toast.error(`Failed to update ${username}: ${errorMessage(error)}`);
+    }
+  };
+
+  const forgetPeer = async (username: string) => {
+    try {
+      await removePeer(username);
+      await loadPeers();
+    } catch (error) {
+      toast.error(`Failed to remove ${username}: ${errorMessage(error)}`);
     }
   };
 
@@ -118,7 +288,7 @@ export default function SettingsPage() {
       setCredentials(creds);
     } catch (error) {
       console.error("Failed to load credentials:", error);
-      toast.error(`Failed to load credentials: ${error}`);
+      toast.error(`Failed to load credentials: ${errorMessage(error)}`);
     }
   };
 
@@ -151,6 +321,8 @@ export default function SettingsPage() {
           spotify_access_token: credentials.spotify_access_token,
           spotify_refresh_token: credentials.spotify_refresh_token,
           spotify_token_expires_at: credentials.spotify_token_expires_at,
+          spotify_accounts: credentials.spotify_accounts,
+          active_spotify_account: credentials.active_spotify_account,
         } 
       });
       
@@ -159,6 +331,7 @@ export default function SettingsPage() {
       // Reload settings to verify they were saved correctly
       await loadSettings();
       await loadCredentials();
+      await loadSystemInfo();
     } catch (error) {
       console.error("Failed to save settings:", error);
       toast.error("Failed to save settings. Please try again.");
@@ -167,6 +340,17 @@ export default function SettingsPage() {
     }
   };
 
+  // Bundle logs and recent downloads for a bug report
+  const exportDiagnostics = async () => {
+    try {
+      const path = await invoke<string>("export_diagnostics");
+      toast.success(`Diagnostics saved to ${path}`);
+    } catch (error) {
+      console.error("Failed to export diagnostics:", error);
+      toast.error(`Failed to export diagnostics: ${errorMessage(error)}`);
+    }
+  };
+
   // Update settings state
   const updateSettings = (
     section: keyof AppSettings,
@@ -182,6 +366,30 @@ export default function SettingsPage() {
     }));
   };
 
+  // Settings stored before the sldl config existed don't have it
+  const sldlConfig: SldlConfig = { ...DEFAULT_SLDL_CONFIG, ...settings.sldl?.config };
+
+  // Replace sections of the sldl config
+  const updateSldlConfig = (update: Partial<SldlConfig>) => {
+    setSettings((prev) => ({
+      ...prev,
+      sldl: {
+        ...prev.sldl,
+        config: { ...DEFAULT_SLDL_CONFIG, ...prev.sldl?.config, ...update },
+      },
+    }));
+  };
+
+  // Settings stored before peer reputation existed don't have it
+  const peerSettings: PeerSettings = { ...DEFAULT_PEER_SETTINGS, ...settings.peers };
+
+  const updatePeerSettings = (update: Partial<PeerSettings>) => {
+    setSettings((prev) => ({
+      ...prev,
+      peers: { ...DEFAULT_PEER_SETTINGS, ...prev.peers, ...update },
+    }));
+  };
+
   // Update credentials state
   const updateCredentials = (field: keyof Credentials, value: string | null) => {
     setCredentials((prev) => ({
@@ -199,6 +407,9 @@ export default function SettingsPage() {
           <TabsTrigger value="soulseek">Soulseek</TabsTrigger>
           <TabsTrigger value="spotify">Spotify</TabsTrigger>
           <TabsTrigger value="output">Output</TabsTrigger>
+          <TabsTrigger value="search">Search</TabsTrigger>
+          <TabsTrigger value="peers">Peers</TabsTrigger>
+          <TabsTrigger value="diagnostics">Diagnostics</TabsTrigger>
         </TabsList>
 
         {/* Soulseek Settings */}
@@ -261,6 +472,26 @@ export default function SettingsPage() {
                   />
                 </div>
 
+                <div className="space-y-2">
+                  <Label htmlFor="min-free-space">Minimum Free Space (MB)</Label>
+                  <Input
+                    id="min-free-space"
+                    type="number"
+                    min={0}
+                    value={settings.soulseek.min_free_space_mb}
+                    onChange={(e) =>
+                      updateSettings(
+                        "soulseek",
+                        "min_free_space_mb",
+                        Math.max(0, parseInt(e.target.value, 10) || 0)
+                      )
+                    }
+                  />
+                  <p className="text-xs text-gray-400">
+                    Downloads are paused when the downloads drive has less free space than this. Set to 0 to never pause.
+                  </p>
+                </div>
+
                 <div className="flex items-center space-x-2">
                   <Checkbox
                     id="remove-special-chars"
@@ -307,7 +538,7 @@ export default function SettingsPage() {
                 </div>
 
                 <div className="space-y-2">
-                  <Label htmlFor="spotify-client-secret">Client Secret</Label>
+                  <Label htmlFor="spotify-client-secret">Client Secret (optional)</Label>
                   <Input
                     id="spotify-client-secret"
                     type="password"
@@ -315,7 +546,7 @@ export default function SettingsPage() {
                     onChange={(e) =>
                       updateCredentials("spotify_client_secret", e.target.value)
                     }
-                    placeholder="Your Spotify Client Secret"
+                    placeholder="Not needed for PKCE login"
                   />
                 </div>
 
@@ -323,13 +554,14 @@ export default function SettingsPage() {
                   <Label htmlFor="spotify-redirect-uri">Redirect URI</Label>
                   <Input
                     id="spotify-redirect-uri"
-                    value="http://localhost:5174/callback"
-                    readOnly
-                    disabled
-                    placeholder="http://localhost:5174/callback"
+                    value={settings.spotify.redirect_uri}
+                    onChange={(e) =>
+                      updateSettings("spotify", "redirect_uri", e.target.value)
+                    }
+                    placeholder="http://localhost:9871/callback"
                   />
                   <p className="text-sm text-muted-foreground">
-                    This URI is fixed for the internal callback server
+                    Must match a Redirect URI registered for your Spotify app. Logging in fails if its port is busy
                   </p>
                 </div>
               </div>
@@ -406,7 +638,354 @@ export default function SettingsPage() {
                     placeholder="{albumartist|artist}/{album} ({year})/{track}. {title}"
                   />
                   <p className="text-sm text-muted-foreground">
-                    Format for organizing downloaded files
+                    Format for organizing downloaded files. Use {"{a|b}"} for fallbacks, {"{(text)tag}"} for text shown only with the tag
+                  </p>
+                  {namePreview.error ? (
+                    <p className="text-sm text-destructive">{namePreview.error}</p>
+                  ) : namePreview.path ? (
+                    <p className="text-sm text-muted-foreground">
+                      Preview: <code>{namePreview.path}</code>
+                    </p>
+                  ) : null}
+                </div>
+              </div>
+            </CardContent>
+          </Card>
+        </TabsContent>
+
+        {/* Search Settings */}
+        <TabsContent value="search">
+          <Card className="dark">
+            <CardHeader>
+              <CardTitle>Search Settings</CardTitle>
+              <CardDescription>
+                Choose which files sldl accepts and how it searches. Empty fields keep sldl's defaults
+              </CardDescription>
+            </CardHeader>
+            <CardContent className="space-y-4">
+              <div className="grid grid-cols-1 gap-4">
+                <div className="space-y-2">
+                  <Label htmlFor="required-formats">Required Formats</Label>
+                  <Input
+                    id="required-formats"
+                    value={sldlConfig.required.formats.join(", ")}
+                    onChange={(e) =>
+                      updateSldlConfig({
+                        required: { ...sldlConfig.required, formats: splitList(e.target.value) },
+                      })
+                    }
+                    placeholder="Any format"
+                  />
+                  <p className="text-sm text-muted-foreground">
+                    Files in other formats are never downloaded. The preferred format is set on the Soulseek tab
+                  </p>
+                </div>
+
+                <div className="grid grid-cols-2 gap-4">
+                  <div className="space-y-2">
+                    <Label htmlFor="required-min-bitrate">Minimum Bitrate (kbps)</Label>
+                    <Input
+                      id="required-min-bitrate"
+                      type="number"
+                      min={0}
+                      value={sldlConfig.required.min_bitrate ?? ""}
+                      onChange={(e) =>
+                        updateSldlConfig({
+                          required: { ...sldlConfig.required, min_bitrate: parseOptional(e.target.value) },
+                        })
+                      }
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="preferred-min-bitrate">Preferred Minimum Bitrate (kbps)</Label>
+                    <Input
+                      id="preferred-min-bitrate"
+                      type="number"
+                      min={0}
+                      value={sldlConfig.preferred.min_bitrate ?? ""}
+                      onChange={(e) =>
+                        updateSldlConfig({
+                          preferred: { ...sldlConfig.preferred, min_bitrate: parseOptional(e.target.value) },
+                        })
+                      }
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="search-timeout">Search Timeout (ms)</Label>
+                    <Input
+                      id="search-timeout"
+                      type="number"
+                      min={1}
+                      value={sldlConfig.search.search_timeout_ms ?? ""}
+                      onChange={(e) =>
+                        updateSldlConfig({
+                          search: { ...sldlConfig.search, search_timeout_ms: parseOptional(e.target.value) },
+                        })
+                      }
+                    />
+                  </div>
+
+                  <div className="space-y-2">
+                    <Label htmlFor="concurrent-downloads">Concurrent Downloads</Label>
+                    <Input
+                      id="concurrent-downloads"
+                      type="number"
+                      min={1}
+                      value={sldlConfig.search.concurrent_downloads ?? ""}
+                      onChange={(e) =>
+                        updateSldlConfig({
+                          search: { ...sldlConfig.search, concurrent_downloads: parseOptional(e.target.value) },
+                        })
+                      }
+                    />
+                  </div>
+                </div>
+
+                <div className="flex items-center space-x-2">
+                  <Checkbox
+                    id="fast-search"
+                    checked={sldlConfig.search.fast_search}
+                    onCheckedChange={(checked) =>
+                      updateSldlConfig({ search: { ...sldlConfig.search, fast_search: checked === true } })
+                    }
+                  />
+                  <Label htmlFor="fast-search">
+                    Fast search: start downloading a good match before the search finishes
+                  </Label>
+                </div>
+
+                <div className="flex items-center space-x-2">
+                  <Checkbox
+                    id="album-mode"
+                    checked={sldlConfig.album.enabled}
+                    onCheckedChange={(checked) =>
+                      updateSldlConfig({ album: { ...sldlConfig.album, enabled: checked === true } })
+                    }
+                  />
+                  <Label htmlFor="album-mode">
+                    Album mode: download every search as a whole album folder
+                  </Label>
+                </div>
+
+                <div className="flex items-center space-x-2">
+                  <Checkbox
+                    id="album-fallback"
+                    checked={sldlConfig.album.fallback_to_tracks}
+                    onCheckedChange={(checked) =>
+                      updateSldlConfig({ album: { ...sldlConfig.album, fallback_to_tracks: checked === true } })
+                    }
+                  />
+                  <Label htmlFor="album-fallback">
+                    When no folder has a whole album, download its tracks one by one from different users
+                  </Label>
+                </div>
+
+                <div className="space-y-2">
+                  <Label htmlFor="sldl-extra-lines">Extra sldl Options</Label>
+                  <textarea
+                    id="sldl-extra-lines"
+                    className="flex min-h-24 w-full rounded-md border border-input bg-transparent px-3 py-2 font-mono text-sm shadow-sm"
+                    value={sldlConfig.extra_lines.join("\n")}
+                    onChange={(e) =>
+                      updateSldlConfig({ extra_lines: e.target.value === "" ? [] : e.target.value.split("\n") })
+                    }
+                    placeholder={"max-retries = 5\n[no-stale]\nmax-stale-time = 9999999"}
+                  />
+                  <p className="text-sm text-muted-foreground">
+                    Lines added to sldl's config file as they are, one "option = value" per line. Sections like [name] start an sldl profile
+                  </p>
+                </div>
+              </div>
+            </CardContent>
+          </Card>
+        </TabsContent>
+
+        {/* Peers */}
+        <TabsContent value="peers">
+          <Card className="dark">
+            <CardHeader>
+              <CardTitle>Peers</CardTitle>
+              <CardDescription>
+                Track how Soulseek users' transfers go and choose whose files to skip
+              </CardDescription>
+            </CardHeader>
+            <CardContent className="space-y-4">
+              <div className="grid grid-cols-1 gap-4">
+                <div className="flex items-center space-x-2">
+                  <Checkbox
+                    id="auto-blacklist"
+                    checked={peerSettings.auto_blacklist}
+                    onCheckedChange={(checked) => updatePeerSettings({ auto_blacklist: checked === true })}
+                  />
+                  <Label htmlFor="auto-blacklist">
+                    Skip users whose transfers keep failing or arrive broken, unless they are whitelisted
+                  </Label>
+                </div>
+
+                <div className="grid grid-cols-2 gap-4">
+                  <div className="space-y-2">
+                    <Label htmlFor="peer-min-transfers">Minimum Transfers</Label>
+                    <Input
+                      id="peer-min-transfers"
+                      type="number"
+                      min={1}
+                      value={peerSettings.min_transfers}
+                      onChange={(e) => updatePeerSettings({ min_transfers: parseOptional(e.target.value) ?? 1 })}
+                    />
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="peer-failure-rate">Failure Rate (%)</Label>
+                    <Input
+                      id="peer-failure-rate"
+                      type="number"
+                      min={1}
+                      max={100}
+                      value={Math.round(peerSettings.max_failure_rate * 100)}
+                      onChange={(e) =>
+                        updatePeerSettings({ max_failure_rate: (parseOptional(e.target.value) ?? 50) / 100 })
+                      }
+                    />
+                  </div>
+                </div>
+
+                <div className="flex gap-2">
+                  <Input
+                    placeholder="Soulseek username"
+                    value={newPeer}
+                    onChange={(e) => setNewPeer(e.target.value)}
+                  />
+                  <Button
+                    variant="outline"
+                    disabled={newPeer.trim() === ""}
+                    onClick={async () => {
+                      await changePeerList(newPeer.trim(), "blacklist");
+                      setNewPeer("");
+                    }}
+                  >
+                    Blacklist
+                  </Button>
+                </div>
+
+                {peers.length === 0 ? (
+                  <p className="text-sm text-muted-foreground">
+                    No peers yet. Users are recorded as downloads finish
+                  </p>
+                ) : (
+                  <table className="w-full text-sm">
+                    <thead className="text-left text-muted-foreground">
+                      <tr>
+                        <th className="py-1">User</th>
+                        <th>OK</th>
+                        <th>Failed</th>
+                        <th>Broken</th>
+                        <th>Speed</th>
+                        <th>List</th>
+                        <th></th>
+                      </tr>
+                    </thead>
+                    <tbody>
+                      {peers.map((peer) => (
+                        <tr key={peer.username} className={peer.banned ? "text-red-400" : undefined}>
+                          <td className="py-1 font-mono">{peer.username}</td>
+                          <td>{peer.successes}</td>
+                          <td>{peer.failures}</td>
+                          <td>{peer.verification_failures}</td>
+                          <td>
+                            {peer.average_speed_kbps !== null ? `${Math.round(peer.average_speed_kbps)} KB/s` : "-"}
+                          </td>
+                          <td>
+                            <select
+                              className="rounded-md border border-input bg-transparent px-2 py-1 text-sm"
+                              value={peer.list ?? ""}
+                              onChange={(e) =>
+                                changePeerList(peer.username, e.target.value === "" ? null : (e.target.value as PeerList))
+                              }
+                            >
+                              <option value="">{peer.banned ? "Auto-blacklisted" : "None"}</option>
+                              <option value="blacklist">Blacklist</option>
+                              <option value="whitelist">Whitelist</option>
+                            </select>
+                          </td>
+                          <td className="text-right">
+                            <Button variant="ghost" size="sm" onClick={() => forgetPeer(peer.username)}>
+                              Forget
+                            </Button>
+                          </td>
+                        </tr>
+                      ))}
+                    </tbody>
+                  </table>
+                )}
+                <p className="text-sm text-muted-foreground">
+                  Blacklisted users are passed to sldl as banned users. Broken counts transfers from album folders with mixed formats or missing tracks. Save the settings to apply the automatic blacklist
+                </p>
+              </div>
+            </CardContent>
+          </Card>
+        </TabsContent>
+
+        {/* Diagnostics */}
+        <TabsContent value="diagnostics">
+          <Card className="dark">
+            <CardHeader>
+              <CardTitle>Diagnostics</CardTitle>
+              <CardDescription>
+                Configure logging and collect information for bug reports
+              </CardDescription>
+            </CardHeader>
+            <CardContent className="space-y-4">
+              <div className="grid grid-cols-1 gap-4">
+                <div className="space-y-2">
+                  <Label htmlFor="log-level">Log Level</Label>
+                  <select
+                    id="log-level"
+                    className="flex h-9 w-full rounded-md border border-input bg-transparent px-3 py-1 text-sm shadow-sm"
+                    value={settings.logging?.level ?? "info"}
+                    onChange={(e) =>
+                      updateSettings("logging", "level", e.target.value)
+                    }
+                  >
+                    {LOG_LEVELS.map((level) => (
+                      <option key={level} value={level}>
+                        {level}
+                      </option>
+                    ))}
+                  </select>
+                  <p className="text-sm text-muted-foreground">
+                    Logs are kept for a week in the app data folder. Use debug to include sldl's output
+                  </p>
+                </div>
+
+                <div className="space-y-2">
+                  <Label htmlFor="sldl-path">sldl Path</Label>
+                  <Input
+                    id="sldl-path"
+                    placeholder="Bundled sldl"
+                    value={settings.sldl?.path ?? ""}
+                    onChange={(e) =>
+                      updateSettings("sldl", "path", e.target.value)
+                    }
+                  />
+                  <p className="text-sm text-muted-foreground">
+                    Run another sldl build instead of the bundled one. Leave empty to use the bundled sldl
+                  </p>
+                  {systemInfo && (
+                    <p className={`text-sm ${systemInfo.sldl.compatibility === "supported" ? "text-muted-foreground" : "text-yellow-400"}`}>
+                      {systemInfo.sldl.version
+                        ? `Detected sldl ${systemInfo.sldl.version} (${systemInfo.sldl.compatibility}, supported: ${systemInfo.sldl.supported})`
+                        : `Could not detect the sldl version${systemInfo.sldl.error ? `: ${systemInfo.sldl.error}` : ""}`}
+                    </p>
+                  )}
+                </div>
+
+                <div className="space-y-2">
+                  <Button variant="outline" onClick={exportDiagnostics}>
+                    Export Diagnostics
+                  </Button>
+                  <p className="text-sm text-muted-foreground">
+                    Saves the logs, your settings without passwords or tokens, the sldl version and recent downloads to a zip file to attach to a bug report
                   </p>
                 </div>
               </div>
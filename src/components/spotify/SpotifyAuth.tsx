@@ -1,10 +1,11 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
-import { load } from "@tauri-apps/plugin-store";
+import { listen } from "@tauri-apps/api/event";
 import { toast } from "sonner";
 import { Button } from "@/components/ui/button";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { useSpotify } from "@/lib/SpotifyContext";
+import { errorMessage, isSoulsharkError, type SoulsharkError } from "@/lib/errors";
 
 // Define types
 interface SpotifySettings {
@@ -20,13 +21,19 @@ interface Credentials {
   spotify_token_expires_at: number | null;
 }
 
+interface SpotifyAccount {
+  id: string;
+  display_name: string;
+  active: boolean;
+}
+
 interface SpotifyAuthProps {
   onAuthSuccess?: () => void;
 }
 
 export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
   // Use the global Spotify context
-  const { isAuthenticated, refreshAuthStatus } = useSpotify();
+  const { isAuthenticated, refreshAuthStatus, clearLikedTracksCache } = useSpotify();
   
   const [isAuthenticating, setIsAuthenticating] = useState(false);
   const [spotifySettings, setSpotifySettings] = useState<SpotifySettings>({
@@ -34,11 +41,13 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
     redirect_uri: "",
   });
   const [credentials, setCredentials] = useState<Credentials | null>(null);
+  const [accounts, setAccounts] = useState<SpotifyAccount[]>([]);
 
   // Load settings and check authentication status on mount
   useEffect(() => {
     loadSettings();
     loadCredentials();
+    loadAccounts();
     
     // Cleanup function to stop the server when the component is unmounted
     return () => {
@@ -48,51 +57,35 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
     };
   }, []);
   
-  // Poll for authentication status after initiating auth flow
+  // Wait for the backend to report the result of the login
   useEffect(() => {
-    let interval: number | null = null;
-    
-    if (isAuthenticating) {
-      // Check every 2 seconds if authentication was successful
-      interval = window.setInterval(async () => {
-        try {
-          // Check for authentication directly from the backend
-          const creds = await invoke<Credentials>("get_credentials");
-          
-          // If we have credentials, authentication was successful
-          if (creds?.spotify_access_token) {
-            // Update local state
-            setCredentials(creds);
-            setIsAuthenticating(false);
-            clearInterval(interval!);
-            
-            // Only refresh auth status once at the end of authentication
-            await refreshAuthStatus();
+    if (!isAuthenticating) return;
+
+    const unlisteners = [
+      listen("auth:completed", async () => {
+        setIsAuthenticating(false);
+
+        // Update local state
+        await loadCredentials();
+        await loadAccounts();
+        clearLikedTracksCache();
+        await refreshAuthStatus();
+
+        toast.success("Successfully connected to Spotify!");
 
-            // Force a second refresh after a short delay to ensure context updates
-            setTimeout(() => {
-              refreshAuthStatus().catch((error) => {
-                console.error("Delayed refreshAuthStatus() failed:", error);
-              });
-            }, 1000);
-            
-            toast.success("Successfully connected to Spotify!");
-            
-            // Call the success callback if provided
-            if (onAuthSuccess) {
-              onAuthSuccess();
-            }
-          }
-        } catch (error) {
-          console.error("Failed to check authentication status:", error);
+        // Call the success callback if provided
+        if (onAuthSuccess) {
+          onAuthSuccess();
         }
-      }, 2000);
-    }
-    
+      }),
+      listen<SoulsharkError>("auth:failed", (event) => {
+        setIsAuthenticating(false);
+        toast.error(`Spotify authentication failed: ${event.payload.message}`);
+      }),
+    ];
+
     return () => {
-      if (interval !== null) {
-        clearInterval(interval);
-      }
+      unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()));
     };
   }, [isAuthenticating, onAuthSuccess, refreshAuthStatus]);
 
@@ -119,8 +112,44 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
     }
   };
 
-  // Initiate Spotify authentication
-  const authenticateWithSpotify = async () => {
+  // Load the linked Spotify accounts
+  const loadAccounts = async () => {
+    try {
+      setAccounts(await invoke<SpotifyAccount[]>("list_spotify_accounts"));
+    } catch (error) {
+      console.error("Failed to load Spotify accounts:", error);
+    }
+  };
+
+  // Make another linked account the active one
+  const switchAccount = async (accountId: string) => {
+    try {
+      setAccounts(await invoke<SpotifyAccount[]>("switch_spotify_account", { accountId }));
+      clearLikedTracksCache();
+      await refreshAuthStatus();
+    } catch (error) {
+      console.error("Failed to switch Spotify account:", error);
+      toast.error(`Failed to switch Spotify account: ${errorMessage(error)}`);
+    }
+  };
+
+  // Unlink a single account
+  const removeAccount = async (account: SpotifyAccount) => {
+    try {
+      await invoke("logout", { service: "spotify", accountId: account.id });
+      await loadAccounts();
+      clearLikedTracksCache();
+      await refreshAuthStatus();
+      toast.success(`Removed ${account.display_name || "Spotify account"}`);
+    } catch (error) {
+      console.error("Failed to remove Spotify account:", error);
+      toast.error(`Failed to remove Spotify account: ${errorMessage(error)}`);
+    }
+  };
+
+  // Initiate Spotify authentication. Adding an account makes Spotify ask
+  // which account to use instead of reusing the browser's session.
+  const authenticateWithSpotify = async (addAccount = false) => {
     setIsAuthenticating(true);
 
     try {
@@ -129,31 +158,9 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
         throw new Error("Spotify Client ID is required");
       }
 
-      // Start the callback server
-      await invoke("start_spotify_callback_server");
-      
-      // Use localhost:5174/callback as the redirect URI
-      const redirectUri = "http://localhost:5174/callback";
-
-      // Generate PKCE code verifier and challenge
-      const verifier = generateCodeVerifier(128);
-      const challenge = await generateCodeChallenge(verifier);
-      
-      // Store the verifier in Tauri's store for later use
-      const store = await load("spotify-auth.json");
-      await store.set("code_verifier", verifier);
-      await store.save();
-      
-      // Build the authorization URL
-      const params = new URLSearchParams();
-      params.append("client_id", spotifySettings.client_id);
-      params.append("response_type", "code");
-      params.append("redirect_uri", redirectUri);
-      params.append("scope", "user-read-private user-read-email playlist-read-private user-library-read");
-      params.append("code_challenge_method", "S256");
-      params.append("code_challenge", challenge);
-      
-      const authUrl = `https://accounts.spotify.com/authorize?${params.toString()}`;
+      // The backend generates the PKCE verifier, starts the callback server
+      // and builds the authorization URL
+      const authUrl = await invoke<string>("begin_spotify_auth", { addAccount });
       
       // Open the authorization URL in the system browser
       await openUrl(authUrl);
@@ -163,7 +170,12 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
       
     } catch (error) {
       console.error("Spotify authentication failed:", error);
-      toast.error(`Spotify authentication failed: ${error instanceof Error ? error.message : String(error)}`);
+      if (isSoulsharkError(error) && error.code === "port_in_use") {
+        // Another program holds the redirect URI's port
+        toast.error(`Spotify login port in use: ${error.message}`);
+      } else {
+        toast.error(`Spotify authentication failed: ${errorMessage(error)}`);
+      }
       
       // Stop the server if authentication fails
       try {
@@ -177,44 +189,17 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
     }
   };
 
-  // Generate a code verifier for PKCE
-  const generateCodeVerifier = (length: number) => {
-    let text = '';
-    const possible = 'ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789';
-    for (let i = 0; i < length; i++) {
-      text += possible.charAt(Math.floor(Math.random() * possible.length));
-    }
-    return text;
-  };
-
-  // Generate a code challenge from the verifier
-  const generateCodeChallenge = async (codeVerifier: string) => {
-    const data = new TextEncoder().encode(codeVerifier);
-    const digest = await window.crypto.subtle.digest('SHA-256', data);
-    return btoa(String.fromCharCode.apply(null, [...new Uint8Array(digest)]))
-      .replace(/\+/g, '-')
-      .replace(/\//g, '_')
-      .replace(/=+$/, '');
-  };
-
   // Logout from Spotify
   const logoutFromSpotify = async () => {
     if (!credentials) return;
 
     try {
-      // Stop the callback server
-      await invoke("stop_spotify_callback_server");
-      
-      await invoke("save_credentials", {
-        credentials: {
-          ...credentials,
-          spotify_access_token: null,
-          spotify_refresh_token: null,
-          spotify_token_expires_at: null,
-        },
-      });
+      // Clears the tokens and stops the callback server
+      await invoke("logout", { service: "spotify" });
       
       // Refresh the global auth status once after logout
+      setAccounts([]);
+      clearLikedTracksCache();
       await refreshAuthStatus();
       
       toast.success("Disconnected from Spotify");
@@ -232,6 +217,37 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
             <div className="flex items-center gap-2 font-medium">
               <span className="text-muted-foreground">✓</span> Connected to Spotify
             </div>
+            {accounts.length > 0 && (
+              <ul className="space-y-2">
+                {accounts.map((account) => (
+                  <li key={account.id} className="flex items-center justify-between gap-2">
+                    <span className={account.active ? "font-medium" : "text-muted-foreground"}>
+                      {account.display_name || "Spotify account"}
+                      {account.active && " (active)"}
+                    </span>
+                    <div className="flex gap-2">
+                      {!account.active && (
+                        <Button size="sm" variant="outline" onClick={() => switchAccount(account.id)}>
+                          Switch
+                        </Button>
+                      )}
+                      {accounts.length > 1 && (
+                        <Button size="sm" variant="ghost" onClick={() => removeAccount(account)}>
+                          Remove
+                        </Button>
+                      )}
+                    </div>
+                  </li>
+                ))}
+              </ul>
+            )}
+            <Button
+              variant="outline"
+              onClick={() => authenticateWithSpotify(true)}
+              disabled={isAuthenticating}
+            >
+              {isAuthenticating ? "Connecting..." : "Add another account"}
+            </Button>
             <Button 
               variant="secondary" 
               onClick={logoutFromSpotify}
@@ -245,7 +261,7 @@ export default function SpotifyAuth({ onAuthSuccess }: SpotifyAuthProps) {
               <span className="text-muted-foreground">⚠</span> Not connected to Spotify
             </div>
             <Button 
-              onClick={authenticateWithSpotify} 
+              onClick={() => authenticateWithSpotify()} 
               disabled={isAuthenticating || !spotifySettings.client_id}
               className="mb-2"
             >
@@ -16,6 +16,7 @@ import {
 } from "@/lib/spotify";
 import { Music } from "lucide-react";
 import { useSpotify } from "@/lib/SpotifyContext";
+import { errorMessage } from "@/lib/errors";
 
 // Types
 interface SpotifyTrack {
@@ -125,7 +126,7 @@ export default function SpotifyContent({
       }
     } catch (error) {
       console.error("Failed to load content:", error);
-      setError(`Failed to load content: ${error instanceof Error ? error.message : String(error)}`);
+      setError(`Failed to load content: ${errorMessage(error)}`);
       
       // If we get an authentication error, refresh auth status
       if (error instanceof Error && error.message.includes("authentication")) {
@@ -390,7 +391,7 @@ export default function SpotifyContent({
       }
     } catch (error) {
       console.error("Failed to save tracks:", error);
-      setError(`Failed to save tracks: ${error instanceof Error ? error.message : String(error)}`);
+      setError(`Failed to save tracks: ${errorMessage(error)}`);
     } finally {
       setIsDownloading(false);
     }
@@ -7,14 +7,6 @@ import { initializeSpotify, resetSpotifyApi } from './spotify';
 import { invoke } from '@tauri-apps/api/core';
 
 // Types
-interface Credentials {
-  soulseek_password: string | null;
-  spotify_client_secret: string | null;
-  spotify_access_token: string | null;
-  spotify_refresh_token: string | null;
-  spotify_token_expires_at: number | null;
-}
-
 
 
 interface SpotifyContextType {
@@ -178,18 +170,8 @@ export const SpotifyProvider: React.FC<{ children: ReactNode }> = ({ children })
   // Logout from Spotify
   const logout = async () => {
     try {
-      // Get current credentials
-      const credentials = await invoke<Credentials>('get_credentials');
-      
-      // Clear Spotify tokens
-      await invoke('save_credentials', {
-        credentials: {
-          ...credentials,
-          spotify_access_token: null,
-          spotify_refresh_token: null,
-          spotify_token_expires_at: null,
-        },
-      });
+      // Clear Spotify tokens and pending login state in the backend
+      await invoke('logout', { service: 'spotify' });
       
       // Reset the API instance
       resetSpotifyApi();
@@ -0,0 +1,32 @@
+// Error returned by the backend commands
+export interface SoulsharkError {
+  code:
+    | "auth"
+    | "locked"
+    | "settings"
+    | "sidecar"
+    | "network"
+    | "port_in_use"
+    | "filesystem"
+    | "not_found"
+    | "validation"
+    | "internal";
+  message: string;
+}
+
+export function isSoulsharkError(error: unknown): error is SoulsharkError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "code" in error &&
+    "message" in error &&
+    typeof (error as SoulsharkError).message === "string"
+  );
+}
+
+// Human readable message for anything a command or event can fail with
+export function errorMessage(error: unknown): string {
+  if (isSoulsharkError(error)) return error.message;
+  if (error instanceof Error) return error.message;
+  return String(error);
+}
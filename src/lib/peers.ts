@@ -0,0 +1,31 @@
+import { invoke } from "@tauri-apps/api/core";
+
+export type PeerList = "blacklist" | "whitelist";
+
+// A Soulseek peer's track record, as recorded from finished downloads
+export interface PeerEntry {
+  username: string;
+  successes: number;
+  failures: number;
+  // Transfers from album folders with mixed formats or missing tracks
+  verification_failures: number;
+  average_speed_kbps: number | null;
+  timed_transfers: number;
+  last_transfer_at: number | null;
+  list: PeerList | null;
+  failure_rate: number;
+  // Whether downloads skip this peer, listed or blacklisted automatically
+  banned: boolean;
+}
+
+export function listPeers(): Promise<PeerEntry[]> {
+  return invoke<PeerEntry[]>("list_peers");
+}
+
+export function setPeerList(username: string, list: PeerList | null): Promise<PeerEntry> {
+  return invoke<PeerEntry>("set_peer_list", { username, list });
+}
+
+export function removePeer(username: string): Promise<void> {
+  return invoke("remove_peer", { username });
+}
@@ -287,33 +287,17 @@ export async function downloadPlaylist(playlistId: string) {
 }
 
 /**
- * Download a Spotify album using sldl
+ * Download a Spotify album in album mode, from a single folder holding the
+ * whole tracklist
  * @param albumId Spotify album ID
+ * @param allowFallback Download the tracks one by one if no folder has the
+ * whole album, defaults to the setting
  * @returns Promise that resolves when the download is started
  */
-export async function downloadAlbum(albumId: string) {
-  const albumUrl = `https://open.spotify.com/album/${albumId}`;
-  
+export async function downloadAlbum(albumId: string, allowFallback?: boolean) {
   try {
-    // Get album details to pass as metadata
-    const spotify = await initializeSpotify();
-    if (!spotify) {
-      throw new Error("Not authenticated with Spotify");
-    }
-    
-    // Get album details
-    const album = await spotify.albums.get(albumId);
-    
-    // Get artist name
-    const artistName = album.artists.length > 0 ? album.artists[0].name : undefined;
-    
-    // Call the Tauri command to execute sldl with album metadata
-    await invoke("execute_sldl", {
-      query: albumUrl,
-      options: {},
-      title: album.name,
-      artist: artistName
-    });
+    // The backend looks the album up to check the tracklist
+    await invoke("download_album", { albumId, allowFallback });
     
     return true;
   } catch (error) {
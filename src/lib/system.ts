@@ -0,0 +1,23 @@
+import { invoke } from "@tauri-apps/api/core";
+
+// The sldl binary in use, as detected by the backend
+export interface SldlInfo {
+  // Configured path, or null for the bundled sldl
+  path: string | null;
+  version: string | null;
+  compatibility: "supported" | "unsupported" | "unknown";
+  // Supported version range, e.g. ">= 2.3.0, < 3.0.0"
+  supported: string;
+  error: string | null;
+}
+
+export interface SystemInfo {
+  app_version: string;
+  os: string;
+  arch: string;
+  sldl: SldlInfo;
+}
+
+export function getSystemInfo(): Promise<SystemInfo> {
+  return invoke<SystemInfo>("get_system_info");
+}